@@ -1,20 +1,61 @@
+use crate::archive;
+use crate::config::config_file_locations;
+use crate::config::discover_config_files;
+use crate::config::Action;
+use crate::config::ActionCondition;
 use crate::config::Config;
+use crate::config::HiddenMode;
+use crate::config::LayoutConfig;
 use crate::config::Mode;
+use crate::config::PasteConflict;
+use crate::config::SizeUnitBase;
+use crate::config::Style;
+use crate::config::TemplateSelection;
+use crate::fs_ops::{available_path_except, relative_path, FsOperation};
+use crate::git_status;
 use crate::input::Key;
-use anyhow::{bail, Result};
+use crate::worker;
+use anyhow::{anyhow, bail, Result};
+use chrono::format::{Item, StrftimeItems};
 use chrono::{DateTime, Local};
 use indexmap::set::IndexSet;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 use std::env;
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::io;
-use std::path::PathBuf;
+#[cfg(unix)]
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use users::os::unix::UserExt;
 
 pub const TEMPLATE_TABLE_ROW: &str = "TEMPLATE_TABLE_ROW";
+pub const TEMPLATE_TABLE_TITLE: &str = "TEMPLATE_TABLE_TITLE";
+pub const TEMPLATE_TAB: &str = "TEMPLATE_TAB";
+pub const TEMPLATE_ACTIVE_TAB: &str = "TEMPLATE_ACTIVE_TAB";
 pub const UNSUPPORTED_STR: &str = "???";
+pub const DEFAULT_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+pub const DEFAULT_LAYOUT_RATIO: u16 = 70;
+pub const MIN_LAYOUT_RATIO: u16 = 10;
+pub const MAX_LAYOUT_RATIO: u16 = 90;
+pub const MAX_UNDO_STACK_SIZE: usize = 100;
+pub const DEFAULT_MAX_RECENT_DIRECTORIES: usize = 20;
+pub const DEFAULT_MAX_LOGS: usize = 1000;
+pub const DEFAULT_FOCUS_CHANGE_DEBOUNCE: u64 = 100;
+pub const DEFAULT_KEY_SEQUENCE_TIMEOUT: u64 = 1000;
+pub const DEFAULT_TYPE_AHEAD_TIMEOUT: u64 = 1000;
+pub const MAX_DIRECTORY_SCORES: usize = 1000;
+pub const DEFAULT_TREE_MAX_DEPTH: usize = 3;
+pub const DEFAULT_TREE_MAX_NODES: usize = 10000;
+pub const DEFAULT_RECURSIVE_SEARCH_MAX_DEPTH: usize = 10;
+pub const MAX_FUNCTION_DEPTH: usize = 16;
 pub const UPGRADE_GUIDE_LINK: &str = "https://github.com/sayanarijit/xplr/wiki/Upgrade-Guide";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -124,10 +165,18 @@ impl ResolvedNode {
     }
 }
 
-#[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
+/// A node is identified by its absolute path, so that the copies read at
+/// different times, e.g. before and after being accessed, are the same node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Node {
     pub parent: String,
     pub relative_path: String,
+
+    /// How deep the node is listed below the explored directory, which is
+    /// more than zero only for the nodes listed in a subtree.
+    #[serde(default)]
+    pub depth: usize,
+
     pub absolute_path: String,
     pub extension: String,
     pub is_dir: bool,
@@ -135,12 +184,44 @@ pub struct Node {
     pub is_symlink: bool,
     pub is_broken: bool,
     pub is_readonly: bool,
+    pub is_executable: bool,
+    pub permissions: u32,
+    pub uid: u32,
+    pub gid: u32,
     pub mime_essence: String,
     pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
     pub canonical: Option<ResolvedNode>,
     pub symlink: Option<ResolvedNode>,
 }
 
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.absolute_path == other.absolute_path
+    }
+}
+
+impl Eq for Node {}
+
+impl Hash for Node {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.absolute_path.hash(state);
+    }
+}
+
+/// The permission bits, the owner and the group, which only unix has.
+#[cfg(unix)]
+fn mode_and_owner(meta: &fs::Metadata) -> (u32, u32, u32) {
+    (meta.permissions().mode() & 0o7777, meta.uid(), meta.gid())
+}
+
+#[cfg(not(unix))]
+fn mode_and_owner(_: &fs::Metadata) -> (u32, u32, u32) {
+    (0, 0, 0)
+}
+
 impl Node {
     pub fn new(parent: String, relative_path: String) -> Self {
         let absolute_path = PathBuf::from(&parent)
@@ -160,18 +241,43 @@ impl Node {
             .map(|p| (false, Some(ResolvedNode::from(p))))
             .unwrap_or_else(|_| (true, None));
 
-        let (is_symlink, is_dir, is_file, is_readonly, size) = path
+        let (
+            is_symlink,
+            is_dir,
+            is_file,
+            is_readonly,
+            permissions,
+            uid,
+            gid,
+            size,
+            modified,
+            created,
+            accessed,
+        ) = path
             .symlink_metadata()
             .map(|m| {
+                let (permissions, uid, gid) = mode_and_owner(&m);
                 (
                     m.file_type().is_symlink(),
                     m.is_dir(),
                     m.is_file(),
                     m.permissions().readonly(),
+                    permissions,
+                    uid,
+                    gid,
                     m.len(),
+                    m.modified().ok(),
+                    m.created().ok(),
+                    m.accessed().ok(),
                 )
             })
-            .unwrap_or((false, false, false, false, 0));
+            .unwrap_or((false, false, false, false, 0, 0, 0, 0, None, None, None));
+
+        // Symlinks are executable if their targets are.
+        let is_executable = path
+            .metadata()
+            .map(|m| m.is_file() && mode_and_owner(&m).0 & 0o111 != 0)
+            .unwrap_or(false);
 
         let mime_essence = mime_guess::from_path(&path)
             .first()
@@ -181,6 +287,7 @@ impl Node {
         Self {
             parent,
             relative_path,
+            depth: 0,
             absolute_path,
             extension,
             is_dir,
@@ -188,8 +295,15 @@ impl Node {
             is_symlink,
             is_broken,
             is_readonly,
+            is_executable,
+            permissions,
+            uid,
+            gid,
             mime_essence,
             size,
+            modified,
+            created,
+            accessed,
             canonical: maybe_canonical_meta.clone(),
             symlink: if is_symlink {
                 maybe_canonical_meta
@@ -200,6 +314,63 @@ impl Node {
     }
 }
 
+impl Node {
+    /// A node for an entry of a zip or tar archive, which is read-only.
+    pub fn in_archive(
+        parent: String,
+        relative_path: String,
+        is_dir: bool,
+        size: u64,
+        modified: Option<SystemTime>,
+    ) -> Self {
+        let path = PathBuf::from(&parent).join(&relative_path);
+
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mime_essence = mime_guess::from_path(&path)
+            .first()
+            .map(|m| m.essence_str().to_string())
+            .unwrap_or_default();
+
+        let resolved = ResolvedNode {
+            absolute_path: path.to_string_lossy().to_string(),
+            extension: extension.clone(),
+            is_dir,
+            is_file: !is_dir,
+            is_readonly: true,
+            mime_essence: mime_essence.clone(),
+            size,
+        };
+
+        Self {
+            parent,
+            relative_path,
+            depth: 0,
+            absolute_path: resolved.absolute_path.clone(),
+            extension,
+            is_dir,
+            is_file: !is_dir,
+            is_symlink: false,
+            is_broken: false,
+            is_readonly: true,
+            is_executable: false,
+            permissions: if is_dir { 0o555 } else { 0o444 },
+            uid: 0,
+            gid: 0,
+            mime_essence,
+            size,
+            modified,
+            created: None,
+            accessed: None,
+            canonical: Some(resolved),
+            symlink: None,
+        }
+    }
+}
+
 impl Ord for Node {
     fn cmp(&self, other: &Self) -> Ordering {
         // Notice that the we flip the ordering on costs.
@@ -219,32 +390,248 @@ pub struct DirectoryBuffer {
     pub parent: String,
     pub nodes: Vec<Node>,
     pub total: usize,
+    pub unfiltered_total: usize,
     pub focus: usize,
+
+    /// The first row scrolled to by `CenterFocus`, `FocusToTop` or
+    /// `FocusToBottom`, kept while the focus stays in view.
+    #[serde(default)]
+    pub offset: Option<usize>,
 }
 
 impl DirectoryBuffer {
-    pub fn new(parent: String, nodes: Vec<Node>, focus: usize) -> Self {
+    pub fn new(parent: String, nodes: Vec<Node>, unfiltered_total: usize, focus: usize) -> Self {
         let total = nodes.len();
         Self {
             parent,
             nodes,
             total,
+            unfiltered_total,
             focus,
+            offset: None,
+        }
+    }
+
+    /// The first node shown in a table of the given height. Without an
+    /// offset keeping the focus in view, the nodes are paged.
+    pub fn first_visible(&self, height: usize) -> usize {
+        let height = height.max(1);
+        match self.offset {
+            Some(offset) if self.focus >= offset && self.focus < offset + height => offset,
+            _ => height * (self.focus / height),
         }
     }
 
+    /// The number of nodes excluded by the filters.
+    pub fn hidden(&self) -> usize {
+        self.unfiltered_total.max(self.total) - self.total
+    }
+
     pub fn focused_node(&self) -> Option<&Node> {
         self.nodes.get(self.focus)
     }
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DirectorySize {
+    pub modified: Option<SystemTime>,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [Self; 3] = [Self::Md5, Self::Sha1, Self::Sha256];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|a| a.name().eq_ignore_ascii_case(name))
+            .copied()
+    }
+}
+
+/// The digest of a file, valid until the file gets modified.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FileHash {
+    pub algorithm: HashAlgorithm,
+    pub modified: Option<SystemTime>,
+    pub digest: String,
+}
+
+/// The git status of a node, from the least to the most notable.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum GitStatus {
+    Ignored,
+    Untracked,
+    Added,
+    Modified,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TransferKind {
+    Copy,
+    Move,
+}
+
+/// The yanked or cut paths, waiting to be pasted.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PasteRegister {
+    pub kind: TransferKind,
+    pub paths: Vec<String>,
+}
+
+/// Paths to copy or move on a worker thread, as `(from, to)` pairs.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Transfer {
+    pub kind: TransferKind,
+    pub items: Vec<(String, String)>,
+
+    /// The destinations to replace if they exist.
+    #[serde(default)]
+    pub replace: HashSet<String>,
+}
+
+/// A copy or move waiting for the paths that already exist at the
+/// destination to be dealt with, one by one.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PendingTransfer {
+    pub transfer: Transfer,
+    pub conflicts: VecDeque<(String, String)>,
+}
+
+impl PendingTransfer {
+    /// Whether the destination exists, or is taken by another path of the
+    /// transfer.
+    fn is_taken(&self, to: &str) -> bool {
+        Path::new(to).symlink_metadata().is_ok() || self.transfer.items.iter().any(|(_, t)| t == to)
+    }
+
+    /// The name to copy or move to in place of the destination, avoiding the
+    /// ones taken by the other paths of the transfer.
+    fn renamed(&self, to: &str) -> String {
+        let reserved = self
+            .transfer
+            .items
+            .iter()
+            .map(|(_, t)| PathBuf::from(t))
+            .collect();
+        available_path_except(Path::new(to), &reserved)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn resolve(&mut self, (from, to): (String, String), conflict: PasteConflict) {
+        match conflict {
+            PasteConflict::Ask => self.conflicts.push_back((from, to)),
+            PasteConflict::Skip => {}
+            PasteConflict::Rename => {
+                let to = self.renamed(&to);
+                self.transfer.items.push((from, to));
+            }
+            PasteConflict::Overwrite => {
+                self.transfer.replace.insert(to.clone());
+                self.transfer.items.push((from, to));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TransferProgress {
+    pub kind: TransferKind,
+    pub current: String,
+    pub done: u64,
+    pub total: u64,
+}
+
+impl TransferProgress {
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            (self.done as f64 / self.total as f64).min(1.0)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TransferredItem {
+    pub kind: TransferKind,
+    pub from: String,
+    pub to: String,
+    pub error: Option<String>,
+}
+
+/// The captured output of a command the focused file was piped through.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CommandOutput {
+    pub command: String,
+    pub path: String,
+    pub success: bool,
+    pub text: String,
+    pub truncated: bool,
+}
+
+/// A search for the nodes matching the pattern in the subdirectories of the
+/// root. The id tells it apart from the earlier searches.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RecursiveSearch {
+    pub id: usize,
+    pub pattern: String,
+    pub root: String,
+    pub max_depth: usize,
+}
+
+/// The paths, relative to the root, found by a recursive search since the
+/// last results.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub id: usize,
+    pub pattern: String,
+    pub paths: Vec<String>,
+    pub done: bool,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum InternalMsg {
     AddDirectory(String, DirectoryBuffer),
+    SetPreview(DirectoryBuffer),
+    SetDirectorySize(String, DirectorySize),
+    SetFileHash(String, FileHash),
+    SetGitStatuses(String, HashMap<String, GitStatus>),
+    SetTransferProgress(TransferProgress),
+    FinishTransferItem(TransferredItem),
+    FinishTransfer,
+    SetCommandOutput(CommandOutput),
+    AddSearchResults(SearchResults),
+    PlayNextMacroStep,
+    AdvanceSpinner,
+    FinishMacroStep(usize),
+    FinishDirectoryChangeHook,
+    RunFocusChangeHook(usize),
+    FinishFocusChangeHook,
+    ResolveKeySequence(usize),
     HandleKey(Key),
+    HandlePaste(String),
+    Tick,
+    HandleMouse(MouseInput),
 }
 
-#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub enum NodeSorter {
     ByRelativePath,
@@ -275,6 +662,65 @@ pub enum NodeSorter {
     BySymlinkIsReadonly,
     BySymlinkMimeEssence,
     BySymlinkSize,
+
+    BySelectedFirst,
+
+    /// Sort by the first capture group of the sorter's `regex` in the
+    /// relative path, numerically if both are digits. The nodes that don't
+    /// match come last.
+    ByRegexCapture,
+}
+
+/// A regex compiled once, and serialized as its pattern.
+#[derive(Debug, Clone)]
+pub struct CompiledRegex(Regex);
+
+impl CompiledRegex {
+    pub fn new(pattern: &str) -> Result<Self> {
+        Ok(Self(Regex::new(pattern)?))
+    }
+
+    /// The first capture group in the text.
+    fn capture<'a>(&self, text: &'a str) -> Option<&'a str> {
+        self.0
+            .captures(text)
+            .and_then(|c| c.get(1))
+            .map(|m| m.as_str())
+    }
+}
+
+impl PartialEq for CompiledRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for CompiledRegex {}
+
+impl Serialize for CompiledRegex {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for CompiledRegex {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern)
+            .map(Self)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Compare the captures numerically if they're both digits, else lexically.
+fn compare_captures(a: &str, b: &str) -> Ordering {
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|c| c.is_ascii_digit());
+    if is_number(a) && is_number(b) {
+        let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+        a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+    } else {
+        a.cmp(b)
+    }
 }
 
 #[derive(Debug, Clone, Eq, Serialize, Deserialize)]
@@ -283,6 +729,10 @@ pub struct NodeSorterApplicable {
     pub sorter: NodeSorter,
     #[serde(default)]
     pub reverse: bool,
+
+    /// The regex used by `ByRegexCapture`.
+    #[serde(default)]
+    pub regex: Option<CompiledRegex>,
 }
 
 impl PartialEq for NodeSorterApplicable {
@@ -303,7 +753,7 @@ impl NodeSorterApplicable {
         self
     }
 
-    fn apply(&self, a: &Node, b: &Node) -> Ordering {
+    fn apply(&self, a: &Node, b: &Node, selection: &HashSet<String>) -> Ordering {
         match (self.sorter, self.reverse) {
             (NodeSorter::ByRelativePath, false) => {
                 natord::compare(&a.relative_path, &b.relative_path)
@@ -565,11 +1015,33 @@ impl NodeSorterApplicable {
                 .as_ref()
                 .map(|s| &s.size)
                 .cmp(&a.symlink.as_ref().map(|s| &s.size)),
+
+            (NodeSorter::BySelectedFirst, false) => selection
+                .contains(&b.absolute_path)
+                .cmp(&selection.contains(&a.absolute_path)),
+
+            (NodeSorter::BySelectedFirst, true) => selection
+                .contains(&a.absolute_path)
+                .cmp(&selection.contains(&b.absolute_path)),
+
+            (NodeSorter::ByRegexCapture, reverse) => {
+                let regex = self.regex.as_ref();
+                match (
+                    regex.and_then(|r| r.capture(&a.relative_path)),
+                    regex.and_then(|r| r.capture(&b.relative_path)),
+                ) {
+                    (Some(a), Some(b)) if reverse => compare_captures(b, a),
+                    (Some(a), Some(b)) => compare_captures(a, b),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub enum NodeFilter {
     RelativePathIs,
@@ -619,51 +1091,72 @@ pub enum NodeFilter {
 
     IAbsolutePathDoesEndWith,
     IAbsolutePathDoesNotEndWith,
+
+    IsSelected,
+
+    IsSymlink,
+    IsNotSymlink,
+
+    IsBrokenSymlink,
+    IsNotBrokenSymlink,
+
+    IsExecutable,
+    IsNotExecutable,
+
+    IsDirectory,
+    IsNotDirectory,
+
+    /// The names missing from the directory given as the input, as listed
+    /// when the filter is added. Nothing matches if it can't be listed.
+    NameNotIn,
 }
 
 impl NodeFilter {
-    fn apply(&self, node: &Node, input: &str) -> bool {
+    /// Relative path filters match against `relative_path`, which is the
+    /// node's own relative path unless a filter is anchored elsewhere.
+    fn apply(
+        &self,
+        node: &Node,
+        relative_path: &str,
+        input: &str,
+        selection: &HashSet<String>,
+        reference_names: &HashMap<String, Option<HashSet<String>>>,
+    ) -> bool {
         match self {
-            Self::RelativePathIs => node.relative_path.eq(input),
-            Self::IRelativePathIs => node.relative_path.eq_ignore_ascii_case(input),
+            Self::RelativePathIs => relative_path.eq(input),
+            Self::IRelativePathIs => relative_path.eq_ignore_ascii_case(input),
 
-            Self::RelativePathIsNot => !node.relative_path.eq(input),
-            Self::IRelativePathIsNot => !node.relative_path.eq_ignore_ascii_case(input),
+            Self::RelativePathIsNot => !relative_path.eq(input),
+            Self::IRelativePathIsNot => !relative_path.eq_ignore_ascii_case(input),
 
-            Self::RelativePathDoesStartWith => node.relative_path.starts_with(input),
-            Self::IRelativePathDoesStartWith => node
-                .relative_path
+            Self::RelativePathDoesStartWith => relative_path.starts_with(input),
+            Self::IRelativePathDoesStartWith => relative_path
                 .to_lowercase()
                 .starts_with(&input.to_lowercase()),
 
-            Self::RelativePathDoesNotStartWith => !node.relative_path.starts_with(input),
+            Self::RelativePathDoesNotStartWith => !relative_path.starts_with(input),
 
-            Self::IRelativePathDoesNotStartWith => !node
-                .relative_path
+            Self::IRelativePathDoesNotStartWith => !relative_path
                 .to_lowercase()
                 .starts_with(&input.to_lowercase()),
 
-            Self::RelativePathDoesContain => node.relative_path.contains(input),
-            Self::IRelativePathDoesContain => node
-                .relative_path
-                .to_lowercase()
-                .contains(&input.to_lowercase()),
+            Self::RelativePathDoesContain => relative_path.contains(input),
+            Self::IRelativePathDoesContain => {
+                relative_path.to_lowercase().contains(&input.to_lowercase())
+            }
 
-            Self::RelativePathDoesNotContain => !node.relative_path.contains(input),
-            Self::IRelativePathDoesNotContain => !node
-                .relative_path
-                .to_lowercase()
-                .contains(&input.to_lowercase()),
+            Self::RelativePathDoesNotContain => !relative_path.contains(input),
+            Self::IRelativePathDoesNotContain => {
+                !relative_path.to_lowercase().contains(&input.to_lowercase())
+            }
 
-            Self::RelativePathDoesEndWith => node.relative_path.ends_with(input),
-            Self::IRelativePathDoesEndWith => node
-                .relative_path
+            Self::RelativePathDoesEndWith => relative_path.ends_with(input),
+            Self::IRelativePathDoesEndWith => relative_path
                 .to_lowercase()
                 .ends_with(&input.to_lowercase()),
 
-            Self::RelativePathDoesNotEndWith => !node.relative_path.ends_with(input),
-            Self::IRelativePathDoesNotEndWith => !node
-                .relative_path
+            Self::RelativePathDoesNotEndWith => !relative_path.ends_with(input),
+            Self::IRelativePathDoesNotEndWith => !relative_path
                 .to_lowercase()
                 .ends_with(&input.to_lowercase()),
 
@@ -708,6 +1201,28 @@ impl NodeFilter {
                 .absolute_path
                 .to_lowercase()
                 .ends_with(&input.to_lowercase()),
+
+            Self::IsSelected => selection.contains(&node.absolute_path),
+
+            Self::IsSymlink => node.is_symlink,
+            Self::IsNotSymlink => !node.is_symlink,
+
+            Self::IsBrokenSymlink => node.is_symlink && node.is_broken,
+            Self::IsNotBrokenSymlink => !(node.is_symlink && node.is_broken),
+
+            Self::IsExecutable => node.is_executable,
+            Self::IsNotExecutable => !node.is_executable,
+
+            Self::IsDirectory => node.is_dir,
+            Self::IsNotDirectory => !node.is_dir,
+
+            Self::NameNotIn => match reference_names.get(input) {
+                Some(Some(names)) => Path::new(&node.absolute_path)
+                    .file_name()
+                    .map(|n| !names.contains(n.to_string_lossy().as_ref()))
+                    .unwrap_or(false),
+                _ => false,
+            },
         }
     }
 }
@@ -716,36 +1231,204 @@ impl NodeFilter {
 #[serde(deny_unknown_fields)]
 pub struct NodeFilterApplicable {
     pub filter: NodeFilter,
+
+    #[serde(default)]
     pub input: String,
+
+    /// The absolute path of the directory the relative path filters match
+    /// against, instead of the explored directory. Nodes outside of it are
+    /// matched by their absolute path.
+    #[serde(default)]
+    pub base: Option<String>,
 }
 
 impl NodeFilterApplicable {
     pub fn new(filter: NodeFilter, input: String) -> Self {
-        Self { filter, input }
+        Self {
+            filter,
+            input,
+            base: None,
+        }
+    }
+
+    /// Anchor the relative path filters to the given directory.
+    pub fn with_base(mut self, base: String) -> Self {
+        self.base = Some(base);
+        self
     }
 
-    fn apply(&self, node: &Node) -> bool {
-        self.filter.apply(node, &self.input)
+    fn apply(
+        &self,
+        node: &Node,
+        selection: &HashSet<String>,
+        reference_names: &HashMap<String, Option<HashSet<String>>>,
+    ) -> bool {
+        match self.base.as_ref() {
+            Some(base) => {
+                let path = Path::new(&node.absolute_path);
+                let relative_path = path.strip_prefix(base).unwrap_or(path).to_string_lossy();
+                self.filter.apply(
+                    node,
+                    &relative_path,
+                    &self.input,
+                    selection,
+                    reference_names,
+                )
+            }
+            None => self.filter.apply(
+                node,
+                &node.relative_path,
+                &self.input,
+                selection,
+                reference_names,
+            ),
+        }
     }
 }
 
+/// The nodes `FocusNextMatching` and `FocusPreviousMatching` look for, and
+/// whether they wrap around the ends of the listing or stop there.
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FocusMatching {
+    pub filter: NodeFilter,
+
+    #[serde(default)]
+    pub input: String,
+
+    #[serde(default)]
+    pub wrap: bool,
+}
+
+/// The prefix typed ahead, and when its last key was typed.
+#[derive(Debug, Clone)]
+struct TypeAhead {
+    prefix: String,
+    last_key: Instant,
+}
+
+/// How deep to descend into the subdirectories when listing the present
+/// working directory as a tree.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TreeListing {
+    pub max_depth: usize,
+    pub max_nodes: usize,
+}
+
+/// The sorters and filters of the explorer, as read by `SetExplorerConfig`
+/// and written by `DumpExplorerConfig`.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExplorerView {
+    #[serde(default)]
+    pub sorters: IndexSet<NodeSorterApplicable>,
+
+    #[serde(default)]
+    pub filters: IndexSet<NodeFilterApplicable>,
+
+    #[serde(default)]
+    pub reversed: bool,
+}
+
 #[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ExplorerConfig {
     filters: IndexSet<NodeFilterApplicable>,
     sorters: IndexSet<NodeSorterApplicable>,
+
+    #[serde(default)]
+    tree: Option<TreeListing>,
+
+    /// The term the relative paths must contain, ignoring case, on top of
+    /// the filters.
+    #[serde(default)]
+    quick_filter: Option<String>,
+
+    /// Whether the order given by the sorters is reversed as a whole.
+    #[serde(default)]
+    reversed: bool,
+
+    /// Whether the filters are kept but not applied.
+    #[serde(default)]
+    filters_suspended: bool,
+
+    /// Whether the subtree of each directory, by path, is expanded or
+    /// collapsed by hand. The others follow the tree listing.
+    #[serde(default)]
+    expansions: BTreeMap<String, bool>,
+
+    #[serde(skip)]
+    selection: HashSet<String>,
+
+    /// The names listed in the reference directories of the `NameNotIn`
+    /// filters, or none if they couldn't be listed.
+    #[serde(skip)]
+    reference_names: HashMap<String, Option<HashSet<String>>>,
 }
 
 impl ExplorerConfig {
+    /// Take a snapshot of the selected paths for the filters and sorters to consult
+    /// while exploring.
+    pub fn with_selection(mut self, selection: &IndexSet<Node>) -> Self {
+        self.selection = selection.iter().map(|n| n.absolute_path.clone()).collect();
+        self
+    }
+
     pub fn filter(&self, node: &Node) -> bool {
-        self.filters.iter().all(|f| f.apply(node))
+        if self.filters_suspended {
+            return true;
+        };
+
+        self.filters
+            .iter()
+            .all(|f| f.apply(node, &self.selection, &self.reference_names))
+            && self
+                .quick_filter
+                .as_ref()
+                .map(|q| {
+                    node.relative_path
+                        .to_lowercase()
+                        .contains(&q.to_lowercase())
+                })
+                .unwrap_or(true)
     }
 
     pub fn sort(&self, a: &Node, b: &Node) -> Ordering {
         let mut ord = Ordering::Equal;
         for s in self.sorters.iter() {
-            ord = ord.then(s.apply(a, b));
+            ord = ord.then(s.apply(a, b, &self.selection));
+        }
+
+        if self.reversed {
+            ord.reverse()
+        } else {
+            ord
         }
-        ord
+    }
+
+    /// The sorters and filters, to be restored with `set_view`.
+    pub fn view(&self) -> ExplorerView {
+        ExplorerView {
+            sorters: self.sorters.clone(),
+            filters: self.filters.clone(),
+            reversed: self.reversed,
+        }
+    }
+
+    /// Replace the sorters and filters.
+    pub fn set_view(&mut self, view: ExplorerView) {
+        self.sorters = view.sorters;
+        self.filters = view.filters;
+        self.reversed = view.reversed;
+    }
+
+    /// Whether the order given by the sorters is reversed as a whole.
+    pub fn is_reversed(&self) -> bool {
+        self.reversed
+    }
+
+    /// Whether the filters are kept but not applied.
+    pub fn are_filters_suspended(&self) -> bool {
+        self.filters_suspended
     }
 
     /// Get a reference to the explorer config's filters.
@@ -757,6 +1440,42 @@ impl ExplorerConfig {
     pub fn sorters(&self) -> &IndexSet<NodeSorterApplicable> {
         &self.sorters
     }
+
+    /// Get a reference to the explorer config's quick filter.
+    pub fn quick_filter(&self) -> Option<&String> {
+        self.quick_filter.as_ref()
+    }
+
+    /// Get the explorer config's tree listing, if the tree view is enabled.
+    pub fn tree(&self) -> Option<TreeListing> {
+        self.tree
+    }
+
+    /// The most nodes listed, the expanded subtrees included.
+    pub fn max_nodes(&self) -> usize {
+        self.tree
+            .map(|t| t.max_nodes)
+            .unwrap_or(DEFAULT_TREE_MAX_NODES)
+    }
+
+    /// The same config, listing a single level.
+    pub fn flat(mut self) -> Self {
+        self.tree = None;
+        self.expansions.clear();
+        self
+    }
+
+    /// Whether to list the subtree of the directory node.
+    pub fn is_expanded(&self, node: &Node) -> bool {
+        self.expansions
+            .get(&node.absolute_path)
+            .cloned()
+            .unwrap_or_else(|| {
+                self.tree
+                    .map(|t| node.depth + 1 < t.max_depth)
+                    .unwrap_or(false)
+            })
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -775,8 +1494,113 @@ pub enum ExternalMsg {
     /// Clears the screen.
     ClearScreen,
 
-    /// Focus next node.
-    FocusNext,
+    /// Do nothing. Binding a key to it swallows the key, where a binding
+    /// without messages would fall through to the default action.
+    ///
+    /// Example: `NoOp`
+    NoOp,
+
+    /// Toggle between listing only the present working directory, and
+    /// listing it as a tree of its subdirectories up to the configured
+    /// `tree_view.max_depth`. Requires `Explore` to take effect.
+    ///
+    /// Example: `ToggleTreeView`
+    ToggleTreeView,
+
+    /// Toggle a pane next to the table listing the nodes of the focused
+    /// directory, one level ahead, following the focus.
+    ///
+    /// Example: `TogglePreviewPaneShowsChildren`
+    TogglePreviewPaneShowsChildren,
+
+    /// Toggle showing the help menu of the current mode over the whole
+    /// screen. The arrow and page keys scroll it, and any other key closes it.
+    ///
+    /// Example: `ToggleFullHelp`
+    ToggleFullHelp,
+
+    /// Show the error logs of the session over the whole screen. The arrow
+    /// and page keys scroll them, and any other key closes them.
+    ///
+    /// Example: `ShowErrors`
+    ShowErrors,
+
+    /// Start typing ahead, focusing the first node whose name starts with
+    /// the keys typed so far. Nothing is filtered out. The prefix starts over
+    /// after the `type_ahead_timeout` config, `backspace` shortens it, and
+    /// `esc`, `enter` or any other key stops typing ahead.
+    ///
+    /// Example: `EnterTypeAheadMode`
+    EnterTypeAheadMode,
+
+    /// Expand the subtree of the focused directory, listing its nodes below
+    /// it, or collapse it if it's already expanded. The state is kept for
+    /// the path, so it survives navigating away and back.
+    ///
+    /// Example: `ToggleExpand`
+    ToggleExpand,
+
+    /// Show only the nodes with relative paths containing the given term,
+    /// ignoring case, on top of the other filters. Unlike the filters, it
+    /// doesn't need a mode to edit, and it's cleared on its own with
+    /// `ClearQuickFilter`. Triggers `Explore`.
+    ///
+    /// Example: `QuickFilter: .rs`
+    QuickFilter(String),
+
+    /// Clear the quick filter, keeping the other filters. Triggers `Explore`.
+    ClearQuickFilter,
+
+    /// Reverse the order given by the sorters as a whole, or restore it if
+    /// it's already reversed. Unlike `ReverseNodeSorters`, this doesn't touch
+    /// the sorters. Requires `Explore` to take effect.
+    ///
+    /// Example: `ReverseListing`
+    ReverseListing,
+
+    /// Open a new tab in the present working directory, with an empty selection.
+    NewTab,
+
+    /// Close the current tab, unless it's the last one.
+    CloseTab,
+
+    /// Switch to the next tab.
+    NextTab,
+
+    /// Switch to the previous tab.
+    PrevTab,
+
+    /// Set the width of the primary pane in percentage of the screen width.
+    /// The value is clamped to keep both the panes visible.
+    ///
+    /// Example: `SetLayoutRatio: 60`
+    SetLayoutRatio(u16),
+
+    /// Grow the primary pane by the given percentage.
+    ///
+    /// Example: `GrowLayoutRatio: 5`
+    GrowLayoutRatio(u16),
+
+    /// Shrink the primary pane by the given percentage.
+    ///
+    /// Example: `ShrinkLayoutRatio: 5`
+    ShrinkLayoutRatio(u16),
+
+    /// Switch to the layout with the given name from the `layouts` config.
+    /// If there's no such layout, an error is logged and the current one is
+    /// kept.
+    ///
+    /// Example: `SwitchLayout: listing_only`
+    SwitchLayout(String),
+
+    /// Switch to the next layout from the `layouts` config, in the order of
+    /// their names.
+    ///
+    /// Example: `CycleLayout`
+    CycleLayout,
+
+    /// Focus next node.
+    FocusNext,
 
     /// Focus on the `n`th node relative to the current focus where `n` is a given value.
     ///
@@ -799,12 +1623,41 @@ pub enum ExternalMsg {
     /// the input buffer.
     FocusPreviousByRelativeIndexFromInput,
 
+    /// Focus on the next node passing the filter, leaving the listing as it
+    /// is. With `wrap`, the search continues from the top.
+    ///
+    /// Example: `FocusNextMatching: {filter: RelativePathDoesEndWith, input: .rs, wrap: true}`
+    FocusNextMatching(FocusMatching),
+
+    /// Like `FocusNextMatching`, but searches upwards.
+    ///
+    /// Example: `FocusPreviousMatching: {filter: RelativePathDoesEndWith, input: .rs}`
+    FocusPreviousMatching(FocusMatching),
+
     /// Focus on the first node.
     FocusFirst,
 
     /// Focus on the last node.
     FocusLast,
 
+    /// Scroll the listing to show the focused node in the middle, as far as
+    /// the ends of the listing allow. The focus doesn't move.
+    ///
+    /// Example: `CenterFocus`
+    CenterFocus,
+
+    /// Scroll the listing to show the focused node at the top, as far as the
+    /// end of the listing allows. The focus doesn't move.
+    ///
+    /// Example: `FocusToTop`
+    FocusToTop,
+
+    /// Scroll the listing to show the focused node at the bottom. The focus
+    /// doesn't move.
+    ///
+    /// Example: `FocusToBottom`
+    FocusToBottom,
+
     /// Focus on the given path.
     ///
     /// Example: `FocusPath: /tmp`
@@ -819,6 +1672,8 @@ pub enum ExternalMsg {
     FocusByIndex(usize),
 
     /// Focus on the absolute `n`th node where `n` is read from the input buffer.
+    /// If `n` starts with `+` or `-`, the focus moves relative to the current
+    /// position instead.
     FocusByIndexFromInput,
 
     /// Focus on the file by name from the present working directory.
@@ -831,21 +1686,162 @@ pub enum ExternalMsg {
     /// Example: `ChangeDirectory: /tmp`
     ChangeDirectory(String),
 
+    /// Change the present working directory to the `$PWD` environment
+    /// variable xplr was started with, i.e. the directory of the parent
+    /// terminal session. If `$PWD` is not set, the process's current working
+    /// directory is used instead. If the directory doesn't exist, an error is
+    /// logged and the present working directory stays the same.
+    SyncPwdFromEnv,
+
+    /// Change the present working directory to the configured
+    /// `home_directory`, or to `$HOME` if it's not set. If the directory
+    /// doesn't exist, an error is logged and the present working directory
+    /// stays the same.
+    GoHome,
+
+    /// Change the present working directory to the Nth component of the
+    /// breadcrumb, counting from the root as 0.
+    ///
+    /// Example: `JumpToBreadcrumb: 1`
+    JumpToBreadcrumb(usize),
+
     /// Enter into the currently focused path if it's a directory.
     Enter,
 
+    /// Enter into the focused directory, or open the focused file like
+    /// `OpenWithTypeCommand`. Symlinks are judged by what they point to, and
+    /// broken ones are left alone.
+    ///
+    /// Example: `EnterOrOpen`
+    EnterOrOpen,
+
     /// Go back to the parent directory.
     Back,
 
+    /// Go to the next directory in the parent directory, in the current
+    /// order, staying put at the last one.
+    NextSiblingDirectory,
+
+    /// Go to the previous directory in the parent directory, in the current
+    /// order, staying put at the first one.
+    PreviousSiblingDirectory,
+
     /// Go to the last path visited.
     LastVisitedPath,
 
     /// Go to the next path visited.
     NextVisitedPath,
 
+    /// Go to the given path. The path can be a glob pattern, and may contain
+    /// `~` and environment variables. If the pattern matches multiple paths,
+    /// they will be presented in a picker.
+    ///
+    /// Example: `GoTo: ~/proj/*/src`
+    GoTo(String),
+
+    /// Go to the path or glob pattern read from the input buffer.
+    GoToFromInput,
+
+    /// Show the recently visited directories in a picker, most recent first.
+    ShowRecentDirectories,
+
+    /// Go to the given recently visited directory. If it no longer exists,
+    /// it's removed from the recent directories instead.
+    ///
+    /// Example: `GoToRecentDirectory: /tmp`
+    GoToRecentDirectory(String),
+
+    /// Go to the directory with the highest score, based on how often and
+    /// how recently it was visited, that contains the words of the given
+    /// fragment in its path.
+    ///
+    /// Example: `JumpToFrequent: proj src`
+    JumpToFrequent(String),
+
+    /// Like `JumpToFrequent`, but reads the fragment from the input buffer.
+    JumpToFrequentFromInput,
+
+    /// Add the current working directory to the favorites.
+    AddToFavorites,
+
+    /// Remove the current working directory from the favorites.
+    RemoveFromFavorites,
+
+    /// Show the favorite directories in a picker.
+    ShowFavorites,
+
+    /// Show the mounted devices in a picker, read from `/proc/mounts`.
+    ///
+    /// Example: `ShowMountPoints`
+    ShowMountPoints,
+
+    /// Focus the next item in the picker.
+    FocusNextPickerItem,
+
+    /// Focus the previous item in the picker.
+    FocusPreviousPickerItem,
+
+    /// Pick the focused item in the picker, running its messages.
+    PickFocusedPickerItem,
+
+    /// Close the picker without picking anything.
+    ClosePicker,
+
+    /// Show the key bindings of all the modes in a picker, filtered by the
+    /// input buffer, and run the messages of the picked one.
+    OpenCommandPalette,
+
+    /// Show only the picker items fuzzy matching the given query.
+    ///
+    /// Example: `FilterPicker: sel`
+    FilterPicker(String),
+
+    /// Like `FilterPicker`, but reads the query from the input buffer.
+    FilterPickerFromInput,
+
+    /// Ask for confirmation showing the given prompt, then run the `on_yes`
+    /// or the `on_no` messages. The mode is restored before running them.
+    ///
+    /// Example:
+    ///
+    /// ```yaml
+    /// Confirm:
+    ///   prompt: Delete the selected files?
+    ///   on_yes:
+    ///     - Delete
+    ///   on_no: []
+    /// ```
+    Confirm {
+        prompt: String,
+        on_yes: Vec<ExternalMsg>,
+        #[serde(default)]
+        on_no: Vec<ExternalMsg>,
+    },
+
+    /// Answer yes to the pending confirmation. The messages run as if sent
+    /// by the key answering it.
+    ConfirmYes,
+
+    /// Answer no to the pending confirmation.
+    ConfirmNo,
+
     /// Follow the symlink under focus to its actual location.
     FollowSymlink,
 
+    /// Recursively compute the size of the focused directory in the background.
+    /// The result is cached until the directory gets modified, and can be
+    /// displayed using the `computedSize` table placeholder.
+    /// Symlinks are not followed.
+    ComputeDirectorySize,
+
+    /// Hash the focused file in the background with the given algorithm,
+    /// one of `md5`, `sha1` and `sha256`, and log the digest. The result is
+    /// cached until the file gets modified, and can be displayed using the
+    /// `hash` table placeholder.
+    ///
+    /// Example: `ComputeHash: sha256`
+    ComputeHash(String),
+
     /// Append/buffer the given string into the input buffer.
     ///
     /// Example: `BufferInput: foo`
@@ -855,6 +1851,18 @@ pub enum ExternalMsg {
     /// input buffer.
     BufferInputFromKey,
 
+    /// Append/buffer the text last pasted into the terminal into the input
+    /// buffer.
+    BufferInputFromPaste,
+
+    /// Set the input buffer to the focused node's name.
+    SetInputBufferToFocusedName,
+
+    /// Set the input buffer to the focused node's name with the stem selected,
+    /// so that typing replaces the stem and keeps the extension. Names without
+    /// an extension are not selected.
+    SetInputBufferToFocusedNameSelectingStem,
+
     /// Set/rewrite the input buffer with the given string.
     /// When the input buffer is not-null (even if empty string)
     /// it will show in the UI.
@@ -868,6 +1876,30 @@ pub enum ExternalMsg {
     /// Remove input buffer's last word.
     RemoveInputBufferLastWord,
 
+    /// Remove the character after the input cursor.
+    RemoveInputBufferNextCharacter,
+
+    /// Remove everything from the input cursor to the end of the input buffer.
+    RemoveInputBufferToEnd,
+
+    /// Move the input cursor one character to the left.
+    InputCursorLeft,
+
+    /// Move the input cursor one character to the right.
+    InputCursorRight,
+
+    /// Move the input cursor to the start of the previous word.
+    InputCursorWordLeft,
+
+    /// Move the input cursor to the end of the next word.
+    InputCursorWordRight,
+
+    /// Move the input cursor to the start of the input buffer.
+    InputCursorHome,
+
+    /// Move the input cursor to the end of the input buffer.
+    InputCursorEnd,
+
     /// Reset the input buffer back to null. It will not show in the UI.
     ResetInputBuffer,
 
@@ -904,6 +1936,37 @@ pub enum ExternalMsg {
     /// Example: `BashExecSilently: "tput bell"`
     BashExecSilently(String),
 
+    /// Open the focused node with the `open_command` of its node type, or
+    /// with the OS default opener if none is configured.
+    OpenWithTypeCommand,
+
+    /// Run the given bash command in the background with the focused file as
+    /// its stdin and `$1`, and show the output. If the command fails, its
+    /// stderr is shown instead.
+    ///
+    /// Example: `PipeFocusedThrough: "jq ."`
+    PipeFocusedThrough(String),
+
+    /// Close the output of the command the focused file was piped through.
+    ClearCommandOutput,
+
+    /// Run the given bash command in the background once per selected node,
+    /// one after another, with the path of the node as `$1`. The failures and
+    /// a summary are logged.
+    ///
+    /// Example: `ForEachSelection: 'gzip "$1"'`
+    ForEachSelection(String),
+
+    /// Diff the two selected text files, with the `diff_command` config if
+    /// set, and show the result in place of the preview.
+    ///
+    /// Example: `DiffSelection`
+    DiffSelection,
+
+    /// Suspend the UI and open an interactive `$SHELL`, or `/bin/sh` if it's
+    /// not set, in the present working directory. Explores again on exit.
+    OpenShell,
+
     /// Select the focused node.
     Select,
 
@@ -915,6 +1978,12 @@ pub enum ExternalMsg {
     /// Example: `SelectPath: "/tmp"`
     SelectPath(String),
 
+    /// Select all the visible nodes passing the filter, leaving the listing
+    /// as it is.
+    ///
+    /// Example: `SelectByFilter: {filter: IRelativePathDoesEndWith, input: .tmp}`
+    SelectByFilter(NodeFilterApplicable),
+
     /// Unselect the focused node.
     UnSelect,
 
@@ -926,6 +1995,11 @@ pub enum ExternalMsg {
     /// Example: `UnSelectPath: "/tmp"`
     UnSelectPath(String),
 
+    /// Unselect all the visible nodes passing the filter.
+    ///
+    /// Example: `UnSelectByFilter: {filter: IRelativePathDoesEndWith, input: .tmp}`
+    UnSelectByFilter(NodeFilterApplicable),
+
     /// Toggle selection on the focused node.
     ToggleSelection,
 
@@ -940,6 +2014,24 @@ pub enum ExternalMsg {
     /// Clear the selection.
     ClearSelection,
 
+    /// Add the paths of the selected nodes, or of the focused node if nothing
+    /// is selected, to the given register. The registers are sets of paths
+    /// kept apart from the selection, and left alone when it changes.
+    ///
+    /// Example: `AddToRegister: a`
+    AddToRegister(char),
+
+    /// Empty the given register.
+    ///
+    /// Example: `ClearRegister: a`
+    ClearRegister(char),
+
+    /// Add the nodes at the paths of the given register, as they are now, to
+    /// the selection.
+    ///
+    /// Example: `SelectRegister: a`
+    SelectRegister(char),
+
     /// Add a filter to exclude nodes while exploring directories.
     ///
     /// Example: `AddNodeFilter: {filter: RelativePathDoesStartWith, input: foo}`
@@ -955,6 +2047,14 @@ pub enum ExternalMsg {
     /// Example: `ToggleNodeFilter: {filter: RelativePathDoesStartWith, input: foo}`
     ToggleNodeFilter(NodeFilterApplicable),
 
+    /// Cycle through showing the hidden nodes, hiding them, and showing only
+    /// the hidden nodes.
+    CycleHiddenMode,
+
+    /// Show only the selected nodes by adding the `IsSelected` filter.
+    /// Remove it with `RemoveNodeFilter: {filter: IsSelected}` to restore the listing.
+    FilterToSelection,
+
     /// Add a node filter reading the input from the buffer.
     ///
     /// Example: `AddNodeFilterFromInput: RelativePathDoesStartWith`
@@ -974,11 +2074,28 @@ pub enum ExternalMsg {
     /// Clear all the node filters.
     ClearNodeFilters,
 
+    /// Suspend the node filters, including the quick filter, so that every
+    /// node gets listed, or apply them again if they're suspended.
+    ///
+    /// Example: `ToggleFiltersEnabled`
+    ToggleFiltersEnabled,
+
     /// Add a sorter to sort nodes while exploring directories.
     ///
     /// Example: `AddNodeSorter: {sorter: ByRelativePath, reverse: false}`
     AddNodeSorter(NodeSorterApplicable),
 
+    /// Sort by the sorter of the given table column, counting from zero, as
+    /// configured in `table.col_sorters`, replacing the sorters of the other
+    /// columns. If it's already sorting, reverse it instead.
+    ///
+    /// Example: `SortByColumn: 2`
+    SortByColumn(usize),
+
+    /// Like `SortByColumn`, but the column is the number key pressed,
+    /// counting from one.
+    SortByColumnFromKey,
+
     /// Remove an existing sorter.
     ///
     /// Example: `RemoveNodeSorter: ByRelativePath`
@@ -1006,6 +2123,196 @@ pub enum ExternalMsg {
     /// Clear all the node sorters.
     ClearNodeSorters,
 
+    /// Rename the focused node. The new name is relative to the present
+    /// working directory.
+    ///
+    /// Example: `Rename: foo.txt`
+    Rename(String),
+
+    /// Rename the focused node using the input buffer as the new name.
+    RenameFromInput,
+
+    /// Change the permissions of the selected nodes, or the focused node if
+    /// nothing is selected. Takes an octal mode, or a comma separated list of
+    /// symbolic modes like `chmod` does.
+    ///
+    /// Example: `SetPermissions: "u+x,go-w"`
+    SetPermissions(String),
+
+    /// Like `SetPermissions`, but reads the mode from the input buffer.
+    SetPermissionsFromInput,
+
+    /// Change the owner of the selected nodes, or the focused node if nothing
+    /// is selected. Takes `user`, `user:group` or `:group`, as names or ids.
+    ///
+    /// Example: `SetOwner: "www-data:www-data"`
+    SetOwner(String),
+
+    /// Like `SetOwner`, but reads the owner from the input buffer.
+    SetOwnerFromInput,
+
+    /// Stage the changes of the selected nodes, or the focused node if
+    /// nothing is selected, in their git repository.
+    ///
+    /// Example: `GitStage`
+    GitStage,
+
+    /// Unstage the changes of the selected nodes, or the focused node if
+    /// nothing is selected, in their git repository.
+    ///
+    /// Example: `GitUnstage`
+    GitUnstage,
+
+    /// Create a directory, along with the missing parent directories, like
+    /// `mkdir -p`. An existing directory is only focused.
+    ///
+    /// Example: `CreateDirectory: foo/bar`
+    CreateDirectory(String),
+
+    /// Create a directory using the input buffer as the path.
+    CreateDirectoryFromInput,
+
+    /// Create a file. If a templates directory is configured, the file may be
+    /// seeded from a template, depending on the template selection setting.
+    ///
+    /// Example: `CreateFile: foo.py`
+    CreateFile(String),
+
+    /// Create a file using the input buffer as the path.
+    CreateFileFromInput,
+
+    /// Create a file with the contents of the given template, or an empty file
+    /// if no template is given.
+    ///
+    /// Example: `CreateFileFromTemplate: {path: foo.py, template: /path/to/template.py}`
+    CreateFileFromTemplate(FileFromTemplate),
+
+    /// Create a symlink pointing to the target. Both paths are relative to
+    /// the present working directory. An existing path is replaced only with
+    /// `force`, and never if it's a directory. A replaced file is trashed.
+    ///
+    /// Example: `CreateSymlink: {target: /etc/hosts, link: hosts, relative: true}`
+    CreateSymlink(Symlink),
+
+    /// Point the existing symlink at the link path to the target. `force`
+    /// isn't needed.
+    ///
+    /// Example: `RetargetSymlink: {target: ../v2, link: current}`
+    RetargetSymlink(Symlink),
+
+    /// Copy the selected nodes to the present working directory. Large
+    /// copies run in the background showing the progress. What to do with
+    /// the existing paths depends on the `paste_conflict` config.
+    CopySelectionHere,
+
+    /// Like `CopySelectionHere`, but deals with the existing paths the given
+    /// way.
+    ///
+    /// Example: `CopySelectionHereOnConflict: Skip`
+    CopySelectionHereOnConflict(PasteConflict),
+
+    /// Extract the selected nodes inside zip or tar archives into the present
+    /// working directory or, when browsing an archive, next to it. Existing
+    /// files are left alone.
+    ///
+    /// Example: `ExtractSelection`
+    ExtractSelection,
+
+    /// Move the selected nodes to the present working directory. Moving
+    /// across filesystems runs in the background showing the progress. What
+    /// to do with the existing paths depends on the `paste_conflict` config.
+    MoveSelectionHere,
+
+    /// Like `MoveSelectionHere`, but deals with the existing paths the given
+    /// way.
+    ///
+    /// Example: `MoveSelectionHereOnConflict: Overwrite`
+    MoveSelectionHereOnConflict(PasteConflict),
+
+    /// Remember the selected paths to be copied by `PasteIntoCurrent`, and
+    /// clear the selection.
+    YankSelection,
+
+    /// Remember the selected paths to be moved by `PasteIntoCurrent`, and
+    /// clear the selection.
+    CutSelection,
+
+    /// Copy the yanked, or move the cut paths into the present working
+    /// directory. The yanked paths can be pasted again, the cut ones are
+    /// forgotten once moved, or pasted where they already are. What to do
+    /// with the existing paths depends on the `paste_conflict` config.
+    PasteIntoCurrent,
+
+    /// Like `PasteIntoCurrent`, but pastes onto the existing paths with new
+    /// names without asking.
+    PasteIntoCurrentWithNewNames,
+
+    /// Like `PasteIntoCurrent`, but deals with the existing paths the given
+    /// way.
+    ///
+    /// Example: `PasteIntoCurrentOnConflict: Skip`
+    PasteIntoCurrentOnConflict(PasteConflict),
+
+    /// Deal with the existing path the copy or move is asking about the
+    /// given way, and ask about the next one. `Ask` puts it off until the
+    /// others are dealt with.
+    ///
+    /// Example: `ResolveTransferConflict: Rename`
+    ResolveTransferConflict(PasteConflict),
+
+    /// Cancel the running copy or move, removing the partially copied path.
+    CancelTransfer,
+
+    /// Search the subdirectories of the present working directory, up to
+    /// the configured `recursive_search_max_depth`, for the nodes with names
+    /// containing the given pattern, ignoring case. The results show up in a
+    /// picker as they're found. Picking one goes to its directory and focuses
+    /// it.
+    ///
+    /// Example: `SearchRecursively: readme`
+    SearchRecursively(String),
+
+    /// Like `SearchRecursively`, but reads the pattern from the input buffer.
+    SearchRecursivelyFromInput,
+
+    /// Stop the running recursive search.
+    CancelSearch,
+
+    /// Move the selected nodes, or the focused node if nothing is selected,
+    /// to the trash.
+    Trash,
+
+    /// Permanently delete the selected nodes, or the focused node if nothing
+    /// is selected. This can't be undone.
+    Delete,
+
+    /// Undo the last rename, move or trash operation.
+    Undo,
+
+    /// Redo the last undone operation.
+    Redo,
+
+    /// Toggle the read-only mode. The current mode's key bindings are derived
+    /// again from the configuration. It's only turned off by a key, or by the
+    /// confirmation answered with one, not through the pipes.
+    ToggleReadOnly,
+
+    /// Toggle whether the sorters and filters carry into the directories
+    /// changed to, or get reset to the initial ones on each change.
+    ///
+    /// Example: `TogglePersistExplorerConfig`
+    TogglePersistExplorerConfig,
+
+    /// Toggle drawing the panes without borders and titles.
+    ///
+    /// Example: `ToggleCompact`
+    ToggleCompact,
+
+    /// Toggle showing the sizes in multiples of 1000 (`kB`) or 1024 (`KiB`).
+    ///
+    /// Example: `ToggleSizeUnitBase`
+    ToggleSizeUnitBase,
+
     /// Log information message.
     ///
     /// Example: `LogInfo: launching satellite`
@@ -1016,17 +2323,59 @@ pub enum ExternalMsg {
     /// Example: `LogSuccess: satellite reached destination`.
     LogSuccess(String),
 
+    /// Log a warning message.
+    ///
+    /// Example: `LogWarning: satellite is flying dangerously close to the sun`
+    LogWarning(String),
+
     /// Log an error message.
     ///
     /// Example: `LogError: satellite crashed`
     LogError(String),
 
+    /// Clear the logs.
+    ///
+    /// Example: `ClearLogs`
+    ClearLogs,
+
+    /// Log an information message in the given style instead of the style
+    /// of the info level.
+    ///
+    /// Example: `LogStyled: {text: satellite is in orbit, style: {fg: Cyan}}`
+    LogStyled(StyledLog),
+
     /// Quit with returncode zero (success).
     Quit,
 
     /// Print selected paths if it's not empty, else, print the focused node's path.
     PrintResultAndQuit,
 
+    /// Print the focused node's path and quit.
+    PrintFocusedPathAndQuit,
+
+    /// Print the selected paths, each followed by the configured
+    /// `selection_separator`, and quit.
+    PrintSelectionAndQuit,
+
+    /// Set the title of the terminal window or tab. The original title is
+    /// restored on quit, if the terminal supports it.
+    ///
+    /// Example: `SetTerminalTitle: xplr`
+    SetTerminalTitle(String),
+
+    /// Copy the focused node's file name, without the directory, to the
+    /// system clipboard.
+    ///
+    /// Example: `CopyNameToClipboard`
+    CopyNameToClipboard,
+
+    /// Copy the file names of the selected nodes, joined by the configured
+    /// `selection_separator`, to the system clipboard. Copies the focused
+    /// node's name if nothing is selected.
+    ///
+    /// Example: `CopySelectedNamesToClipboard`
+    CopySelectedNamesToClipboard,
+
     /// Print the state of application in YAML format. Helpful for debugging or generating
     /// the default configuration file.
     PrintAppStateAndQuit,
@@ -1034,15 +2383,173 @@ pub enum ExternalMsg {
     /// Write the application state to a file, without quitting. Also helpful for debugging.
     Debug(String),
 
+    /// Write the effective config, i.e. the default config with all the
+    /// config files layered on top, to a file as YAML.
+    ///
+    /// Example: `DumpEffectiveConfig: /tmp/xplr.yml`
+    DumpEffectiveConfig(String),
+
+    /// Load the config files again, as found at startup, and switch to the
+    /// `default` mode. If they can't be loaded, the error is logged and the
+    /// current config is kept.
+    ///
+    /// Example: `ReloadConfig`
+    ReloadConfig,
+
+    /// Open the config file taking precedence in `$EDITOR`, or the first of
+    /// the usual locations if there's none yet, and `ReloadConfig` once the
+    /// editor exits.
+    ///
+    /// Example: `EditConfig`
+    EditConfig,
+
+    /// Replace the sorters and filters with the ones in the given YAML or
+    /// JSON, as written by `DumpExplorerConfig`.
+    ///
+    /// Example: `SetExplorerConfig: '{sorters: [{sorter: BySize, reverse: true}], filters: []}'`
+    SetExplorerConfig(String),
+
+    /// Write the sorters and filters to the given file as YAML.
+    ///
+    /// Example: `DumpExplorerConfig: /tmp/view.yml`
+    DumpExplorerConfig(String),
+
+    /// Save the sorters, the filters and the layout ratio under the given
+    /// name, replacing the view of the same name, if any. The views aren't
+    /// tied to the session: they're kept in `xplr/views.yml` under the data
+    /// directory, shared by all the sessions.
+    ///
+    /// Example: `SaveView: by size`
+    SaveView(String),
+
+    /// Restore the view saved under the given name, in this session or an
+    /// earlier one.
+    ///
+    /// Example: `LoadView: by size`
+    LoadView(String),
+
+    /// Show the saved views in a picker.
+    ShowViews,
+
+    /// Write the filtered and sorted nodes of the present working directory
+    /// to the given file as a JSON array. Each node is marked as selected or
+    /// not.
+    ///
+    /// Example: `ExportListingJson: /tmp/listing.json`
+    ExportListingJson(String),
+
+    /// Start recording the actions into the given macro register.
+    ///
+    /// Example: `StartRecordingMacro: a`
+    StartRecordingMacro(char),
+
+    /// Start recording the actions into the macro register named by the key
+    /// pressed.
+    StartRecordingMacroFromKey,
+
+    /// Stop recording the macro.
+    StopRecordingMacro,
+
+    /// Play the macro recorded in the given register. The playback stops at
+    /// the first message that logs an error.
+    ///
+    /// Example: `PlayMacro: a`
+    PlayMacro(char),
+
+    /// Play the macro recorded in the register named by the key pressed.
+    PlayMacroFromKey,
+
+    /// Run the messages of the last action again, except the actions made up
+    /// of only the messages listed in `repeat_ignored_messages`.
+    RepeatLastAction,
+
+    /// Run the messages of the function with the given name, from the
+    /// `functions` config, in place of this message. Functions may call
+    /// functions, up to 16 calls deep. The calls are expanded before anything
+    /// runs, so a function can't call itself, not even through others.
+    ///
+    /// Example: `CallFunction: open-in-split`
+    CallFunction(String),
+
     /// Terminate the application with a non-zero return code.
     Terminate,
 }
 
 impl ExternalMsg {
+    /// Whether the message records or plays macros, or switches to the modes
+    /// doing so. These are never recorded.
+    pub fn is_macro_control(&self) -> bool {
+        match self {
+            Self::StartRecordingMacro(_)
+            | Self::StartRecordingMacroFromKey
+            | Self::StopRecordingMacro
+            | Self::PlayMacro(_)
+            | Self::PlayMacroFromKey => true,
+            Self::SwitchMode(mode) => matches!(
+                mode.as_str(),
+                "record macro" | "record_macro" | "play macro" | "play_macro"
+            ),
+            _ => false,
+        }
+    }
+
+    /// The name of the message, e.g. `SwitchMode` for `SwitchMode: default`.
+    pub fn name(&self) -> String {
+        match serde_yaml::to_value(self) {
+            Ok(serde_yaml::Value::String(name)) => name,
+            Ok(serde_yaml::Value::Mapping(map)) => map
+                .iter()
+                .next()
+                .and_then(|(k, _)| k.as_str().map(|k| k.to_string()))
+                .unwrap_or_default(),
+            _ => Default::default(),
+        }
+    }
+
     pub fn is_read_only(&self) -> bool {
         !matches!(
             self,
-            Self::Call(_) | Self::CallSilently(_) | Self::BashExec(_) | Self::BashExecSilently(_)
+            Self::Call(_)
+                | Self::CallSilently(_)
+                | Self::EditConfig
+                | Self::BashExec(_)
+                | Self::BashExecSilently(_)
+                | Self::OpenShell
+                | Self::PipeFocusedThrough(_)
+                | Self::ForEachSelection(_)
+                | Self::DiffSelection
+                | Self::DumpEffectiveConfig(_)
+                | Self::OpenWithTypeCommand
+                | Self::ExportListingJson(_)
+                | Self::DumpExplorerConfig(_)
+                | Self::Rename(_)
+                | Self::RenameFromInput
+                | Self::SetPermissions(_)
+                | Self::SetPermissionsFromInput
+                | Self::SetOwner(_)
+                | Self::SetOwnerFromInput
+                | Self::GitStage
+                | Self::GitUnstage
+                | Self::CreateDirectory(_)
+                | Self::CreateDirectoryFromInput
+                | Self::CreateFile(_)
+                | Self::CreateFileFromInput
+                | Self::CreateFileFromTemplate(_)
+                | Self::CreateSymlink(_)
+                | Self::RetargetSymlink(_)
+                | Self::CopySelectionHere
+                | Self::CopySelectionHereOnConflict(_)
+                | Self::ExtractSelection
+                | Self::MoveSelectionHere
+                | Self::MoveSelectionHereOnConflict(_)
+                | Self::PasteIntoCurrent
+                | Self::PasteIntoCurrentWithNewNames
+                | Self::PasteIntoCurrentOnConflict(_)
+                | Self::ResolveTransferConflict(_)
+                | Self::Trash
+                | Self::Delete
+                | Self::Undo
+                | Self::Redo
         )
     }
 }
@@ -1062,6 +2569,31 @@ pub struct Command {
     pub args: Vec<String>,
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Symlink {
+    pub target: String,
+    pub link: String,
+
+    /// Store the target relative to the directory of the link, instead of as
+    /// an absolute path.
+    #[serde(default)]
+    pub relative: bool,
+
+    /// Replace the existing file or symlink at the link path.
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileFromTemplate {
+    pub path: String,
+
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum MsgOut {
     Explore,
@@ -1069,10 +2601,30 @@ pub enum MsgOut {
     ClearScreen,
     Quit,
     PrintResultAndQuit,
+    PrintFocusedPathAndQuit,
+    PrintSelectionAndQuit,
     PrintAppStateAndQuit,
+    CopyToClipboard(String),
+    SetTerminalTitle(String),
     Debug(String),
+    DumpEffectiveConfig(String),
+    ExportListingJson(String),
+    DumpExplorerConfig(String),
     Call(Command),
     CallSilently(Command),
+    OpenShell(Command),
+    CaptureCommandOutput(String, String),
+    ForEachSelection(String, Vec<String>),
+    DiffFiles(String, String, Option<String>),
+    DebounceFocusChange(usize, Duration),
+    DebounceKeySequence(usize, Duration),
+    ComputeDirectorySize(String),
+    ComputeHash(String, HashAlgorithm),
+    Transfer(Transfer),
+    Extract(Vec<String>, String),
+    CancelTransfer,
+    SearchRecursively(RecursiveSearch),
+    CancelSearch,
     Enque(Task),
 }
 
@@ -1093,14 +2645,37 @@ impl Task {
 pub enum LogLevel {
     Info,
     Success,
+    Warning,
     Error,
 }
 
+/// A node of the listing, as exported by `ExportListingJson`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListedNode<'a> {
+    #[serde(flatten)]
+    pub node: &'a Node,
+    pub is_selected: bool,
+}
+
+/// The text and the style of a line logged with `LogStyled`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StyledLog {
+    pub text: String,
+
+    #[serde(default)]
+    pub style: Style,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Log {
     pub level: LogLevel,
     pub message: String,
     pub created_at: DateTime<Local>,
+
+    /// Overrides the style of the level.
+    #[serde(default)]
+    pub style: Option<Style>,
 }
 
 impl Log {
@@ -1109,6 +2684,7 @@ impl Log {
             level,
             message,
             created_at: Local::now(),
+            style: None,
         }
     }
 }
@@ -1118,6 +2694,7 @@ impl std::fmt::Display for Log {
         let level_str = match self.level {
             LogLevel::Info => "INFO   ",
             LogLevel::Success => "SUCCESS",
+            LogLevel::Warning => "WARNING",
             LogLevel::Error => "ERROR  ",
         };
         write!(f, "[{}] {} {}", &self.created_at, level_str, &self.message)
@@ -1159,56 +2736,722 @@ impl History {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct App {
-    version: String,
-    config: Config,
-    pwd: String,
-    directory_buffers: HashMap<String, DirectoryBuffer>,
-    selection: IndexSet<Node>,
-    msg_out: VecDeque<MsgOut>,
-    mode: Mode,
-    input_buffer: Option<String>,
-    pid: u32,
-    session_path: String,
-    pipe: Pipe,
-    explorer_config: ExplorerConfig,
-    logs: Vec<Log>,
-    history: History,
+/// Where the state kept between sessions is read from and written to. The
+/// state without a file isn't persisted, as with the default.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StateFiles {
+    pub recent_directories: Option<PathBuf>,
+    pub directory_scores: Option<PathBuf>,
+    pub favorites: Option<PathBuf>,
+    pub views: Option<PathBuf>,
 }
 
-impl App {
-    pub fn create(pwd: PathBuf) -> Result<Self> {
-        let config_dir = dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("xplr");
+impl StateFiles {
+    /// The files in the platform's data directory, if there's one, but the
+    /// favorites, which are kept next to the config file.
+    pub fn discover() -> Self {
+        let data_dir = dirs::data_dir().map(|d| d.join("xplr"));
+        Self {
+            recent_directories: data_dir.as_ref().map(|d| d.join("recent_directories")),
+            directory_scores: data_dir.as_ref().map(|d| d.join("directory_scores")),
+            favorites: dirs::config_dir().map(|d| d.join("xplr").join("favorites")),
+            views: data_dir.as_ref().map(|d| d.join("views.yml")),
+        }
+    }
+}
+
+/// How often and how recently each directory was visited, for jumping to the
+/// directories by a fragment of their paths.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryScores {
+    /// The number of visits and the time of the last visit in seconds since
+    /// the epoch, by path.
+    visits: HashMap<String, (u64, u64)>,
+}
+
+impl DirectoryScores {
+    /// Read the scores from the given file, one `count<TAB>time<TAB>path` per
+    /// line. A missing or unreadable file gives no scores.
+    pub fn load(file: &Path) -> Self {
+        let visits = fs::read_to_string(file)
+            .map(|s| {
+                s.lines()
+                    .filter_map(|l| {
+                        let mut parts = l.splitn(3, '\t');
+                        let count = parts.next()?.parse().ok()?;
+                        let time = parts.next()?.parse().ok()?;
+                        let path = parts.next()?.to_string();
+                        Some((path, (count, time)))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { visits }
+    }
+
+    /// Frequency weighted by recency, like z does it.
+    fn score(count: u64, time: u64, now: u64) -> f64 {
+        let age = now.saturating_sub(time);
+        let weight = if age < 60 * 60 {
+            4.0
+        } else if age < 24 * 60 * 60 {
+            2.0
+        } else if age < 7 * 24 * 60 * 60 {
+            0.5
+        } else {
+            0.25
+        };
+        count as f64 * weight
+    }
+
+    pub fn visit(mut self, path: String, now: u64) -> Self {
+        let (count, time) = self.visits.entry(path).or_insert((0, now));
+        *count += 1;
+        *time = now;
+
+        if self.visits.len() > MAX_DIRECTORY_SCORES {
+            let lowest = self
+                .visits
+                .iter()
+                .min_by(|(_, (c1, t1)), (_, (c2, t2))| {
+                    Self::score(*c1, *t1, now)
+                        .partial_cmp(&Self::score(*c2, *t2, now))
+                        .unwrap_or(Ordering::Equal)
+                })
+                .map(|(p, _)| p.clone());
+
+            if let Some(path) = lowest {
+                self.visits.remove(&path);
+            };
+        };
+        self
+    }
+
+    pub fn remove(mut self, path: &str) -> Self {
+        self.visits.remove(path);
+        self
+    }
+
+    /// The paths containing the words of the fragment in order, ignoring
+    /// case, highest score first.
+    pub fn matches(&self, fragment: &str, now: u64) -> Vec<String> {
+        let words = fragment
+            .split_whitespace()
+            .map(|w| w.to_lowercase())
+            .collect::<Vec<String>>();
+
+        let mut matches = self
+            .visits
+            .iter()
+            .filter(|(path, _)| {
+                let path = path.to_lowercase();
+                let mut rest = path.as_str();
+                words.iter().all(|w| match rest.find(w.as_str()) {
+                    Some(i) => {
+                        rest = &rest[i + w.len()..];
+                        true
+                    }
+                    None => false,
+                })
+            })
+            .map(|(path, (count, time))| (Self::score(*count, *time, now), path.clone()))
+            .collect::<Vec<(f64, String)>>();
+
+        matches.sort_by(|(s1, p1), (s2, p2)| {
+            s2.partial_cmp(s1)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| p1.cmp(p2))
+        });
+        matches.into_iter().map(|(_, p)| p).collect()
+    }
+
+    pub fn to_file_content(&self) -> String {
+        self.visits
+            .iter()
+            .map(|(path, (count, time))| format!("{}\t{}\t{}\n", count, time, path))
+            .collect::<Vec<String>>()
+            .join("")
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// A mounted device, as listed in `/proc/mounts`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MountPoint {
+    pub device: String,
+    pub path: String,
+    pub fs_type: String,
+}
+
+/// Decode the octal escapes, like `\040` for a space, of a `/proc/mounts`
+/// field.
+fn unescape_mount_field(field: &str) -> String {
+    let mut bytes = vec![];
+    let mut rest = field.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let code = tail
+            .get(..3)
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .and_then(|d| u8::from_str_radix(d, 8).ok());
+
+        match code {
+            Some(c) if b == b'\\' => {
+                bytes.push(c);
+                rest = &tail[3..];
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+/// The mounted devices listed in the content of `/proc/mounts`, leaving out
+/// the pseudo filesystems like `proc` that aren't backed by a device.
+pub fn parse_mounts(content: &str) -> Vec<MountPoint> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = unescape_mount_field(fields.next()?);
+            let path = unescape_mount_field(fields.next()?);
+            let fs_type = fields.next()?.to_string();
+            Some(MountPoint {
+                device,
+                path,
+                fs_type,
+            })
+        })
+        .filter(|m| m.device.starts_with('/'))
+        .collect()
+}
+
+/// The directories picked by hand to go back to often.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Favorites {
+    paths: IndexSet<String>,
+}
+
+impl Favorites {
+    /// Read the directories from the given file, one per line. A missing or
+    /// unreadable file gives no favorites.
+    pub fn load(file: &Path) -> Self {
+        let paths = fs::read_to_string(file)
+            .map(|s| {
+                s.lines()
+                    .filter(|l| !l.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { paths }
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.paths.contains(path)
+    }
+
+    pub fn insert(mut self, path: String) -> Self {
+        self.paths.insert(path);
+        self
+    }
+
+    pub fn remove(mut self, path: &str) -> Self {
+        self.paths.shift_remove(path);
+        self
+    }
+
+    pub fn paths(&self) -> &IndexSet<String> {
+        &self.paths
+    }
+
+    pub fn to_file_content(&self) -> String {
+        self.paths
+            .iter()
+            .map(|p| format!("{}\n", p))
+            .collect::<Vec<String>>()
+            .join("")
+    }
+}
+
+/// A setup of the listing and the layout, saved with `SaveView`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct View {
+    pub explorer: ExplorerView,
+
+    #[serde(default)]
+    pub layout_ratio: Option<u16>,
+}
+
+/// The views saved by name.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Views {
+    views: BTreeMap<String, View>,
+}
+
+impl Views {
+    /// Read the views from the given YAML file. A missing, unreadable or
+    /// invalid file gives no views.
+    pub fn load(file: &Path) -> Self {
+        let views = fs::read_to_string(file)
+            .ok()
+            .and_then(|s| serde_yaml::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { views }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&View> {
+        self.views.get(name)
+    }
+
+    pub fn insert(mut self, name: String, view: View) -> Self {
+        self.views.insert(name, view);
+        self
+    }
+
+    pub fn names(&self) -> Vec<&String> {
+        self.views.keys().collect()
+    }
+
+    pub fn to_file_content(&self) -> String {
+        serde_yaml::to_string(&self.views).unwrap_or_default()
+    }
+}
+
+/// The directories visited recently, most recent first.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RecentDirectories {
+    paths: Vec<String>,
+}
+
+impl RecentDirectories {
+    /// Read the directories from the given file, one per line. A missing or
+    /// unreadable file gives an empty list.
+    pub fn load(file: &Path) -> Self {
+        let paths = fs::read_to_string(file)
+            .map(|s| {
+                s.lines()
+                    .filter(|l| !l.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { paths }
+    }
+
+    pub fn push(mut self, path: String, max: usize) -> Self {
+        self.paths.retain(|p| p != &path);
+        self.paths.insert(0, path);
+        self.paths.truncate(max);
+        self
+    }
+
+    pub fn remove(mut self, path: &str) -> Self {
+        self.paths.retain(|p| p != path);
+        self
+    }
+
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    pub fn to_file_content(&self) -> String {
+        self.paths
+            .iter()
+            .map(|p| format!("{}\n", p))
+            .collect::<Vec<String>>()
+            .join("")
+    }
+}
+
+/// The explorer state of a tab.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tab {
+    pub pwd: String,
+    pub directory_buffers: HashMap<String, DirectoryBuffer>,
+    pub selection: IndexSet<Node>,
+    pub explorer_config: ExplorerConfig,
+    pub history: History,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PickerItem {
+    pub label: String,
+    pub messages: Vec<ExternalMsg>,
+}
+
+impl PickerItem {
+    pub fn new(label: String, messages: Vec<ExternalMsg>) -> Self {
+        Self { label, messages }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MouseAction {
+    Click,
+    DoubleClick,
+    ScrollUp,
+    ScrollDown,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MouseInput {
+    pub action: MouseAction,
+    pub column: u16,
+    pub row: u16,
+}
+
+/// The part of the screen where the table rows were last drawn.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TableArea {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl TableArea {
+    pub fn contains(&self, column: u16, row: u16) -> bool {
+        column >= self.x
+            && column < self.x + self.width
+            && row >= self.y
+            && row < self.y + self.height
+    }
+}
+
+/// A list of items to pick one from. Picking an item runs its messages.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Picker {
+    pub title: String,
+    pub items: Vec<PickerItem>,
+    pub focus: usize,
+    pub query: String,
+    pub mode: String,
+    pub return_mode: String,
+}
+
+/// A pending yes/no question, and what to do with the answer.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Confirmation {
+    pub prompt: String,
+    pub on_yes: Vec<ExternalMsg>,
+    pub on_no: Vec<ExternalMsg>,
+    pub previous_mode: String,
+}
+
+impl Picker {
+    pub fn new(title: String, items: Vec<PickerItem>) -> Self {
+        Self {
+            title,
+            items,
+            focus: 0,
+            query: Default::default(),
+            mode: "picker".into(),
+            return_mode: "default".into(),
+        }
+    }
+
+    /// Show the picker in the given mode, and switch to the return mode once
+    /// it's closed.
+    pub fn with_modes(mut self, mode: String, return_mode: String) -> Self {
+        self.mode = mode;
+        self.return_mode = return_mode;
+        self
+    }
 
-        let config_file = config_dir.join("config.yml");
-        let default_config = Config::default();
-        let default_config_version = default_config.version.clone();
+    /// The items fuzzy matching the query.
+    pub fn matches(&self) -> Vec<&PickerItem> {
+        self.items
+            .iter()
+            .filter(|i| is_fuzzy_match(&i.label, &self.query))
+            .collect()
+    }
+
+    pub fn focused_item(&self) -> Option<&PickerItem> {
+        self.matches().get(self.focus).copied()
+    }
+}
+
+/// Whether all the characters of the query appear in the text in order,
+/// ignoring the case.
+fn is_fuzzy_match(text: &str, query: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| text.any(|t| t == q))
+}
+
+/// The filter implementing the given hidden mode, if any.
+fn hidden_filter(mode: HiddenMode) -> Option<NodeFilterApplicable> {
+    match mode {
+        HiddenMode::Show => None,
+        HiddenMode::Hide => Some(NodeFilterApplicable::new(
+            NodeFilter::RelativePathDoesNotStartWith,
+            ".".into(),
+        )),
+        HiddenMode::Only => Some(NodeFilterApplicable::new(
+            NodeFilter::RelativePathDoesStartWith,
+            ".".into(),
+        )),
+    }
+}
+
+/// Expand the leading `~` or `~user`, and the environment variables in the
+/// given path. Fails if the user or any of the variables can't be found.
+pub fn expand_path(path: &str) -> Result<String> {
+    let path = if let Some(rest) = path.strip_prefix('~') {
+        let (user, rest) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let home = if user.is_empty() {
+            dirs::home_dir()
+        } else {
+            users::get_user_by_name(user).map(|u| u.home_dir().to_owned())
+        };
+
+        match home {
+            Some(home) => format!("{}{}", home.to_string_lossy(), rest),
+            None => bail!("cannot expand ~{} in {}", user, path),
+        }
+    } else {
+        path.to_string()
+    };
+
+    let mut expanded = String::new();
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        };
 
-        let config: Config = if config_file.exists() {
-            let c: Config =
-                serde_yaml::from_reader(io::BufReader::new(&fs::File::open(&config_file)?))?;
-            c.extended()
+        let braced = chars.peek() == Some(&'{');
+        if braced {
+            chars.next();
+        };
+
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_alphanumeric() || c == '_' {
+                name.push(c);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        if braced && chars.next() != Some('}') {
+            bail!("unclosed ${{ in {}", path)
+        };
+
+        if name.is_empty() && !braced {
+            expanded.push(c);
         } else {
-            default_config
+            match env::var(&name) {
+                Ok(val) => expanded.push_str(&val),
+                Err(_) => bail!("cannot expand ${} in {}", name, path),
+            }
         };
+    }
+    Ok(expanded)
+}
+
+/// The names of the components of the path, from the root, with the paths
+/// they lead to.
+pub fn breadcrumb(path: &str) -> Vec<(String, String)> {
+    let mut components = Path::new(path)
+        .ancestors()
+        .map(|p| {
+            let name = p
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| p.to_string_lossy().to_string());
+            (name, p.to_string_lossy().to_string())
+        })
+        .collect::<Vec<(String, String)>>();
+    components.reverse();
+    components
+}
+
+/// Make the path absolute, relative to the given working directory, and
+/// canonicalize its parent. The last component is kept as is so that a symlink
+/// can still be focused.
+pub fn canonicalize_path(pwd: &str, path: &str) -> String {
+    let path = PathBuf::from(pwd).join(path);
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent.canonicalize().map(|p| p.join(name)).unwrap_or(path),
+        _ => path.canonicalize().unwrap_or(path),
+    }
+    .to_string_lossy()
+    .to_string()
+}
+
+/// Layer the config files, lowest precedence last, on top of the default
+/// config.
+fn load_config(config_files: &[PathBuf]) -> Result<Config> {
+    let default_config = Config::default();
+    let default_config_version = default_config.version.clone();
 
-        if !config.is_compatible()? {
+    let mut config = default_config;
+    for config_file in config_files.iter().rev() {
+        let c: Config = serde_yaml::from_reader(io::BufReader::new(&fs::File::open(config_file)?))
+            .map_err(|e| anyhow!("{}: {}", config_file.to_string_lossy(), e))?;
+
+        if !c.is_compatible()? {
             bail!(
                 "incompatible configuration version in {}
                 You config version is : {}
                 Required version is   : {}
                 Visit {}",
                 config_file.to_string_lossy().to_string(),
-                config.version,
+                c.version,
                 default_config_version,
                 UPGRADE_GUIDE_LINK,
             )
         };
 
-        let mode = match config.modes.builtin.get(&"default".to_string()) {
+        config = config.extend(c);
+    }
+    config.modes = config.modes.resolved();
+    Ok(config)
+}
+
+/// Unset the datetime format if it can't be rendered, for the default one
+/// to be used, and tell why.
+fn drop_invalid_datetime_format(config: &mut Config) -> Option<String> {
+    let format = config
+        .general
+        .datetime_format
+        .as_ref()
+        .filter(|f| !is_valid_datetime_format(f))
+        .cloned()?;
+
+    config.general.datetime_format = None;
+    Some(format!(
+        "invalid datetime format {:?}, falling back to {:?}",
+        format, DEFAULT_DATETIME_FORMAT
+    ))
+}
+
+fn loaded_config_message(config_files: &[PathBuf]) -> String {
+    if config_files.is_empty() {
+        "No config file found, using the default config".to_string()
+    } else {
+        format!(
+            "Loaded config from {}",
+            config_files
+                .iter()
+                .map(|f| f.to_string_lossy().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        )
+    }
+}
+
+/// Check that the strftime-style format string can be rendered.
+pub fn is_valid_datetime_format(format: &str) -> bool {
+    !StrftimeItems::new(format).any(|i| i == Item::Error)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct App {
+    version: String,
+    /// Boxed, because it's big enough for the copies of the app moving between
+    /// the message handlers to overflow the stack in debug builds.
+    config: Box<Config>,
+    /// The config file passed explicitly, to find the same files again when
+    /// reloading.
+    config_file: Option<PathBuf>,
+    pwd: String,
+    directory_buffers: HashMap<String, DirectoryBuffer>,
+    selection: IndexSet<Node>,
+    msg_out: VecDeque<MsgOut>,
+    mode: Mode,
+    input_buffer: Option<String>,
+    input_selection: Option<(usize, usize)>,
+    pid: u32,
+    session_path: String,
+    pipe: Pipe,
+    explorer_config: ExplorerConfig,
+    logs: Vec<Log>,
+    logs_total: usize,
+    history: History,
+    directory_sizes: HashMap<String, DirectorySize>,
+    file_hashes: HashMap<String, FileHash>,
+    /// The git status of the nodes in the working directory, as of the last
+    /// time it got explored.
+    git_statuses: HashMap<String, GitStatus>,
+    preview_children: bool,
+    full_help: Option<usize>,
+    errors_shown: Option<usize>,
+    #[serde(skip)]
+    type_ahead: Option<TypeAhead>,
+    preview: Option<DirectoryBuffer>,
+    last_paste: Option<String>,
+    layout_ratio: u16,
+    layout: String,
+    tabs: Vec<Tab>,
+    tab_index: usize,
+    undo_stack: Vec<FsOperation>,
+    redo_stack: Vec<FsOperation>,
+    picker: Option<Picker>,
+    confirmation: Option<Confirmation>,
+    transfer: Option<Transfer>,
+    pending_transfer: Option<PendingTransfer>,
+    paste_register: Option<PasteRegister>,
+    transfer_progress: Option<TransferProgress>,
+    command_output: Option<CommandOutput>,
+    last_action: Option<(Vec<ExternalMsg>, Key)>,
+    macros: HashMap<char, Vec<(ExternalMsg, Key)>>,
+    registers: BTreeMap<char, IndexSet<String>>,
+    recording_macro: Option<(char, Vec<(ExternalMsg, Key)>)>,
+    macro_playback: Option<(char, usize)>,
+    in_directory_change_hook: bool,
+    focus_changes: usize,
+    in_focus_change_hook: bool,
+    pending_keys: Option<Vec<Key>>,
+    key_sequences: usize,
+    spinner_frame: usize,
+    table_area: Option<TableArea>,
+    recent_directories: RecentDirectories,
+    directory_scores: DirectoryScores,
+    favorites: Favorites,
+    views: Views,
+    search: Option<RecursiveSearch>,
+    searches: usize,
+    state_files: StateFiles,
+}
+
+impl App {
+    pub fn create(pwd: PathBuf) -> Result<Self> {
+        Self::create_with_config(pwd, None)
+    }
+
+    /// Create the app with the config layered from the discovered config
+    /// files, the explicitly passed one taking precedence.
+    pub fn create_with_config(pwd: PathBuf, config_file: Option<PathBuf>) -> Result<Self> {
+        Self::create_with_state_files(pwd, config_file, StateFiles::discover())
+    }
+
+    /// Like `create_with_config`, but with the state kept between sessions
+    /// in the given files.
+    pub fn create_with_state_files(
+        pwd: PathBuf,
+        config_file: Option<PathBuf>,
+        state_files: StateFiles,
+    ) -> Result<Self> {
+        let config_files = discover_config_files(config_file.clone())?;
+        let mut config = load_config(&config_files)?;
+
+        let mode = match config.modes.builtin.get("default") {
             Some(m) => m
                 .clone()
                 .sanitized(config.general.read_only.unwrap_or_default()),
@@ -1217,21 +3460,21 @@ impl App {
             }
         };
 
+        let invalid_datetime_format = drop_invalid_datetime_format(&mut config);
+
         let pid = std::process::id();
         let session_path = dirs::runtime_dir()
             .unwrap_or_else(|| "/tmp".into())
             .join("xplr")
             .join("session")
-            .join(&pid.to_string())
+            .join(pid.to_string())
             .to_string_lossy()
             .to_string();
 
         let mut explorer_config = ExplorerConfig::default();
-        if !config.general.show_hidden.unwrap_or_default() {
-            explorer_config.filters.replace(NodeFilterApplicable::new(
-                NodeFilter::RelativePathDoesNotStartWith,
-                ".".into(),
-            ));
+        if let Some(filter) = hidden_filter(config.general.show_hidden.unwrap_or(HiddenMode::Hide))
+        {
+            explorer_config.filters.replace(filter);
         }
 
         if let Some(sorters) = &config.general.initial_sorting {
@@ -1243,21 +3486,100 @@ impl App {
 
         let mut app = Self {
             version: Config::default().version,
-            config: config.clone(),
+            config: Box::new(config.clone()),
+            config_file,
             pwd: pwd.to_string_lossy().to_string(),
             directory_buffers: Default::default(),
             selection: Default::default(),
             msg_out: Default::default(),
             mode,
             input_buffer: Default::default(),
+            input_selection: Default::default(),
             pid,
             session_path: session_path.clone(),
             pipe: Pipe::from_session_path(&session_path)?,
             explorer_config,
             logs: Default::default(),
+            logs_total: 0,
             history,
+            directory_sizes: Default::default(),
+            file_hashes: Default::default(),
+            git_statuses: Default::default(),
+            preview_children: false,
+            full_help: Default::default(),
+            errors_shown: Default::default(),
+            type_ahead: Default::default(),
+            preview: None,
+            last_paste: Default::default(),
+            layout_ratio: DEFAULT_LAYOUT_RATIO,
+            layout: "default".into(),
+            tabs: Default::default(),
+            tab_index: 0,
+            undo_stack: Default::default(),
+            redo_stack: Default::default(),
+            picker: Default::default(),
+            confirmation: Default::default(),
+            transfer: Default::default(),
+            pending_transfer: Default::default(),
+            paste_register: Default::default(),
+            transfer_progress: Default::default(),
+            command_output: Default::default(),
+            last_action: Default::default(),
+            macros: Default::default(),
+            registers: Default::default(),
+            recording_macro: Default::default(),
+            macro_playback: Default::default(),
+            in_directory_change_hook: Default::default(),
+            focus_changes: Default::default(),
+            pending_keys: Default::default(),
+            key_sequences: Default::default(),
+            in_focus_change_hook: Default::default(),
+            spinner_frame: Default::default(),
+            table_area: Default::default(),
+            recent_directories: state_files
+                .recent_directories
+                .as_deref()
+                .map(RecentDirectories::load)
+                .unwrap_or_default()
+                .push(
+                    pwd.to_string_lossy().to_string(),
+                    config
+                        .general
+                        .max_recent_directories
+                        .unwrap_or(DEFAULT_MAX_RECENT_DIRECTORIES),
+                ),
+            directory_scores: state_files
+                .directory_scores
+                .as_deref()
+                .map(DirectoryScores::load)
+                .unwrap_or_default()
+                .visit(pwd.to_string_lossy().to_string(), now_secs()),
+            favorites: state_files
+                .favorites
+                .as_deref()
+                .map(Favorites::load)
+                .unwrap_or_default(),
+            views: state_files
+                .views
+                .as_deref()
+                .map(Views::load)
+                .unwrap_or_default(),
+            search: Default::default(),
+            searches: Default::default(),
+            state_files,
         };
 
+        app.tabs.push(app.current_tab());
+
+        if let Some(err) = invalid_datetime_format {
+            app = app.enqueue(Task::new(MsgIn::External(ExternalMsg::LogError(err)), None));
+        }
+
+        app = app.enqueue(Task::new(
+            MsgIn::External(ExternalMsg::LogInfo(loaded_config_message(&config_files))),
+            None,
+        ));
+
         if let Some(notif) = config.upgrade_notification()? {
             let notif = format!(
                 "{}. To stop seeing this log, update your config version from {} to {}.",
@@ -1288,29 +3610,170 @@ impl App {
     }
 
     pub fn handle_task(self, task: Task) -> Result<Self> {
-        match task.msg {
+        let pwd = self.pwd.clone();
+        let focus = self
+            .config
+            .general
+            .on_focus_change
+            .as_ref()
+            .map(|_| self.focused_node_str());
+
+        let mut app = match task.msg {
             MsgIn::Internal(msg) => self.handle_internal(msg),
             MsgIn::External(msg) => self.handle_external(msg, task.key),
+        }?;
+
+        if app.pwd != pwd {
+            app = app.run_directory_change_hook()?;
+        };
+
+        match focus {
+            Some(focus) if app.focused_node_str() != focus => app.debounce_focus_change(),
+            _ => Ok(app),
         }
     }
 
-    fn handle_internal(self, msg: InternalMsg) -> Result<Self> {
-        match msg {
-            InternalMsg::AddDirectory(parent, dir) => self.add_directory(parent, dir),
-            InternalMsg::HandleKey(key) => self.handle_key(key),
-        }
+    /// Whether the current mode, or else the config, refuses to modify files
+    /// or call commands.
+    fn is_read_only(&self) -> bool {
+        self.mode
+            .read_only
+            .or(self.config.general.read_only)
+            .unwrap_or_default()
+    }
+
+    /// The part of a hook action that can run right now, if any.
+    fn runnable_hook(&self, action: Option<&Action>) -> Option<Action> {
+        let read_only = self.is_read_only();
+
+        action
+            .cloned()
+            .and_then(|a| a.sanitized(read_only))
+            .and_then(|a| a.applicable(&|c| self.is_met(c)).cloned())
     }
 
-    fn handle_external(self, msg: ExternalMsg, key: Option<Key>) -> Result<Self> {
-        if self.config().general.read_only.unwrap_or_default() && !msg.is_read_only() {
-            self.log_error("Cannot call shell command in read-only mode.".into())
+    /// The directories the hook itself enters don't run it again, so that a
+    /// hook changing the directory can't loop forever.
+    fn run_directory_change_hook(mut self) -> Result<Self> {
+        if self.in_directory_change_hook {
+            return Ok(self);
+        };
+
+        let action = self.runnable_hook(self.config.general.on_directory_change.as_ref());
+        if let Some(action) = action {
+            self.in_directory_change_hook = true;
+            Ok(self
+                .enqueue_action(action.messages, None)?
+                .enqueue(Task::new(
+                    MsgIn::Internal(InternalMsg::FinishDirectoryChangeHook),
+                    None,
+                )))
         } else {
-            match msg {
+            Ok(self)
+        }
+    }
+
+    fn finish_directory_change_hook(mut self) -> Result<Self> {
+        self.in_directory_change_hook = false;
+        Ok(self)
+    }
+
+    /// Each focus change restarts the wait, and only the last one of a burst
+    /// runs the hook. Like for directories, the hook moving the focus itself
+    /// doesn't run it again.
+    fn debounce_focus_change(mut self) -> Result<Self> {
+        if self.in_focus_change_hook {
+            return Ok(self);
+        };
+
+        self.focus_changes += 1;
+        let delay = self
+            .config
+            .general
+            .focus_change_debounce
+            .unwrap_or(DEFAULT_FOCUS_CHANGE_DEBOUNCE);
+
+        self.msg_out.push_back(MsgOut::DebounceFocusChange(
+            self.focus_changes,
+            Duration::from_millis(delay),
+        ));
+        Ok(self)
+    }
+
+    fn run_focus_change_hook(mut self, change: usize) -> Result<Self> {
+        if change != self.focus_changes || self.in_focus_change_hook {
+            return Ok(self);
+        };
+
+        let action = self.runnable_hook(self.config.general.on_focus_change.as_ref());
+        if let Some(action) = action {
+            self.in_focus_change_hook = true;
+            Ok(self
+                .enqueue_action(action.messages, None)?
+                .enqueue(Task::new(
+                    MsgIn::Internal(InternalMsg::FinishFocusChangeHook),
+                    None,
+                )))
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn finish_focus_change_hook(mut self) -> Result<Self> {
+        self.in_focus_change_hook = false;
+        Ok(self)
+    }
+
+    fn handle_internal(self, msg: InternalMsg) -> Result<Self> {
+        match msg {
+            InternalMsg::AddDirectory(parent, dir) => self.add_directory(parent, dir),
+            InternalMsg::SetPreview(dir) => self.set_preview(dir),
+            InternalMsg::SetDirectorySize(path, size) => self.set_directory_size(path, size),
+            InternalMsg::SetFileHash(path, hash) => self.set_file_hash(path, hash),
+            InternalMsg::SetGitStatuses(dir, statuses) => self.set_git_statuses(dir, statuses),
+            InternalMsg::SetTransferProgress(progress) => self.set_transfer_progress(progress),
+            InternalMsg::FinishTransferItem(item) => self.finish_transfer_item(item),
+            InternalMsg::FinishTransfer => self.finish_transfer(),
+            InternalMsg::AddSearchResults(results) => self.add_search_results(results),
+            InternalMsg::SetCommandOutput(output) => self.set_command_output(output),
+            InternalMsg::PlayNextMacroStep => self.play_next_macro_step(),
+            InternalMsg::AdvanceSpinner => self.advance_spinner(),
+            InternalMsg::FinishMacroStep(logs) => self.finish_macro_step(logs),
+            InternalMsg::FinishDirectoryChangeHook => self.finish_directory_change_hook(),
+            InternalMsg::RunFocusChangeHook(change) => self.run_focus_change_hook(change),
+            InternalMsg::ResolveKeySequence(sequence) => self.resolve_key_sequence(sequence),
+            InternalMsg::FinishFocusChangeHook => self.finish_focus_change_hook(),
+            InternalMsg::HandleKey(key) => self.handle_key(key),
+            InternalMsg::HandlePaste(text) => self.handle_paste(text),
+            InternalMsg::Tick => self.handle_tick(),
+            InternalMsg::HandleMouse(input) => self.handle_mouse(input),
+        }
+    }
+
+    fn handle_external(mut self, msg: ExternalMsg, key: Option<Key>) -> Result<Self> {
+        let read_only = self.is_read_only();
+
+        if read_only && !msg.is_read_only() {
+            self.log_error("Cannot call shell command or modify files in read-only mode.".into())
+        } else {
+            match msg {
                 ExternalMsg::Explore => self.explore(),
                 ExternalMsg::Refresh => self.refresh(),
                 ExternalMsg::ClearScreen => self.clear_screen(),
+                ExternalMsg::NewTab => self.new_tab(),
+                ExternalMsg::CloseTab => self.close_tab(),
+                ExternalMsg::NextTab => self.next_tab(),
+                ExternalMsg::PrevTab => self.prev_tab(),
+                ExternalMsg::SetLayoutRatio(ratio) => self.set_layout_ratio(ratio),
+                ExternalMsg::GrowLayoutRatio(by) => self.grow_layout_ratio(by),
+                ExternalMsg::ShrinkLayoutRatio(by) => self.shrink_layout_ratio(by),
+                ExternalMsg::SwitchLayout(name) => self.switch_layout(name),
+                ExternalMsg::CycleLayout => self.cycle_layout(),
                 ExternalMsg::FocusFirst => self.focus_first(),
                 ExternalMsg::FocusLast => self.focus_last(),
+                ExternalMsg::CenterFocus => self.scroll_focus_to(|height| height / 2),
+                ExternalMsg::FocusToTop => self.scroll_focus_to(|_| 0),
+                ExternalMsg::FocusToBottom => self.scroll_focus_to(|height| height - 1),
                 ExternalMsg::FocusPrevious => self.focus_previous(),
                 ExternalMsg::FocusPreviousByRelativeIndex(i) => {
                     self.focus_previous_by_relative_index(i)
@@ -1321,52 +3784,143 @@ impl App {
                 }
                 ExternalMsg::FocusNext => self.focus_next(),
                 ExternalMsg::FocusNextByRelativeIndex(i) => self.focus_next_by_relative_index(i),
+                ExternalMsg::FocusNextMatching(m) => self.focus_matching(m, true),
+                ExternalMsg::FocusPreviousMatching(m) => self.focus_matching(m, false),
                 ExternalMsg::FocusNextByRelativeIndexFromInput => {
                     self.focus_next_by_relative_index_from_input()
                 }
-                ExternalMsg::FocusPath(p) => self.focus_path(&p),
+                ExternalMsg::FocusPath(p) => {
+                    let p = self.normalize_path(&p);
+                    self.focus_path(&p)
+                }
                 ExternalMsg::FocusPathFromInput => self.focus_path_from_input(),
                 ExternalMsg::FocusByIndex(i) => self.focus_by_index(i),
                 ExternalMsg::FocusByIndexFromInput => self.focus_by_index_from_input(),
                 ExternalMsg::FocusByFileName(n) => self.focus_by_file_name(&n),
-                ExternalMsg::ChangeDirectory(dir) => self.change_directory(&dir),
+                ExternalMsg::ChangeDirectory(dir) => {
+                    let dir = self.normalize_path(&dir);
+                    self.change_directory(&dir)
+                }
+                ExternalMsg::SyncPwdFromEnv => self.sync_pwd_from_env(),
+                ExternalMsg::GoHome => self.go_home(),
+                ExternalMsg::JumpToBreadcrumb(index) => self.jump_to_breadcrumb(index),
                 ExternalMsg::Enter => self.enter(),
+                ExternalMsg::EnterOrOpen => self.enter_or_open(key),
                 ExternalMsg::Back => self.back(),
+                ExternalMsg::NextSiblingDirectory => self.sibling_directory(true),
+                ExternalMsg::PreviousSiblingDirectory => self.sibling_directory(false),
                 ExternalMsg::LastVisitedPath => self.last_visited_path(),
                 ExternalMsg::NextVisitedPath => self.next_visited_path(),
+                ExternalMsg::GoTo(path) => self.go_to(&path),
+                ExternalMsg::GoToFromInput => self.go_to_from_input(),
+                ExternalMsg::ShowRecentDirectories => self.show_recent_directories(),
+                ExternalMsg::GoToRecentDirectory(dir) => self.go_to_recent_directory(&dir),
+                ExternalMsg::JumpToFrequent(fragment) => self.jump_to_frequent(&fragment),
+                ExternalMsg::JumpToFrequentFromInput => self.jump_to_frequent_from_input(),
+                ExternalMsg::AddToFavorites => self.add_to_favorites(),
+                ExternalMsg::ToggleTreeView => self.toggle_tree_view(),
+                ExternalMsg::TogglePreviewPaneShowsChildren => {
+                    self.toggle_preview_pane_shows_children()
+                }
+                ExternalMsg::ToggleFullHelp => self.toggle_full_help(),
+                ExternalMsg::ShowErrors => self.show_errors(),
+                ExternalMsg::EnterTypeAheadMode => self.enter_type_ahead_mode(),
+                ExternalMsg::ToggleExpand => self.toggle_expand(),
+                ExternalMsg::QuickFilter(term) => self.quick_filter(term),
+                ExternalMsg::ClearQuickFilter => self.clear_quick_filter(),
+                ExternalMsg::ReverseListing => self.reverse_listing(),
+                ExternalMsg::RemoveFromFavorites => self.remove_from_favorites(),
+                ExternalMsg::ShowFavorites => self.show_favorites(),
+                ExternalMsg::ShowMountPoints => self.show_mount_points(),
+                ExternalMsg::FocusNextPickerItem => self.focus_next_picker_item(),
+                ExternalMsg::FocusPreviousPickerItem => self.focus_previous_picker_item(),
+                ExternalMsg::PickFocusedPickerItem => self.pick_focused_picker_item(),
+                ExternalMsg::ClosePicker => self.close_picker(),
+                ExternalMsg::OpenCommandPalette => self.open_command_palette(),
+                ExternalMsg::FilterPicker(query) => self.filter_picker(query),
+                ExternalMsg::FilterPickerFromInput => self.filter_picker_from_input(),
+                ExternalMsg::Confirm {
+                    prompt,
+                    on_yes,
+                    on_no,
+                } => self.confirm(prompt, on_yes, on_no),
+                ExternalMsg::ConfirmYes => self.confirm_yes(key),
+                ExternalMsg::ConfirmNo => self.confirm_no(),
                 ExternalMsg::FollowSymlink => self.follow_symlink(),
+                ExternalMsg::ComputeDirectorySize => self.compute_directory_size(),
+                ExternalMsg::ComputeHash(algorithm) => self.compute_hash(algorithm),
                 ExternalMsg::BufferInput(input) => self.buffer_input(&input),
                 ExternalMsg::BufferInputFromKey => self.buffer_input_from_key(key),
+                ExternalMsg::BufferInputFromPaste => self.buffer_input_from_paste(),
                 ExternalMsg::SetInputBuffer(input) => self.set_input_buffer(input),
+                ExternalMsg::SetInputBufferToFocusedName => self.set_input_buffer_to_focused_name(),
+                ExternalMsg::SetInputBufferToFocusedNameSelectingStem => {
+                    self.set_input_buffer_to_focused_name_selecting_stem()
+                }
                 ExternalMsg::RemoveInputBufferLastCharacter => {
                     self.remove_input_buffer_last_character()
                 }
                 ExternalMsg::RemoveInputBufferLastWord => self.remove_input_buffer_last_word(),
+                ExternalMsg::RemoveInputBufferNextCharacter => {
+                    self.remove_input_buffer_next_character()
+                }
+                ExternalMsg::RemoveInputBufferToEnd => self.remove_input_buffer_to_end(),
+                ExternalMsg::InputCursorLeft => self.input_cursor_left(),
+                ExternalMsg::InputCursorRight => self.input_cursor_right(),
+                ExternalMsg::InputCursorWordLeft => self.input_cursor_word_left(),
+                ExternalMsg::InputCursorWordRight => self.input_cursor_word_right(),
+                ExternalMsg::InputCursorHome => self.input_cursor_home(),
+                ExternalMsg::InputCursorEnd => self.input_cursor_end(),
                 ExternalMsg::ResetInputBuffer => self.reset_input_buffer(),
                 ExternalMsg::SwitchMode(mode) => self.switch_mode(&mode),
                 ExternalMsg::Call(cmd) => self.call(cmd),
                 ExternalMsg::CallSilently(cmd) => self.call_silently(cmd),
                 ExternalMsg::BashExec(cmd) => self.bash_exec(cmd),
                 ExternalMsg::BashExecSilently(cmd) => self.bash_exec_silently(cmd),
+                ExternalMsg::OpenShell => self.open_shell(),
+                ExternalMsg::PipeFocusedThrough(cmd) => self.pipe_focused_through(cmd),
+                ExternalMsg::ForEachSelection(cmd) => self.for_each_selection(cmd),
+                ExternalMsg::DiffSelection => self.diff_selection(),
+                ExternalMsg::ClearCommandOutput => self.clear_command_output(),
+                ExternalMsg::OpenWithTypeCommand => self.open_with_type_command(),
                 ExternalMsg::Select => self.select(),
                 ExternalMsg::SelectAll => self.select_all(),
-                ExternalMsg::SelectPath(p) => self.select_path(p),
+                ExternalMsg::SelectByFilter(filter) => self.select_by_filter(&filter),
+                ExternalMsg::UnSelectByFilter(filter) => self.un_select_by_filter(&filter),
+                ExternalMsg::SelectPath(p) => {
+                    let p = self.normalize_path(&p);
+                    self.select_path(p)
+                }
                 ExternalMsg::UnSelect => self.un_select(),
                 ExternalMsg::UnSelectAll => self.un_select_all(),
-                ExternalMsg::UnSelectPath(p) => self.un_select_path(p),
+                ExternalMsg::UnSelectPath(p) => {
+                    let p = self.normalize_path(&p);
+                    self.un_select_path(p)
+                }
                 ExternalMsg::ToggleSelection => self.toggle_selection(),
                 ExternalMsg::ToggleSelectAll => self.toggle_select_all(),
-                ExternalMsg::ToggleSelectionByPath(p) => self.toggle_selection_by_path(p),
+                ExternalMsg::ToggleSelectionByPath(p) => {
+                    let p = self.normalize_path(&p);
+                    self.toggle_selection_by_path(p)
+                }
                 ExternalMsg::ClearSelection => self.clear_selection(),
+                ExternalMsg::AddToRegister(r) => self.add_to_register(r),
+                ExternalMsg::ClearRegister(r) => self.clear_register(r),
+                ExternalMsg::SelectRegister(r) => self.select_register(r),
                 ExternalMsg::AddNodeFilter(f) => self.add_node_filter(f),
                 ExternalMsg::AddNodeFilterFromInput(f) => self.add_node_filter_from_input(f),
                 ExternalMsg::RemoveNodeFilter(f) => self.remove_node_filter(f),
                 ExternalMsg::RemoveNodeFilterFromInput(f) => self.remove_node_filter_from_input(f),
                 ExternalMsg::ToggleNodeFilter(f) => self.toggle_node_filter(f),
+                ExternalMsg::CycleHiddenMode => self.cycle_hidden_mode(),
+                ExternalMsg::FilterToSelection => self.filter_to_selection(),
                 ExternalMsg::RemoveLastNodeFilter => self.remove_last_node_filter(),
                 ExternalMsg::ResetNodeFilters => self.reset_node_filters(),
                 ExternalMsg::ClearNodeFilters => self.clear_node_filters(),
+                ExternalMsg::ToggleFiltersEnabled => self.toggle_filters_enabled(),
                 ExternalMsg::AddNodeSorter(f) => self.add_node_sorter(f),
+                ExternalMsg::SortByColumn(c) => self.sort_by_column(c),
+                ExternalMsg::SortByColumnFromKey => self.sort_by_column_from_key(key),
                 ExternalMsg::RemoveNodeSorter(f) => self.remove_node_sorter(f),
                 ExternalMsg::ReverseNodeSorter(f) => self.reverse_node_sorter(f),
                 ExternalMsg::ToggleNodeSorter(f) => self.toggle_node_sorter(f),
@@ -1374,48 +3928,473 @@ impl App {
                 ExternalMsg::ReverseNodeSorters => self.reverse_node_sorters(),
                 ExternalMsg::ResetNodeSorters => self.reset_node_sorters(),
                 ExternalMsg::ClearNodeSorters => self.clear_node_sorters(),
+                ExternalMsg::ToggleReadOnly => self.toggle_read_only(key),
+                ExternalMsg::TogglePersistExplorerConfig => self.toggle_persist_explorer_config(),
+                ExternalMsg::ToggleCompact => self.toggle_compact(),
+                ExternalMsg::ToggleSizeUnitBase => self.toggle_size_unit_base(),
+                ExternalMsg::Rename(name) => {
+                    let name = self.normalize_path(&name);
+                    self.rename(&name)
+                }
+                ExternalMsg::RenameFromInput => self.rename_from_input(),
+                ExternalMsg::SetPermissions(mode) => self.set_permissions(mode),
+                ExternalMsg::SetPermissionsFromInput => self.set_permissions_from_input(),
+                ExternalMsg::SetOwner(owner) => self.set_owner(owner),
+                ExternalMsg::SetOwnerFromInput => self.set_owner_from_input(),
+                ExternalMsg::GitStage => self.git_stage(),
+                ExternalMsg::GitUnstage => self.git_unstage(),
+                ExternalMsg::CreateDirectory(path) => {
+                    let path = self.normalize_path(&path);
+                    self.create_directory(&path)
+                }
+                ExternalMsg::CreateDirectoryFromInput => self.create_directory_from_input(),
+                ExternalMsg::CreateFile(path) => {
+                    let path = self.normalize_path(&path);
+                    self.create_file(&path)
+                }
+                ExternalMsg::CreateFileFromInput => self.create_file_from_input(),
+                ExternalMsg::CreateFileFromTemplate(f) => self.create_file_from_template(f),
+                ExternalMsg::CreateSymlink(symlink) => self.create_symlink(symlink),
+                ExternalMsg::RetargetSymlink(symlink) => self.retarget_symlink(symlink),
+                ExternalMsg::CopySelectionHere => self.copy_selection_here(),
+                ExternalMsg::CopySelectionHereOnConflict(conflict) => {
+                    self.transfer_selection_here(TransferKind::Copy, conflict)
+                }
+                ExternalMsg::ExtractSelection => self.extract_selection(),
+                ExternalMsg::MoveSelectionHere => self.move_selection_here(),
+                ExternalMsg::MoveSelectionHereOnConflict(conflict) => {
+                    self.transfer_selection_here(TransferKind::Move, conflict)
+                }
+                ExternalMsg::YankSelection => self.yank_selection(),
+                ExternalMsg::CutSelection => self.cut_selection(),
+                ExternalMsg::PasteIntoCurrent => {
+                    let conflict = self.paste_conflict();
+                    self.paste_into_current(conflict)
+                }
+                ExternalMsg::PasteIntoCurrentWithNewNames => {
+                    self.paste_into_current(PasteConflict::Rename)
+                }
+                ExternalMsg::PasteIntoCurrentOnConflict(conflict) => {
+                    self.paste_into_current(conflict)
+                }
+                ExternalMsg::ResolveTransferConflict(conflict) => {
+                    self.resolve_transfer_conflict(conflict)
+                }
+                ExternalMsg::CancelTransfer => self.cancel_transfer(),
+                ExternalMsg::SearchRecursively(pattern) => self.search_recursively(pattern),
+                ExternalMsg::SearchRecursivelyFromInput => self.search_recursively_from_input(),
+                ExternalMsg::CancelSearch => self.cancel_search(),
+                ExternalMsg::Trash => self.trash(),
+                ExternalMsg::Delete => self.delete(),
+                ExternalMsg::Undo => self.undo(),
+                ExternalMsg::Redo => self.redo(),
                 ExternalMsg::LogInfo(l) => self.log_info(l),
+                ExternalMsg::LogWarning(l) => self.log_warning(l),
                 ExternalMsg::LogSuccess(l) => self.log_success(l),
                 ExternalMsg::LogError(l) => self.log_error(l),
+                ExternalMsg::LogStyled(l) => self.log_styled(l),
+                ExternalMsg::ClearLogs => self.clear_logs(),
                 ExternalMsg::Quit => self.quit(),
                 ExternalMsg::PrintResultAndQuit => self.print_result_and_quit(),
+                ExternalMsg::PrintFocusedPathAndQuit => self.print_focused_path_and_quit(),
+                ExternalMsg::SetTerminalTitle(title) => self.set_terminal_title(title),
+                ExternalMsg::CopyNameToClipboard => self.copy_name_to_clipboard(),
+                ExternalMsg::CopySelectedNamesToClipboard => {
+                    self.copy_selected_names_to_clipboard()
+                }
+                ExternalMsg::PrintSelectionAndQuit => self.print_selection_and_quit(),
                 ExternalMsg::PrintAppStateAndQuit => self.print_app_state_and_quit(),
                 ExternalMsg::Debug(path) => self.debug(path),
+                ExternalMsg::DumpEffectiveConfig(path) => self.dump_effective_config(path),
+                ExternalMsg::ReloadConfig => self.reload_config(),
+                ExternalMsg::EditConfig => self.edit_config(),
+                ExternalMsg::ExportListingJson(path) => self.export_listing_json(path),
+                ExternalMsg::SetExplorerConfig(view) => self.set_explorer_config(&view),
+                ExternalMsg::DumpExplorerConfig(path) => self.dump_explorer_config(path),
+                ExternalMsg::SaveView(name) => self.save_view(name),
+                ExternalMsg::LoadView(name) => self.load_view(&name),
+                ExternalMsg::ShowViews => self.show_views(),
+                ExternalMsg::StartRecordingMacro(r) => self.start_recording_macro(r),
+                ExternalMsg::StartRecordingMacroFromKey => self.start_recording_macro_from_key(key),
+                ExternalMsg::StopRecordingMacro => self.stop_recording_macro(),
+                ExternalMsg::PlayMacro(r) => self.play_macro(r),
+                ExternalMsg::PlayMacroFromKey => self.play_macro_from_key(key),
+                ExternalMsg::CallFunction(name) => self.call_function(name, key),
+                ExternalMsg::RepeatLastAction => self.repeat_last_action(),
+                ExternalMsg::NoOp => Ok(self),
                 ExternalMsg::Terminate => bail!(""),
             }
         }
     }
 
     fn handle_key(mut self, key: Key) -> Result<Self> {
-        let kb = self.mode.key_bindings.clone();
+        if let Some(scroll) = self.full_help {
+            return self.handle_full_help_key(scroll, key);
+        };
+
+        if let Some(scroll) = self.errors_shown {
+            return self.handle_errors_key(scroll, key);
+        };
+
+        if let Some(type_ahead) = self.type_ahead.take() {
+            return self.handle_type_ahead_key(type_ahead, key);
+        };
+
+        let mut keys = self.pending_keys.take().unwrap_or_default();
+        keys.push(key);
+        let names = keys.iter().map(|k| k.to_string()).collect::<Vec<String>>();
+
+        // Wait for the rest of the key sequence, or for the timeout.
+        if self.mode.key_bindings.is_key_sequence_prefix(&names) {
+            self.pending_keys = Some(keys);
+            self.key_sequences += 1;
+            let timeout = self
+                .config
+                .general
+                .key_sequence_timeout
+                .unwrap_or(DEFAULT_KEY_SEQUENCE_TIMEOUT);
+            self.msg_out.push_back(MsgOut::DebounceKeySequence(
+                self.key_sequences,
+                Duration::from_millis(timeout),
+            ));
+            return Ok(self);
+        };
+
+        self.dispatch_keys(keys)
+    }
+
+    /// Once the timeout passes without the key sequence going on, the keys
+    /// typed so far run their own bindings.
+    fn resolve_key_sequence(mut self, sequence: usize) -> Result<Self> {
+        if sequence != self.key_sequences {
+            return Ok(self);
+        };
+
+        match self.pending_keys.take() {
+            Some(keys) => self.dispatch_keys(keys),
+            None => Ok(self),
+        }
+    }
+
+    /// Run the binding of the longest key sequence the keys start with, or
+    /// of the first key. The keys left over are handled again after it, in
+    /// the mode it may switch to.
+    fn dispatch_keys(mut self, keys: Vec<Key>) -> Result<Self> {
+        let names = keys.iter().map(|k| k.to_string()).collect::<Vec<String>>();
+        let sequence = (2..=keys.len()).rev().find_map(|len| {
+            self.mode
+                .key_bindings
+                .key_sequence_action(&names[..len])
+                .and_then(|a| a.applicable(&|c| self.is_met(c)))
+                .map(|a| (len, a.messages.clone()))
+        });
+
+        let (len, msgs) = match (sequence, keys.first()) {
+            (Some(sequence), _) => sequence,
+            (None, Some(key)) => (1, self.key_messages(*key)),
+            (None, None) => return Ok(self),
+        };
+
+        self = self.run_key_messages(msgs, keys[len - 1])?;
+        for key in &keys[len..] {
+            self = self.enqueue(Task::new(
+                MsgIn::Internal(InternalMsg::HandleKey(*key)),
+                None,
+            ));
+        }
+        Ok(self)
+    }
+
+    /// The messages bound to the single key.
+    fn key_messages(&self, key: Key) -> Vec<ExternalMsg> {
+        let kb = &self.mode.key_bindings;
         let key_str = key.to_string();
-        let default = kb.default.clone();
-        let msgs = kb
-            .remaps
-            .get(&key_str)
-            .and_then(|k| kb.on_key.get(k))
-            .or_else(|| kb.on_key.get(&key_str))
-            .map(|a| Some(a.messages.clone()))
-            .unwrap_or_else(|| {
-                if key.is_alphabet() {
-                    kb.on_alphabet.map(|a| a.messages)
-                } else if key.is_number() {
-                    kb.on_number.map(|a| a.messages)
-                } else if key.is_special_character() {
-                    kb.on_special_character.map(|a| a.messages)
-                } else {
-                    None
-                }
-            })
-            .unwrap_or_else(|| default.map(|a| a.messages).unwrap_or_default());
+        let is_met = |c| self.is_met(c);
+        let applicable = |a: Option<&Action>| {
+            a.and_then(|a| a.applicable(&is_met))
+                .map(|a| a.messages.clone())
+        };
+
+        applicable(
+            kb.remaps
+                .get(&key_str)
+                .and_then(|k| kb.on_key.get(k))
+                .or_else(|| kb.on_key.get(&key_str)),
+        )
+        .or_else(|| {
+            if key.is_alphabet() {
+                applicable(kb.on_alphabet.as_ref())
+            } else if key.is_number() {
+                applicable(kb.on_number.as_ref())
+            } else if key.is_special_character() {
+                applicable(kb.on_special_character.as_ref())
+            } else {
+                None
+            }
+        })
+        .or_else(|| applicable(kb.default.as_ref()))
+        .unwrap_or_default()
+    }
+
+    fn run_key_messages(mut self, msgs: Vec<ExternalMsg>, key: Key) -> Result<Self> {
+        if self.is_repeatable(&msgs) {
+            self.last_action = Some((msgs.clone(), key));
+        };
+
+        if !msgs.iter().any(|m| m.is_macro_control()) {
+            if let Some((_, steps)) = self.recording_macro.as_mut() {
+                steps.extend(msgs.iter().map(|m| (m.clone(), key)));
+            };
+        };
+
+        self.enqueue_action(msgs, Some(key))
+    }
 
+    /// Replace the `CallFunction` messages with the messages of the functions.
+    fn expand_functions(
+        &self,
+        msgs: Vec<ExternalMsg>,
+        depth: usize,
+    ) -> Result<Vec<ExternalMsg>, String> {
+        let mut expanded = vec![];
         for msg in msgs {
-            self = self.enqueue(Task::new(MsgIn::External(msg), Some(key)));
+            if let ExternalMsg::CallFunction(name) = msg {
+                if depth >= MAX_FUNCTION_DEPTH {
+                    return Err(format!(
+                        "Function {} calls functions more than {} deep",
+                        name, MAX_FUNCTION_DEPTH
+                    ));
+                };
+                let body = self
+                    .config
+                    .functions
+                    .get(&name)
+                    .cloned()
+                    .ok_or_else(|| format!("No function named {}", name))?;
+                expanded.extend(self.expand_functions(body, depth + 1)?);
+            } else {
+                expanded.push(msg);
+            };
+        }
+        Ok(expanded)
+    }
+
+    /// Run the messages of the function right away, ahead of the queued ones,
+    /// like the other messages coming from a pipe or a hook.
+    fn call_function(self, name: String, key: Option<Key>) -> Result<Self> {
+        match self.expand_functions(vec![ExternalMsg::CallFunction(name)], 0) {
+            Ok(msgs) => msgs
+                .into_iter()
+                .try_fold(self, |app, msg| app.handle_external(msg, key)),
+            Err(e) => self.log_error(e),
+        }
+    }
+
+    /// Queue the messages, with the functions they call expanded in place so
+    /// that everything runs in order. Nothing is queued if an expansion fails.
+    fn enqueue_action(mut self, msgs: Vec<ExternalMsg>, key: Option<Key>) -> Result<Self> {
+        match self.expand_functions(msgs, 0) {
+            Ok(msgs) => {
+                for msg in msgs {
+                    self = self.enqueue(Task::new(MsgIn::External(msg), key));
+                }
+                Ok(self)
+            }
+            Err(e) => self.log_error(e),
+        }
+    }
+
+    /// Whether the condition of an action holds.
+    pub fn is_met(&self, condition: ActionCondition) -> bool {
+        match condition {
+            ActionCondition::FocusIsDirectory => {
+                self.focused_node().map(|n| n.is_dir).unwrap_or(false)
+            }
+            ActionCondition::FocusIsFile => self.focused_node().map(|n| n.is_file).unwrap_or(false),
+            ActionCondition::SelectionIsEmpty => self.selection.is_empty(),
+            ActionCondition::SelectionIsNotEmpty => !self.selection.is_empty(),
+            ActionCondition::ReadOnly => self.is_read_only(),
+            ActionCondition::NotReadOnly => !self.is_read_only(),
+        }
+    }
+
+    fn is_repeatable(&self, msgs: &[ExternalMsg]) -> bool {
+        let ignored = self
+            .config
+            .general
+            .repeat_ignored_messages
+            .clone()
+            .unwrap_or_default();
+
+        !msgs.iter().any(|m| m == &ExternalMsg::RepeatLastAction)
+            && msgs.iter().any(|m| !ignored.contains(&m.name()))
+    }
+
+    fn start_recording_macro(mut self, register: char) -> Result<Self> {
+        self.recording_macro = Some((register, vec![]));
+        self.log_info(format!("recording macro {}", register))
+    }
+
+    fn start_recording_macro_from_key(self, key: Option<Key>) -> Result<Self> {
+        if let Some(c) = key.and_then(|k| k.to_char()) {
+            self.start_recording_macro(c)
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn stop_recording_macro(mut self) -> Result<Self> {
+        if let Some((register, steps)) = self.recording_macro.take() {
+            let len = steps.len();
+            self.macros.insert(register, steps);
+            self.log_info(format!("macro {} recorded with {} messages", register, len))
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn play_macro(mut self, register: char) -> Result<Self> {
+        if self.macros.contains_key(&register) {
+            self.macro_playback = Some((register, 0));
+            self.play_next_macro_step()
+        } else {
+            self.log_error(format!("No macro recorded in {}", register))
+        }
+    }
+
+    fn play_macro_from_key(self, key: Option<Key>) -> Result<Self> {
+        if let Some(c) = key.and_then(|k| k.to_char()) {
+            self.play_macro(c)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Each message is followed by a check for errors, and the next message
+    /// is queued after the ones the previous message queued, so that the macro
+    /// plays the same way it was recorded.
+    fn play_next_macro_step(mut self) -> Result<Self> {
+        let step = self.macro_playback.and_then(|(register, index)| {
+            self.macros
+                .get(&register)
+                .and_then(|steps| steps.get(index))
+                .cloned()
+        });
+
+        if let Some((msg, key)) = step {
+            let logs = self.logs_total;
+            Ok(self
+                .enqueue(Task::new(MsgIn::External(msg), Some(key)))
+                .enqueue(Task::new(
+                    MsgIn::Internal(InternalMsg::FinishMacroStep(logs)),
+                    None,
+                )))
+        } else {
+            self.macro_playback = None;
+            Ok(self)
+        }
+    }
+
+    fn finish_macro_step(mut self, logs: usize) -> Result<Self> {
+        if let Some((register, index)) = self.macro_playback {
+            if self
+                .logs_since(logs)
+                .iter()
+                .any(|l| l.level == LogLevel::Error)
+            {
+                self.macro_playback = None;
+                let name = self
+                    .macros
+                    .get(&register)
+                    .and_then(|steps| steps.get(index))
+                    .map(|(msg, _)| msg.name())
+                    .unwrap_or_default();
+
+                self.log_error(format!(
+                    "Macro {} stopped at message {} ({})",
+                    register,
+                    index + 1,
+                    name
+                ))
+            } else {
+                self.macro_playback = Some((register, index + 1));
+                Ok(self.enqueue(Task::new(
+                    MsgIn::Internal(InternalMsg::PlayNextMacroStep),
+                    None,
+                )))
+            }
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// The messages are handled as usual, so the read-only mode still applies.
+    fn repeat_last_action(self) -> Result<Self> {
+        if let Some((msgs, key)) = self.last_action.clone() {
+            self.enqueue_action(msgs, Some(key))
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn handle_paste(mut self, text: String) -> Result<Self> {
+        self.last_paste = Some(text);
+        if let Some(action) = self.mode.key_bindings.on_paste.clone() {
+            self.enqueue_action(action.messages, None)
+        } else {
+            self.buffer_input_from_paste()
         }
+    }
 
+    fn handle_tick(mut self) -> Result<Self> {
+        if let Some(action) = self.mode.key_bindings.on_tick.clone() {
+            for msg in action.messages {
+                self = self.enqueue(Task::new(MsgIn::External(msg), None));
+            }
+        };
         Ok(self)
     }
 
+    fn handle_mouse(self, input: MouseInput) -> Result<Self> {
+        if !self.config.general.enable_mouse.unwrap_or(false) || self.picker.is_some() {
+            return Ok(self);
+        };
+
+        let area = match self.table_area {
+            Some(area) if area.contains(input.column, input.row) => area,
+            _ => return Ok(self),
+        };
+
+        match input.action {
+            MouseAction::ScrollUp => self.focus_previous(),
+            MouseAction::ScrollDown => self.focus_next(),
+            MouseAction::Click | MouseAction::DoubleClick => {
+                // The rows are paged the same way the table draws them.
+                let height = usize::from(area.height).max(1);
+                let clicked = self.directory_buffer().and_then(|dir| {
+                    let index = dir.first_visible(height) + usize::from(input.row - area.y);
+                    dir.nodes.get(index).map(|n| (index, n.is_dir))
+                });
+
+                match clicked {
+                    Some((index, is_dir)) => {
+                        let mut app = self.focus_by_index(index)?;
+                        if input.action == MouseAction::DoubleClick {
+                            let msg = if is_dir {
+                                ExternalMsg::Enter
+                            } else {
+                                ExternalMsg::OpenWithTypeCommand
+                            };
+                            app = app.enqueue(Task::new(MsgIn::External(msg), None));
+                        };
+                        Ok(app)
+                    }
+                    None => Ok(self),
+                }
+            }
+        }
+    }
+
     fn explore(mut self) -> Result<Self> {
         self.msg_out.push_back(MsgOut::Explore);
         Ok(self)
@@ -1431,6 +4410,104 @@ impl App {
         Ok(self)
     }
 
+    fn current_tab(&self) -> Tab {
+        Tab {
+            pwd: self.pwd.clone(),
+            directory_buffers: self.directory_buffers.clone(),
+            selection: self.selection.clone(),
+            explorer_config: self.explorer_config.clone(),
+            history: self.history.clone(),
+        }
+    }
+
+    fn load_tab(mut self, tab: Tab) -> Result<Self> {
+        if let Err(e) = archive::set_current_dir(&tab.pwd) {
+            self = self.log_error(e.to_string())?;
+        };
+        self.pwd = tab.pwd;
+        self.directory_buffers = tab.directory_buffers;
+        self.selection = tab.selection;
+        self.explorer_config = tab.explorer_config;
+        self.history = tab.history;
+        self.explore()
+    }
+
+    fn switch_tab(mut self, index: usize) -> Result<Self> {
+        self.tabs[self.tab_index] = self.current_tab();
+        self.tab_index = index;
+        let tab = self.tabs[index].clone();
+        self.load_tab(tab)
+    }
+
+    fn new_tab(mut self) -> Result<Self> {
+        let mut tab = self.current_tab();
+        tab.selection.clear();
+        let index = self.tab_index + 1;
+        self.tabs.insert(index, tab);
+        self.switch_tab(index)
+    }
+
+    fn close_tab(mut self) -> Result<Self> {
+        if self.tabs.len() <= 1 {
+            self.log_error("Cannot close the last tab.".into())
+        } else {
+            self.tabs.remove(self.tab_index);
+            self.tab_index = self.tab_index.min(self.tabs.len() - 1);
+            let tab = self.tabs[self.tab_index].clone();
+            self.load_tab(tab)
+        }
+    }
+
+    fn next_tab(self) -> Result<Self> {
+        let index = (self.tab_index + 1) % self.tabs.len();
+        self.switch_tab(index)
+    }
+
+    fn prev_tab(self) -> Result<Self> {
+        let index = (self.tab_index + self.tabs.len() - 1) % self.tabs.len();
+        self.switch_tab(index)
+    }
+
+    fn set_layout_ratio(mut self, ratio: u16) -> Result<Self> {
+        self.layout_ratio = ratio.clamp(MIN_LAYOUT_RATIO, MAX_LAYOUT_RATIO);
+        self.refresh()
+    }
+
+    fn grow_layout_ratio(self, by: u16) -> Result<Self> {
+        let ratio = self.layout_ratio.saturating_add(by);
+        self.set_layout_ratio(ratio)
+    }
+
+    fn shrink_layout_ratio(self, by: u16) -> Result<Self> {
+        let ratio = self.layout_ratio.saturating_sub(by);
+        self.set_layout_ratio(ratio)
+    }
+
+    fn switch_layout(mut self, name: String) -> Result<Self> {
+        if self.config.layouts.contains_key(&name) {
+            self.layout = name;
+            self.refresh()
+        } else {
+            self.log_error(format!("Layout not found: {}", name))
+        }
+    }
+
+    fn cycle_layout(self) -> Result<Self> {
+        let names = self.config.layouts.keys().cloned().collect::<Vec<String>>();
+        let next = names
+            .iter()
+            .position(|n| n == &self.layout)
+            .map(|i| (i + 1) % names.len())
+            .and_then(|i| names.get(i))
+            .or_else(|| names.first())
+            .cloned();
+
+        match next {
+            Some(name) => self.switch_layout(name),
+            None => Ok(self),
+        }
+    }
+
     fn focus_first(mut self) -> Result<Self> {
         if let Some(dir) = self.directory_buffer_mut() {
             dir.focus = 0;
@@ -1447,6 +4524,22 @@ impl App {
         Ok(self)
     }
 
+    /// Scroll so that the focused node is the given number of rows below
+    /// the first visible one, if the table was drawn.
+    fn scroll_focus_to(mut self, row: impl Fn(usize) -> usize) -> Result<Self> {
+        let height = match self.table_area {
+            Some(area) => usize::from(area.height).max(1),
+            None => return Ok(self),
+        };
+
+        if let Some(dir) = self.directory_buffer_mut() {
+            let last = dir.total.saturating_sub(height);
+            dir.offset = Some(dir.focus.saturating_sub(row(height)).min(last));
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(self)
+    }
+
     fn focus_previous(mut self) -> Result<Self> {
         if let Some(dir) = self.directory_buffer_mut() {
             dir.focus = dir.focus.max(1) - 1;
@@ -1495,50 +4588,600 @@ impl App {
         }
     }
 
-    fn follow_symlink(self) -> Result<Self> {
-        if let Some(pth) = self
-            .focused_node()
-            .and_then(|n| n.symlink.to_owned().map(|s| s.absolute_path))
-        {
-            self.focus_path(&pth)
-        } else {
-            Ok(self)
+    fn selected_paths(&self) -> HashSet<String> {
+        self.selection
+            .iter()
+            .map(|n| n.absolute_path.clone())
+            .collect()
+    }
+
+    fn focus_matching(self, matching: FocusMatching, forward: bool) -> Result<Self> {
+        let selection = self.selected_paths();
+
+        let found = self.directory_buffer().and_then(|dir| {
+            let len = dir.nodes.len();
+            let focus = dir.focus;
+
+            // Step away from the focus, past the ends only when wrapping.
+            (1..len)
+                .map(|i| if forward { focus + i } else { focus + len - i })
+                .filter(|&i| matching.wrap || (forward && i < len) || (!forward && i >= len))
+                .map(|i| i % len)
+                .find(|&i| {
+                    let node = &dir.nodes[i];
+                    matching.filter.apply(
+                        node,
+                        &node.relative_path,
+                        &matching.input,
+                        &selection,
+                        &self.explorer_config.reference_names,
+                    )
+                })
+        });
+
+        match found {
+            Some(index) => self.focus_by_index(index),
+            None => self.log_info("No more nodes match".into()),
         }
     }
 
-    fn change_directory(mut self, dir: &str) -> Result<Self> {
-        if PathBuf::from(dir).is_dir() {
-            match env::set_current_dir(dir) {
-                Ok(()) => {
-                    self.pwd = dir.to_owned();
-                    self.history = self.history.push(self.pwd.clone());
-                    self.msg_out.push_back(MsgOut::Refresh);
-                    Ok(self)
-                }
-                Err(e) => self.log_error(e.to_string()),
+    /// Expand and canonicalize the path. If the expansion fails, a warning is
+    /// logged and the original path is used.
+    fn normalize_path(&mut self, path: &str) -> String {
+        match expand_path(path) {
+            Ok(expanded) => canonicalize_path(&self.pwd, &expanded),
+            Err(e) => {
+                self.push_log(Log::new(LogLevel::Warning, e.to_string()));
+                path.into()
             }
-        } else {
-            Ok(self)
         }
     }
 
-    fn enter(self) -> Result<Self> {
-        self.focused_node()
-            .map(|n| n.absolute_path.clone())
-            .map(|p| self.clone().change_directory(&p))
-            .unwrap_or(Ok(self))
-    }
+    fn go_to(mut self, pattern: &str) -> Result<Self> {
+        let pattern = match expand_path(pattern) {
+            Ok(expanded) => PathBuf::from(&self.pwd)
+                .join(expanded)
+                .to_string_lossy()
+                .to_string(),
+            Err(e) => {
+                self = self.log_warning(e.to_string())?;
+                pattern.into()
+            }
+        };
+        let paths = match glob::glob(&pattern) {
+            Ok(paths) => paths
+                .filter_map(|p| p.ok())
+                .map(|p| (p.is_dir(), p.to_string_lossy().to_string()))
+                .collect::<Vec<(bool, String)>>(),
+            Err(e) => return self.log_error(format!("Invalid pattern {}: {}", pattern, e)),
+        };
 
-    fn back(self) -> Result<Self> {
-        PathBuf::from(self.pwd())
-            .parent()
-            .map(|p| {
-                self.clone()
-                    .change_directory(&p.to_string_lossy().to_string())
-            })
+        let to_msg = |is_dir: bool, path: String| {
+            if is_dir {
+                ExternalMsg::ChangeDirectory(path)
+            } else {
+                ExternalMsg::FocusPath(path)
+            }
+        };
+
+        match paths.len() {
+            0 => self.log_error(format!("Nothing matches {}", pattern)),
+            1 => {
+                let (is_dir, path) = paths[0].clone();
+                Ok(self.enqueue(Task::new(MsgIn::External(to_msg(is_dir, path)), None)))
+            }
+            _ => {
+                let items = paths
+                    .into_iter()
+                    .map(|(is_dir, path)| PickerItem::new(path.clone(), vec![to_msg(is_dir, path)]))
+                    .collect();
+                self.open_picker(Picker::new(pattern, items))
+            }
+        }
+    }
+
+    fn go_to_from_input(self) -> Result<Self> {
+        if let Some(pattern) = self.input_buffer() {
+            self.go_to(&pattern)
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn show_recent_directories(self) -> Result<Self> {
+        let items = self
+            .recent_directories
+            .paths()
+            .iter()
+            .filter(|p| p != &&self.pwd)
+            .map(|p| PickerItem::new(p.clone(), vec![ExternalMsg::GoToRecentDirectory(p.clone())]))
+            .collect::<Vec<PickerItem>>();
+
+        if items.is_empty() {
+            self.log_info("No recent directories".into())
+        } else {
+            self.open_picker(Picker::new("Recent directories".into(), items))
+        }
+    }
+
+    fn go_to_recent_directory(mut self, dir: &str) -> Result<Self> {
+        if PathBuf::from(dir).is_dir() {
+            self.change_directory(dir)
+        } else {
+            self.recent_directories = self.recent_directories.remove(dir);
+            self.log_info(format!(
+                "{} no longer exists, removed it from the recent directories",
+                dir
+            ))
+        }
+    }
+
+    fn jump_to_frequent(mut self, fragment: &str) -> Result<Self> {
+        let matches = self.directory_scores.matches(fragment, now_secs());
+        for dir in matches {
+            if dir == self.pwd {
+                continue;
+            } else if PathBuf::from(&dir).is_dir() {
+                return self.change_directory(&dir);
+            } else {
+                self.directory_scores = self.directory_scores.remove(&dir);
+            };
+        }
+        self.log_error(format!("No frequent directory matches {}", fragment))
+    }
+
+    fn jump_to_frequent_from_input(self) -> Result<Self> {
+        if let Some(fragment) = self.input_buffer() {
+            self.jump_to_frequent(&fragment)
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn add_to_favorites(mut self) -> Result<Self> {
+        let dir = self.pwd.clone();
+        if self.favorites.contains(&dir) {
+            self.log_info(format!("{} is already a favorite", dir))
+        } else {
+            self.favorites = self.favorites.insert(dir.clone());
+            self.log_success(format!("Added {} to the favorites", dir))
+        }
+    }
+
+    fn remove_from_favorites(mut self) -> Result<Self> {
+        let dir = self.pwd.clone();
+        if self.favorites.contains(&dir) {
+            self.favorites = self.favorites.remove(&dir);
+            self.log_success(format!("Removed {} from the favorites", dir))
+        } else {
+            self.log_info(format!("{} is not a favorite", dir))
+        }
+    }
+
+    fn show_favorites(self) -> Result<Self> {
+        let items = self
+            .favorites
+            .paths()
+            .iter()
+            .map(|p| PickerItem::new(p.clone(), vec![ExternalMsg::ChangeDirectory(p.clone())]))
+            .collect::<Vec<PickerItem>>();
+
+        if items.is_empty() {
+            self.log_info("No favorites".into())
+        } else {
+            self.open_picker(Picker::new("Favorites".into(), items))
+        }
+    }
+
+    fn show_mount_points(self) -> Result<Self> {
+        let mounts = match fs::read_to_string("/proc/mounts") {
+            Ok(mounts) => mounts,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return self.log_error("Listing the mount points is not supported here".into())
+            }
+            Err(e) => return self.log_error(format!("/proc/mounts: {}", e)),
+        };
+
+        let items = parse_mounts(&mounts)
+            .into_iter()
+            .map(|m| {
+                let label = format!("{} ({}, {})", m.path, m.device, m.fs_type);
+                PickerItem::new(label, vec![ExternalMsg::ChangeDirectory(m.path)])
+            })
+            .collect::<Vec<PickerItem>>();
+
+        if items.is_empty() {
+            self.log_info("No mounted devices".into())
+        } else {
+            self.open_picker(Picker::new("Mount points".into(), items))
+        }
+    }
+
+    /// Show the picker. The mode is switched after the messages already in the
+    /// queue are handled, so that the picker survives a trailing `SwitchMode`.
+    fn open_picker(mut self, picker: Picker) -> Result<Self> {
+        let mode = picker.mode.clone();
+        self.picker = Some(picker);
+        Ok(self.enqueue(Task::new(
+            MsgIn::External(ExternalMsg::SwitchMode(mode)),
+            None,
+        )))
+    }
+
+    /// Every described key binding of every mode, searchable in a picker.
+    fn open_command_palette(self) -> Result<Self> {
+        let read_only = self.config.general.read_only.unwrap_or_default();
+        let items = self
+            .modes()
+            .into_iter()
+            .flat_map(|(name, mode)| {
+                mode.sanitized(read_only)
+                    .key_bindings
+                    .on_key
+                    .into_iter()
+                    .filter_map(move |(key, action)| {
+                        let label = action
+                            .help
+                            .map(|help| format!("{} | {} | {}", name, key, help))?;
+                        Some(PickerItem::new(label, action.messages))
+                    })
+            })
+            .collect::<Vec<PickerItem>>();
+
+        let picker = Picker::new("Command palette".into(), items)
+            .with_modes("command palette".into(), self.mode.name.clone());
+
+        Ok(self.open_picker(picker)?.enqueue(Task::new(
+            MsgIn::External(ExternalMsg::SetInputBuffer("".into())),
+            None,
+        )))
+    }
+
+    fn filter_picker(mut self, query: String) -> Result<Self> {
+        if let Some(picker) = self.picker.as_mut() {
+            picker.query = query;
+            picker.focus = 0;
+        };
+        self.refresh()
+    }
+
+    fn filter_picker_from_input(self) -> Result<Self> {
+        let query = self.input_buffer().unwrap_or_default();
+        self.filter_picker(query)
+    }
+
+    fn focus_next_picker_item(mut self) -> Result<Self> {
+        if let Some(picker) = self.picker.as_mut() {
+            picker.focus = (picker.focus + 1).min(picker.matches().len().max(1) - 1);
+        };
+        self.refresh()
+    }
+
+    fn focus_previous_picker_item(mut self) -> Result<Self> {
+        if let Some(picker) = self.picker.as_mut() {
+            picker.focus = picker.focus.max(1) - 1;
+        };
+        self.refresh()
+    }
+
+    fn pick_focused_picker_item(mut self) -> Result<Self> {
+        let picker = self.picker.take();
+        let item = picker
+            .as_ref()
+            .and_then(|p| p.focused_item().map(|i| i.to_owned()));
+        let return_mode = picker
+            .map(|p| p.return_mode)
+            .unwrap_or_else(|| "default".into());
+
+        self = self.cancel_search()?.switch_mode(&return_mode)?;
+        if let Some(item) = item {
+            for msg in item.messages {
+                self = self.enqueue(Task::new(MsgIn::External(msg), None));
+            }
+        };
+        Ok(self)
+    }
+
+    /// Closing the picker asking about an existing path cancels the copy or
+    /// move.
+    fn close_picker(mut self) -> Result<Self> {
+        let return_mode = self
+            .picker
+            .take()
+            .map(|p| p.return_mode)
+            .unwrap_or_else(|| "default".into());
+        self = self.cancel_search()?.switch_mode(&return_mode)?;
+
+        if self.pending_transfer.take().is_some() {
+            self.log_info("Cancelled the copy or move".into())
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Like the picker, the mode is switched after the messages already in the
+    /// queue are handled.
+    fn confirm(
+        mut self,
+        prompt: String,
+        on_yes: Vec<ExternalMsg>,
+        on_no: Vec<ExternalMsg>,
+    ) -> Result<Self> {
+        self.confirmation = Some(Confirmation {
+            prompt,
+            on_yes,
+            on_no,
+            previous_mode: self.mode.name.clone(),
+        });
+        Ok(self.enqueue(Task::new(
+            MsgIn::External(ExternalMsg::SwitchMode("confirm".into())),
+            None,
+        )))
+    }
+
+    fn confirm_yes(mut self, key: Option<Key>) -> Result<Self> {
+        if let Some(confirmation) = self.confirmation.take() {
+            self = self.switch_mode(&confirmation.previous_mode)?;
+
+            let read_only = self.is_read_only();
+
+            if read_only && confirmation.on_yes.iter().any(|m| !m.is_read_only()) {
+                self.log_error(
+                    "Cannot call shell command or modify files in read-only mode.".into(),
+                )
+            } else {
+                for msg in confirmation.on_yes {
+                    self = self.enqueue(Task::new(MsgIn::External(msg), key));
+                }
+                Ok(self)
+            }
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn confirm_no(mut self) -> Result<Self> {
+        if let Some(confirmation) = self.confirmation.take() {
+            self = self.switch_mode(&confirmation.previous_mode)?;
+            for msg in confirmation.on_no {
+                self = self.enqueue(Task::new(MsgIn::External(msg), None));
+            }
+        };
+        Ok(self)
+    }
+
+    fn follow_symlink(self) -> Result<Self> {
+        if let Some(pth) = self
+            .focused_node()
+            .and_then(|n| n.symlink.to_owned().map(|s| s.absolute_path))
+        {
+            self.focus_path(&pth)
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn compute_directory_size(mut self) -> Result<Self> {
+        if let Some(path) = self
+            .focused_node()
+            .filter(|n| n.is_dir)
+            .map(|n| n.absolute_path.clone())
+        {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            let is_cached = self
+                .directory_sizes
+                .get(&path)
+                .map(|s| s.modified.is_some() && s.modified == modified)
+                .unwrap_or(false);
+
+            if !is_cached {
+                self.msg_out.push_back(MsgOut::ComputeDirectorySize(path));
+            }
+        };
+        Ok(self)
+    }
+
+    fn set_directory_size(mut self, path: String, size: DirectorySize) -> Result<Self> {
+        self.directory_sizes.insert(path, size);
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    fn compute_hash(mut self, algorithm: String) -> Result<Self> {
+        let algorithm = match HashAlgorithm::from_name(&algorithm) {
+            Some(a) => a,
+            None => {
+                let supported = HashAlgorithm::ALL
+                    .iter()
+                    .map(|a| a.name())
+                    .collect::<Vec<&str>>()
+                    .join(", ");
+                return self.log_error(format!(
+                    "Unsupported hash algorithm {}, use one of {}",
+                    algorithm, supported
+                ));
+            }
+        };
+
+        if let Some(path) = self
+            .focused_node()
+            .filter(|n| n.canonical.as_ref().map(|c| !c.is_dir).unwrap_or(false))
+            .map(|n| n.absolute_path.clone())
+        {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            let cached = self
+                .file_hashes
+                .get(&path)
+                .filter(|h| {
+                    h.algorithm == algorithm && h.modified.is_some() && h.modified == modified
+                })
+                .cloned();
+
+            match cached {
+                Some(hash) => return self.set_file_hash(path, hash),
+                None => self.msg_out.push_back(MsgOut::ComputeHash(path, algorithm)),
+            };
+        };
+        Ok(self)
+    }
+
+    fn set_file_hash(mut self, path: String, hash: FileHash) -> Result<Self> {
+        let message = format!("{} {} {}", hash.algorithm.name(), hash.digest, path);
+        self.file_hashes.insert(path, hash);
+        self.log_success(message)
+    }
+
+    fn set_git_statuses(
+        mut self,
+        dir: String,
+        statuses: HashMap<String, GitStatus>,
+    ) -> Result<Self> {
+        if dir == self.pwd {
+            self.git_statuses = statuses;
+        };
+        Ok(self)
+    }
+
+    fn change_directory(mut self, dir: &str) -> Result<Self> {
+        if PathBuf::from(dir).is_dir() || archive::split(dir).is_some() {
+            match archive::set_current_dir(dir) {
+                Ok(()) => {
+                    if !self
+                        .config
+                        .general
+                        .persist_explorer_config_across_directories
+                        .unwrap_or(true)
+                    {
+                        self.explorer_config.reversed = false;
+                        self = self.reset_node_filters()?.reset_node_sorters()?;
+                    };
+
+                    self.pwd = dir.to_owned();
+                    self.history = self.history.push(self.pwd.clone());
+                    self.recent_directories = self.recent_directories.push(
+                        self.pwd.clone(),
+                        self.config
+                            .general
+                            .max_recent_directories
+                            .unwrap_or(DEFAULT_MAX_RECENT_DIRECTORIES),
+                    );
+                    self.directory_scores =
+                        self.directory_scores.visit(self.pwd.clone(), now_secs());
+                    self.msg_out.push_back(MsgOut::Refresh);
+                    Ok(self)
+                }
+                Err(e) => self.log_error(e.to_string()),
+            }
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn sync_pwd_from_env(self) -> Result<Self> {
+        let dir = env::var("PWD")
+            .map(PathBuf::from)
+            .or_else(|_| env::current_dir());
+
+        match dir {
+            Ok(dir) if dir.is_dir() => self.change_directory(dir.to_string_lossy().as_ref()),
+            Ok(dir) => self.log_error(format!(
+                "Cannot sync to {}: not a directory",
+                dir.to_string_lossy()
+            )),
+            Err(e) => self.log_error(format!("Cannot sync the working directory: {}", e)),
+        }
+    }
+
+    fn go_home(self) -> Result<Self> {
+        let home = self
+            .config
+            .general
+            .home_directory
+            .clone()
+            .unwrap_or_else(|| "~".into());
+
+        match expand_path(&home) {
+            Ok(dir) => {
+                let dir = canonicalize_path(&self.pwd, &dir);
+                if PathBuf::from(&dir).is_dir() {
+                    self.change_directory(&dir)
+                } else {
+                    self.log_error(format!("Cannot go home to {}: not a directory", dir))
+                }
+            }
+            Err(e) => self.log_error(format!("Cannot go home: {}", e)),
+        }
+    }
+
+    fn jump_to_breadcrumb(self, index: usize) -> Result<Self> {
+        match breadcrumb(&self.pwd).into_iter().nth(index) {
+            Some((_, dir)) => self.change_directory(&dir),
+            None => self.log_error(format!("No breadcrumb component at {}", index)),
+        }
+    }
+
+    fn enter(self) -> Result<Self> {
+        self.focused_node()
+            .map(|n| n.absolute_path.clone())
+            .map(|p| self.clone().change_directory(&p))
+            .unwrap_or(Ok(self))
+    }
+
+    /// Opening is queued as a message of its own, so that it's refused in
+    /// read-only mode while entering directories isn't.
+    fn enter_or_open(self, key: Option<Key>) -> Result<Self> {
+        let node = match self.focused_node() {
+            Some(n) => n.clone(),
+            None => return Ok(self),
+        };
+
+        match node.canonical {
+            Some(c) if c.is_dir => self.change_directory(&node.absolute_path),
+            Some(_) => Ok(self.enqueue(Task::new(
+                MsgIn::External(ExternalMsg::OpenWithTypeCommand),
+                key,
+            ))),
+            None => self.log_error(format!("{} is a broken link", node.absolute_path)),
+        }
+    }
+
+    fn back(self) -> Result<Self> {
+        PathBuf::from(self.pwd())
+            .parent()
+            .map(|p| self.clone().change_directory(p.to_string_lossy().as_ref()))
             .unwrap_or(Ok(self))
     }
 
+    /// The siblings are taken from the listing of the parent directory, which
+    /// is explored along with the present working directory.
+    fn sibling_directory(self, forward: bool) -> Result<Self> {
+        let parent = match PathBuf::from(&self.pwd).parent() {
+            Some(p) => p.to_string_lossy().to_string(),
+            None => return Ok(self),
+        };
+
+        let nodes = match self.directory_buffers.get(&parent) {
+            Some(dir) => &dir.nodes,
+            None => return self.log_error(format!("{} is not explored yet", parent)),
+        };
+
+        let index = match nodes.iter().position(|n| n.absolute_path == self.pwd) {
+            Some(i) => i,
+            None => return Ok(self),
+        };
+
+        let sibling = if forward {
+            nodes.iter().skip(index + 1).find(|n| n.is_dir)
+        } else {
+            nodes.iter().take(index).rev().find(|n| n.is_dir)
+        };
+
+        match sibling.map(|n| n.absolute_path.clone()) {
+            Some(path) => self.change_directory(&path),
+            None => Ok(self),
+        }
+    }
+
     fn last_visited_path(mut self) -> Result<Self> {
         self.history = self.history.visit_last();
         self.pwd = self
@@ -1546,7 +5189,7 @@ impl App {
             .peek()
             .map(|p| p.to_owned())
             .unwrap_or(self.pwd);
-        env::set_current_dir(&self.pwd)?;
+        archive::set_current_dir(&self.pwd)?;
         self.refresh()
     }
 
@@ -1557,402 +5200,2024 @@ impl App {
             .peek()
             .map(|p| p.to_owned())
             .unwrap_or(self.pwd);
-        env::set_current_dir(&self.pwd)?;
+        archive::set_current_dir(&self.pwd)?;
+        self.refresh()
+    }
+
+    /// The characters of the input buffer along with the selected region, if
+    /// any. An empty region marks the point where the input is inserted.
+    fn selected_input(&self) -> Option<(Vec<char>, usize, usize)> {
+        match (&self.input_buffer, self.input_selection) {
+            (Some(buf), Some((start, end))) => {
+                let chars = buf.chars().collect::<Vec<char>>();
+                let end = end.min(chars.len());
+                Some((chars, start.min(end), end))
+            }
+            _ => None,
+        }
+    }
+
+    fn set_selected_input(&mut self, chars: Vec<char>, point: usize) {
+        self.input_buffer = Some(chars.into_iter().collect());
+        self.input_selection = Some((point, point));
+    }
+
+    /// Like `selected_input`, but the input buffer is selected at its end if
+    /// nothing is selected.
+    fn selected_input_or_end(&self) -> Option<(Vec<char>, usize, usize)> {
+        self.selected_input().or_else(|| {
+            self.input_buffer.as_ref().map(|buf| {
+                let chars = buf.chars().collect::<Vec<char>>();
+                let len = chars.len();
+                (chars, len, len)
+            })
+        })
+    }
+
+    /// Move the input cursor to the point returned by the given function,
+    /// called with the characters and the selected region.
+    fn move_input_cursor<F>(mut self, f: F) -> Result<Self>
+    where
+        F: FnOnce(&[char], usize, usize) -> usize,
+    {
+        if let Some((chars, start, end)) = self.selected_input_or_end() {
+            let point = f(&chars, start, end).min(chars.len());
+            self.set_selected_input(chars, point);
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(self)
+    }
+
+    fn input_cursor_left(self) -> Result<Self> {
+        self.move_input_cursor(|_, start, end| {
+            if start == end {
+                start.max(1) - 1
+            } else {
+                start
+            }
+        })
+    }
+
+    fn input_cursor_right(self) -> Result<Self> {
+        self.move_input_cursor(|_, start, end| if start == end { end + 1 } else { end })
+    }
+
+    fn input_cursor_word_left(self) -> Result<Self> {
+        self.move_input_cursor(|chars, start, _| {
+            chars[..start]
+                .iter()
+                .rev()
+                .skip_while(|c| !c.is_ascii_alphanumeric())
+                .skip_while(|c| c.is_ascii_alphanumeric())
+                .count()
+        })
+    }
+
+    fn input_cursor_word_right(self) -> Result<Self> {
+        self.move_input_cursor(|chars, _, end| {
+            chars.len()
+                - chars[end..]
+                    .iter()
+                    .skip_while(|c| !c.is_ascii_alphanumeric())
+                    .skip_while(|c| c.is_ascii_alphanumeric())
+                    .count()
+        })
+    }
+
+    fn input_cursor_home(self) -> Result<Self> {
+        self.move_input_cursor(|_, _, _| 0)
+    }
+
+    fn input_cursor_end(self) -> Result<Self> {
+        self.move_input_cursor(|chars, _, _| chars.len())
+    }
+
+    fn buffer_input(mut self, input: &str) -> Result<Self> {
+        if let Some((mut chars, start, end)) = self.selected_input() {
+            chars.splice(start..end, input.chars());
+            self.set_selected_input(chars, start + input.chars().count());
+        } else if let Some(buf) = self.input_buffer.as_mut() {
+            buf.push_str(input)
+        } else {
+            self.input_buffer = Some(input.to_owned());
+        };
         self.refresh()
     }
 
-    fn buffer_input(mut self, input: &str) -> Result<Self> {
-        if let Some(buf) = self.input_buffer.as_mut() {
-            buf.push_str(input)
+    fn buffer_input_from_key(self, key: Option<Key>) -> Result<Self> {
+        if let Some(c) = key.and_then(|k| k.to_char()) {
+            self.buffer_input(&c.to_string())
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn buffer_input_from_paste(self) -> Result<Self> {
+        if let Some(text) = self.last_paste.clone() {
+            self.buffer_input(&text)
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn set_input_buffer(mut self, string: String) -> Result<Self> {
+        self.input_buffer = Some(string);
+        self.input_selection = None;
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    fn remove_input_buffer_last_character(mut self) -> Result<Self> {
+        if let Some((mut chars, start, end)) = self.selected_input() {
+            let start = if start == end {
+                start.max(1) - 1
+            } else {
+                start
+            };
+            chars.drain(start..end);
+            self.set_selected_input(chars, start);
+        } else if let Some(mut buf) = self.input_buffer {
+            buf.pop();
+            self.input_buffer = Some(buf);
+        };
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    fn remove_input_buffer_next_character(mut self) -> Result<Self> {
+        if let Some((mut chars, start, end)) = self.selected_input_or_end() {
+            let end = if start == end {
+                (end + 1).min(chars.len())
+            } else {
+                end
+            };
+            chars.drain(start..end);
+            self.set_selected_input(chars, start);
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(self)
+    }
+
+    fn remove_input_buffer_to_end(mut self) -> Result<Self> {
+        if let Some((mut chars, start, _)) = self.selected_input_or_end() {
+            chars.truncate(start);
+            self.set_selected_input(chars, start);
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(self)
+    }
+
+    fn remove_input_buffer_last_word(mut self) -> Result<Self> {
+        if let Some((mut chars, start, end)) = self.selected_input() {
+            let start = if start == end {
+                chars[..end]
+                    .iter()
+                    .rev()
+                    .skip_while(|c| !c.is_ascii_alphanumeric())
+                    .skip_while(|c| c.is_ascii_alphanumeric())
+                    .count()
+            } else {
+                start
+            };
+            chars.drain(start..end);
+            self.set_selected_input(chars, start);
+        } else if let Some(buf) = self.input_buffer {
+            let buf = buf
+                .chars()
+                .rev()
+                .skip_while(|c| !c.is_ascii_alphanumeric())
+                .skip_while(|c| c.is_ascii_alphanumeric())
+                .collect::<Vec<char>>()
+                .into_iter()
+                .rev()
+                .collect::<String>();
+
+            self.input_buffer = Some(buf);
+        };
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    fn set_input_buffer_to_focused_name(self) -> Result<Self> {
+        if let Some(name) = self.focused_node().map(|n| n.relative_path.clone()) {
+            self.set_input_buffer(name)
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn set_input_buffer_to_focused_name_selecting_stem(mut self) -> Result<Self> {
+        if let Some(name) = self.focused_node().map(|n| n.relative_path.clone()) {
+            let stem = PathBuf::from(&name)
+                .file_stem()
+                .map(|s| s.to_string_lossy().chars().count())
+                .unwrap_or_default();
+
+            let has_extension = PathBuf::from(&name).extension().is_some();
+            self.input_buffer = Some(name);
+            self.input_selection = if has_extension { Some((0, stem)) } else { None };
+        };
+        self.refresh()
+    }
+
+    fn reset_input_buffer(mut self) -> Result<Self> {
+        self.input_buffer = None;
+        self.input_selection = None;
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    fn focus_by_index(mut self, index: usize) -> Result<Self> {
+        if let Some(dir) = self.directory_buffer_mut() {
+            dir.focus = index.min(dir.total.max(1) - 1);
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(self)
+    }
+
+    fn focus_by_index_from_input(self) -> Result<Self> {
+        let input = self.input_buffer().unwrap_or_default();
+        if let Some(index) = input
+            .strip_prefix('+')
+            .and_then(|i| i.parse::<usize>().ok())
+        {
+            self.focus_next_by_relative_index(index)
+        } else if let Some(index) = input
+            .strip_prefix('-')
+            .and_then(|i| i.parse::<usize>().ok())
+        {
+            self.focus_previous_by_relative_index(index)
+        } else if let Ok(index) = input.parse::<usize>() {
+            self.focus_by_index(index)
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn focus_by_file_name(mut self, name: &str) -> Result<Self> {
+        if let Some(dir_buf) = self.directory_buffer_mut() {
+            if let Some(focus) = dir_buf
+                .clone()
+                .nodes
+                .iter()
+                .enumerate()
+                .find(|(_, n)| n.relative_path == name)
+                .map(|(i, _)| i)
+            {
+                dir_buf.focus = focus;
+                self.msg_out.push_back(MsgOut::Refresh);
+            };
+        };
+        Ok(self)
+    }
+
+    fn focus_path(self, path: &str) -> Result<Self> {
+        let pathbuf = PathBuf::from(path);
+        if let Some(parent) = pathbuf.parent() {
+            if let Some(filename) = pathbuf.file_name() {
+                self.change_directory(parent.to_string_lossy().as_ref())?
+                    .focus_by_file_name(filename.to_string_lossy().as_ref())
+            } else {
+                bail!("invalid path {}", path)
+            }
+        } else {
+            self.change_directory("/")
+        }
+    }
+
+    fn focus_path_from_input(mut self) -> Result<Self> {
+        if let Some(p) = self.input_buffer() {
+            let p = self.normalize_path(&p);
+            self.focus_path(&p)
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn switch_mode(mut self, mode: &str) -> Result<Self> {
+        if let Some(mode) = self.config.modes.get(mode) {
+            self.input_buffer = None;
+            self.input_selection = None;
+            self.mode = mode
+                .to_owned()
+                .sanitized(self.config.general.read_only.unwrap_or_default());
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(self)
+    }
+
+    fn call(mut self, command: Command) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::Call(command));
+        Ok(self)
+    }
+
+    fn call_silently(mut self, command: Command) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::CallSilently(command));
+        Ok(self)
+    }
+
+    fn bash_exec(self, script: String) -> Result<Self> {
+        self.call(Command {
+            command: "bash".into(),
+            args: vec!["-c".into(), script],
+        })
+    }
+
+    fn bash_exec_silently(self, script: String) -> Result<Self> {
+        self.call_silently(Command {
+            command: "bash".into(),
+            args: vec!["-c".into(), script],
+        })
+    }
+
+    fn open_with_type_command(self) -> Result<Self> {
+        if let Some(node) = self.focused_node().cloned() {
+            if let Some(cmd) = self.config.node_types.open_command(&node).cloned() {
+                let mut args = cmd.args;
+                args.push(node.absolute_path);
+                self.call(Command {
+                    command: cmd.command,
+                    args,
+                })
+            } else {
+                let opener = if cfg!(target_os = "macos") {
+                    "open"
+                } else {
+                    "xdg-open"
+                };
+
+                self.call_silently(Command {
+                    command: opener.into(),
+                    args: vec![node.absolute_path],
+                })
+            }
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn pipe_focused_through(mut self, command: String) -> Result<Self> {
+        if let Some(path) = self.focused_node().map(|n| n.absolute_path.clone()) {
+            self.msg_out
+                .push_back(MsgOut::CaptureCommandOutput(command, path));
+        };
+        Ok(self)
+    }
+
+    fn for_each_selection(mut self, command: String) -> Result<Self> {
+        let paths = self
+            .selection
+            .iter()
+            .map(|n| n.absolute_path.clone())
+            .collect::<Vec<String>>();
+
+        if paths.is_empty() {
+            self.log_warning("Nothing is selected".into())
+        } else {
+            self.msg_out
+                .push_back(MsgOut::ForEachSelection(command, paths));
+            Ok(self)
+        }
+    }
+
+    fn diff_selection(mut self) -> Result<Self> {
+        let files = self
+            .selection
+            .iter()
+            .map(|n| (n.absolute_path.clone(), n.is_dir))
+            .collect::<Vec<(String, bool)>>();
+
+        match files.as_slice() {
+            [(_, true), _] | [_, (_, true)] => {
+                self.log_error("Only files can be diffed, not directories".into())
+            }
+            [(a, _), (b, _)] => {
+                let command = self.config().general.diff_command.clone();
+                self.msg_out
+                    .push_back(MsgOut::DiffFiles(a.clone(), b.clone(), command));
+                Ok(self)
+            }
+            _ => self.log_error(format!(
+                "Select exactly two files to diff, {} selected",
+                files.len()
+            )),
+        }
+    }
+
+    fn advance_spinner(mut self) -> Result<Self> {
+        self.spinner_frame = self.spinner_frame.wrapping_add(1);
+        self.refresh()
+    }
+
+    fn set_command_output(mut self, output: CommandOutput) -> Result<Self> {
+        self.command_output = Some(output);
+        self.refresh()
+    }
+
+    fn clear_command_output(mut self) -> Result<Self> {
+        self.command_output = None;
+        self.refresh()
+    }
+
+    fn open_shell(mut self) -> Result<Self> {
+        let shell = match env::var("SHELL") {
+            Ok(shell) if !shell.is_empty() => shell,
+            _ => {
+                self = self.log_info("$SHELL is not set, falling back to /bin/sh".into())?;
+                "/bin/sh".into()
+            }
+        };
+
+        self.msg_out.push_back(MsgOut::OpenShell(Command {
+            command: shell,
+            args: vec![],
+        }));
+        self.explore()
+    }
+
+    fn add_directory(mut self, parent: String, mut dir: DirectoryBuffer) -> Result<Self> {
+        // Keep the listing scrolled where it was.
+        dir.offset = self.directory_buffers.get(&parent).and_then(|d| d.offset);
+        self.directory_buffers.insert(parent, dir);
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    /// Add the nodes to the selection, up to the `max_selection` limit. The
+    /// nodes beyond the limit are left out with a warning.
+    fn select_nodes(mut self, nodes: Vec<Node>) -> Result<Self> {
+        let max = self
+            .config
+            .general
+            .max_selection
+            .filter(|m| *m > 0)
+            .unwrap_or(usize::MAX);
+
+        let mut refused = 0;
+        for node in nodes {
+            if self.selection.len() < max || self.selection.contains(&node) {
+                self.selection.insert(node);
+            } else {
+                refused += 1;
+            };
+        }
+        self.msg_out.push_back(MsgOut::Refresh);
+
+        if refused > 0 {
+            self.log_warning(format!(
+                "Cannot select more than {} nodes, {} left out",
+                max, refused
+            ))
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn select(self) -> Result<Self> {
+        if let Some(n) = self.focused_node().map(|n| n.to_owned()) {
+            self.select_nodes(vec![n])
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn select_path(self, path: String) -> Result<Self> {
+        let path = PathBuf::from(path);
+        let parent = path.parent().map(|p| p.to_string_lossy().to_string());
+        let filename = path.file_name().map(|p| p.to_string_lossy().to_string());
+        if let (Some(p), Some(n)) = (parent, filename) {
+            self.select_nodes(vec![Node::new(p, n)])
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn select_all(self) -> Result<Self> {
+        if let Some(nodes) = self.directory_buffer().map(|d| d.nodes.clone()) {
+            self.select_nodes(nodes)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// The visible nodes passing the filter.
+    fn nodes_matching(&self, filter: &NodeFilterApplicable) -> Vec<Node> {
+        let selection = self.selected_paths();
+        self.directory_buffer()
+            .map(|d| {
+                d.nodes
+                    .iter()
+                    .filter(|n| filter.apply(n, &selection, &self.explorer_config.reference_names))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn select_by_filter(self, filter: &NodeFilterApplicable) -> Result<Self> {
+        let app = self.list_reference_names(filter)?;
+        let nodes = app.nodes_matching(filter);
+        app.select_nodes(nodes)
+    }
+
+    fn un_select_by_filter(self, filter: &NodeFilterApplicable) -> Result<Self> {
+        let mut app = self.list_reference_names(filter)?;
+        let nodes = app.nodes_matching(filter);
+        app.selection.retain(|n| !nodes.contains(n));
+        app.msg_out.push_back(MsgOut::Refresh);
+        Ok(app)
+    }
+
+    fn un_select(mut self) -> Result<Self> {
+        if let Some(n) = self.focused_node().map(|n| n.to_owned()) {
+            self.selection.retain(|s| s != &n);
+            self.msg_out.push_back(MsgOut::Refresh);
+        }
+        Ok(self)
+    }
+
+    fn un_select_path(mut self, path: String) -> Result<Self> {
+        self.selection.retain(|n| n.absolute_path != path);
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    fn un_select_all(mut self) -> Result<Self> {
+        if let Some(d) = self.directory_buffer() {
+            d.nodes.clone().into_iter().for_each(|n| {
+                self.selection.retain(|s| s != &n);
+            });
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+
+        Ok(self)
+    }
+
+    fn toggle_selection(mut self) -> Result<Self> {
+        if let Some(n) = self.focused_node() {
+            if self.selection().contains(n) {
+                self = self.un_select()?;
+            } else {
+                self = self.select()?;
+            }
+        }
+        Ok(self)
+    }
+
+    fn toggle_select_all(self) -> Result<Self> {
+        if let Some(d) = self.directory_buffer() {
+            if d.nodes.iter().all(|n| self.selection.contains(n)) {
+                self.un_select_all()
+            } else {
+                self.select_all()
+            }
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn toggle_selection_by_path(self, path: String) -> Result<Self> {
+        if self.selection.iter().any(|n| n.absolute_path == path) {
+            self.select_path(path)
+        } else {
+            self.un_select_path(path)
+        }
+    }
+
+    fn clear_selection(mut self) -> Result<Self> {
+        self.selection.clear();
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    fn add_to_register(mut self, register: char) -> Result<Self> {
+        let paths: Vec<String> = self
+            .result()
+            .into_iter()
+            .map(|n| n.absolute_path.clone())
+            .collect();
+        let paths_in_register = self.registers.entry(register).or_default();
+        paths_in_register.extend(paths);
+        let len = paths_in_register.len();
+        self.msg_out.push_back(MsgOut::Refresh);
+        self.log_info(format!("{} nodes in register {}", len, register))
+    }
+
+    fn clear_register(mut self, register: char) -> Result<Self> {
+        self.registers.remove(&register);
+        self.msg_out.push_back(MsgOut::Refresh);
+        Ok(self)
+    }
+
+    fn select_register(self, register: char) -> Result<Self> {
+        if let Some(paths) = self.registers.get(&register) {
+            let listed = self.directory_buffer().map(|d| &d.nodes);
+            let nodes = paths
+                .iter()
+                .filter_map(|path| {
+                    listed
+                        .and_then(|nodes| nodes.iter().find(|n| &n.absolute_path == path))
+                        .cloned()
+                        .or_else(|| {
+                            let path = PathBuf::from(path);
+                            let parent = path.parent()?.to_string_lossy().to_string();
+                            let filename = path.file_name()?.to_string_lossy().to_string();
+                            Some(Node::new(parent, filename))
+                        })
+                })
+                .collect();
+            self.select_nodes(nodes)
+        } else {
+            self.log_error(format!("register {} is empty", register))
+        }
+    }
+
+    /// List the reference directory of a `NameNotIn` filter once, for the
+    /// filter to consult.
+    fn list_reference_names(mut self, filter: &NodeFilterApplicable) -> Result<Self> {
+        if filter.filter != NodeFilter::NameNotIn
+            || self
+                .explorer_config
+                .reference_names
+                .contains_key(&filter.input)
+        {
+            return Ok(self);
+        };
+
+        let dir = self.normalize_path(&filter.input);
+        let names = fs::read_dir(&dir).and_then(|entries| {
+            entries
+                .map(|e| e.map(|e| e.file_name().to_string_lossy().to_string()))
+                .collect::<io::Result<HashSet<String>>>()
+        });
+
+        match names {
+            Ok(names) => {
+                self.explorer_config
+                    .reference_names
+                    .insert(filter.input.clone(), Some(names));
+                Ok(self)
+            }
+            Err(e) => {
+                self.explorer_config
+                    .reference_names
+                    .insert(filter.input.clone(), None);
+                self.log_error(format!("Cannot list {}: {}", dir, e))
+            }
+        }
+    }
+
+    fn add_node_filter(self, filter: NodeFilterApplicable) -> Result<Self> {
+        let mut app = self.list_reference_names(&filter)?;
+        app.explorer_config.filters.replace(filter);
+        Ok(app)
+    }
+
+    fn add_node_filter_from_input(self, filter: NodeFilter) -> Result<Self> {
+        match self.input_buffer() {
+            Some(input) => self.add_node_filter(NodeFilterApplicable::new(filter, input)),
+            None => Ok(self),
+        }
+    }
+
+    fn remove_node_filter(mut self, filter: NodeFilterApplicable) -> Result<Self> {
+        self.explorer_config.filters.retain(|f| f != &filter);
+        Ok(self)
+    }
+
+    fn remove_node_filter_from_input(mut self, filter: NodeFilter) -> Result<Self> {
+        if let Some(input) = self.input_buffer() {
+            let nfa = NodeFilterApplicable::new(filter, input);
+            self.explorer_config.filters.retain(|f| f != &nfa);
+        };
+        Ok(self)
+    }
+
+    fn toggle_node_filter(self, filter: NodeFilterApplicable) -> Result<Self> {
+        if self.explorer_config.filters.contains(&filter) {
+            self.remove_node_filter(filter)
+        } else {
+            self.add_node_filter(filter)
+        }
+    }
+
+    fn filter_to_selection(self) -> Result<Self> {
+        self.add_node_filter(NodeFilterApplicable::new(NodeFilter::IsSelected, "".into()))
+    }
+
+    fn remove_last_node_filter(mut self) -> Result<Self> {
+        self.explorer_config.filters.pop();
+        Ok(self)
+    }
+
+    fn reset_node_filters(mut self) -> Result<Self> {
+        self.explorer_config.filters.clear();
+
+        if let Some(filter) =
+            hidden_filter(self.config.general.show_hidden.unwrap_or(HiddenMode::Hide))
+        {
+            self.add_node_filter(filter)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// The hidden mode as derived from the filters being applied.
+    pub fn hidden_mode(&self) -> HiddenMode {
+        [HiddenMode::Hide, HiddenMode::Only]
+            .iter()
+            .find(|m| {
+                hidden_filter(**m)
+                    .map(|f| self.explorer_config.filters.contains(&f))
+                    .unwrap_or_default()
+            })
+            .copied()
+            .unwrap_or(HiddenMode::Show)
+    }
+
+    fn toggle_tree_view(mut self) -> Result<Self> {
+        self.explorer_config.tree = if self.explorer_config.tree.is_some() {
+            None
+        } else {
+            let tree_view = &self.config.general.tree_view;
+            Some(TreeListing {
+                max_depth: tree_view.max_depth.unwrap_or(DEFAULT_TREE_MAX_DEPTH),
+                max_nodes: tree_view.max_nodes.unwrap_or(DEFAULT_TREE_MAX_NODES),
+            })
+        };
+        Ok(self)
+    }
+
+    fn toggle_preview_pane_shows_children(mut self) -> Result<Self> {
+        self.preview_children = !self.preview_children;
+        self.preview = None;
+        self.refresh()
+    }
+
+    fn toggle_full_help(mut self) -> Result<Self> {
+        self.full_help = match self.full_help {
+            Some(_) => None,
+            None => Some(0),
+        };
+        self.refresh()
+    }
+
+    /// The keys pressed while the full help is shown only scroll or close it.
+    fn handle_full_help_key(mut self, scroll: usize, key: Key) -> Result<Self> {
+        let last = self.mode.help_menu().len().saturating_sub(1);
+        self.full_help = match key {
+            Key::Up => Some(scroll.saturating_sub(1)),
+            Key::Down => Some((scroll + 1).min(last)),
+            Key::PageUp => Some(scroll.saturating_sub(10)),
+            Key::PageDown => Some((scroll + 10).min(last)),
+            _ => None,
+        };
+        self.refresh()
+    }
+
+    fn show_errors(mut self) -> Result<Self> {
+        self.errors_shown = Some(0);
+        self.refresh()
+    }
+
+    /// The keys pressed while the errors are shown only scroll or close them.
+    fn handle_errors_key(mut self, scroll: usize, key: Key) -> Result<Self> {
+        let last = self.errors().len().saturating_sub(1);
+        self.errors_shown = match key {
+            Key::Up => Some(scroll.saturating_sub(1)),
+            Key::Down => Some((scroll + 1).min(last)),
+            Key::PageUp => Some(scroll.saturating_sub(10)),
+            Key::PageDown => Some((scroll + 10).min(last)),
+            _ => None,
+        };
+        self.refresh()
+    }
+
+    fn enter_type_ahead_mode(mut self) -> Result<Self> {
+        self.type_ahead = Some(TypeAhead {
+            prefix: Default::default(),
+            last_key: Instant::now(),
+        });
+        self.refresh()
+    }
+
+    /// The keys typed ahead extend the prefix and move the focus, any other
+    /// key stops typing ahead and is handled as usual.
+    fn handle_type_ahead_key(mut self, mut type_ahead: TypeAhead, key: Key) -> Result<Self> {
+        let timeout = Duration::from_millis(
+            self.config
+                .general
+                .type_ahead_timeout
+                .unwrap_or(DEFAULT_TYPE_AHEAD_TIMEOUT),
+        );
+
+        match key {
+            Key::Esc | Key::Enter => return self.refresh(),
+            Key::Backspace => {
+                type_ahead.prefix.pop();
+            }
+            _ => match key.to_char() {
+                Some(c) => {
+                    if type_ahead.last_key.elapsed() > timeout {
+                        type_ahead.prefix.clear();
+                    };
+                    type_ahead.prefix.push(c);
+                }
+                None => return self.refresh()?.handle_key(key),
+            },
+        };
+
+        type_ahead.last_key = Instant::now();
+        let prefix = type_ahead.prefix.clone();
+        self.type_ahead = Some(type_ahead);
+
+        if prefix.is_empty() {
+            return self.refresh();
+        };
+
+        let case_sensitive = self
+            .config
+            .general
+            .type_ahead_case_sensitive
+            .unwrap_or_default();
+        let starts_with = |name: &str| {
+            if case_sensitive {
+                name.starts_with(&prefix)
+            } else {
+                name.to_lowercase().starts_with(&prefix.to_lowercase())
+            }
+        };
+
+        let found = self.directory_buffer().and_then(|dir| {
+            dir.nodes.iter().position(|n| {
+                Path::new(&n.relative_path)
+                    .file_name()
+                    .map(|name| starts_with(&name.to_string_lossy()))
+                    .unwrap_or(false)
+            })
+        });
+
+        match found {
+            Some(index) => self.focus_by_index(index),
+            None => self.refresh(),
+        }
+    }
+
+    fn set_preview(mut self, dir: DirectoryBuffer) -> Result<Self> {
+        if self.preview_path().as_ref() == Some(&dir.parent) {
+            self.preview = Some(dir);
+            self.msg_out.push_back(MsgOut::Refresh);
+        };
+        Ok(self)
+    }
+
+    fn quick_filter(mut self, term: String) -> Result<Self> {
+        self.explorer_config.quick_filter = Some(term).filter(|t| !t.is_empty());
+        self.explore()
+    }
+
+    fn clear_quick_filter(mut self) -> Result<Self> {
+        self.explorer_config.quick_filter = None;
+        self.explore()
+    }
+
+    fn reverse_listing(mut self) -> Result<Self> {
+        self.explorer_config.reversed = !self.explorer_config.reversed;
+        Ok(self)
+    }
+
+    fn toggle_expand(mut self) -> Result<Self> {
+        match self.focused_node().filter(|n| n.is_dir).cloned() {
+            Some(node) => {
+                let expanded = !self.explorer_config.is_expanded(&node);
+                self.explorer_config
+                    .expansions
+                    .insert(node.absolute_path, expanded);
+                self.explore()
+            }
+            None => Ok(self),
+        }
+    }
+
+    fn cycle_hidden_mode(mut self) -> Result<Self> {
+        let mode = self.hidden_mode().next();
+        let filters: Vec<NodeFilterApplicable> = [HiddenMode::Hide, HiddenMode::Only]
+            .iter()
+            .filter_map(|m| hidden_filter(*m))
+            .collect();
+
+        self.explorer_config
+            .filters
+            .retain(|f| !filters.contains(f));
+
+        if let Some(filter) = hidden_filter(mode) {
+            self.explorer_config.filters.insert(filter);
+        };
+        Ok(self)
+    }
+    fn clear_node_filters(mut self) -> Result<Self> {
+        self.explorer_config.filters.clear();
+        Ok(self)
+    }
+
+    fn toggle_filters_enabled(mut self) -> Result<Self> {
+        self.explorer_config.filters_suspended = !self.explorer_config.filters_suspended;
+        Ok(self)
+    }
+
+    fn add_node_sorter(mut self, sorter: NodeSorterApplicable) -> Result<Self> {
+        self.explorer_config.sorters.replace(sorter);
+        Ok(self)
+    }
+
+    fn sort_by_column(mut self, column: usize) -> Result<Self> {
+        let col_sorters = self
+            .config
+            .general
+            .table
+            .col_sorters
+            .clone()
+            .unwrap_or_default();
+
+        let sorter = match col_sorters.get(column).cloned().flatten() {
+            Some(sorter) => sorter,
+            None => return self.log_error(format!("Column {} has no sorter", column)),
+        };
+
+        if self
+            .explorer_config
+            .sorters
+            .iter()
+            .any(|s| s.sorter == sorter)
+        {
+            self.reverse_node_sorter(sorter)
+        } else {
+            self.explorer_config
+                .sorters
+                .retain(|s| !col_sorters.contains(&Some(s.sorter)));
+            self.add_node_sorter(NodeSorterApplicable {
+                sorter,
+                reverse: false,
+                regex: None,
+            })
+        }
+    }
+
+    fn sort_by_column_from_key(self, key: Option<Key>) -> Result<Self> {
+        let column = key
+            .and_then(|k| k.to_char())
+            .and_then(|c| c.to_digit(10))
+            .and_then(|n| (n as usize).checked_sub(1));
+
+        if let Some(column) = column {
+            self.sort_by_column(column)
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn remove_node_sorter(mut self, sorter: NodeSorter) -> Result<Self> {
+        self.explorer_config.sorters.retain(|s| s.sorter != sorter);
+        Ok(self)
+    }
+
+    fn reverse_node_sorter(mut self, sorter: NodeSorter) -> Result<Self> {
+        self.explorer_config.sorters = self
+            .explorer_config
+            .sorters
+            .into_iter()
+            .map(|s| if s.sorter == sorter { s.reversed() } else { s })
+            .collect();
+        Ok(self)
+    }
+
+    fn toggle_node_sorter(self, sorter: NodeSorterApplicable) -> Result<Self> {
+        if self.explorer_config.sorters.contains(&sorter) {
+            self.remove_node_sorter(sorter.sorter)
+        } else {
+            self.add_node_sorter(sorter)
+        }
+    }
+
+    fn remove_last_node_sorter(mut self) -> Result<Self> {
+        self.explorer_config.sorters.pop();
+        Ok(self)
+    }
+
+    fn reverse_node_sorters(mut self) -> Result<Self> {
+        self.explorer_config.sorters = self
+            .explorer_config
+            .sorters
+            .into_iter()
+            .map(|s| s.reversed())
+            .collect();
+        Ok(self)
+    }
+
+    fn reset_node_sorters(mut self) -> Result<Self> {
+        self.explorer_config.sorters = self
+            .config
+            .general
+            .initial_sorting
+            .to_owned()
+            .unwrap_or_default();
+        Ok(self)
+    }
+
+    fn clear_node_sorters(mut self) -> Result<Self> {
+        self.explorer_config.sorters.clear();
+        Ok(self)
+    }
+
+    fn toggle_read_only(mut self, key: Option<Key>) -> Result<Self> {
+        let read_only = !self.config.general.read_only.unwrap_or_default();
+        if !read_only && key.is_none() {
+            return self.log_error("Read-only mode can only be turned off by a key.".into());
+        };
+
+        self.config.general.read_only = Some(read_only);
+
+        // The active mode has been sanitized already, so we need to derive it
+        // again from the configuration.
+        if let Some(mode) = self.config.modes.get(&self.mode.name) {
+            self.mode = mode.to_owned().sanitized(read_only);
+        };
+
+        self.msg_out.push_back(MsgOut::Refresh);
+        if read_only {
+            self.log_info("read-only mode enabled".into())
+        } else {
+            self.log_info("read-only mode disabled".into())
+        }
+    }
+
+    fn toggle_compact(mut self) -> Result<Self> {
+        let compact = !self.config.general.compact.unwrap_or_default();
+        self.config.general.compact = Some(compact);
+        self.refresh()
+    }
+
+    fn toggle_size_unit_base(mut self) -> Result<Self> {
+        self.config.general.size_unit_base = Some(match self.size_unit_base() {
+            SizeUnitBase::Si => SizeUnitBase::Binary,
+            SizeUnitBase::Binary => SizeUnitBase::Si,
+        });
+        self.refresh()
+    }
+
+    fn toggle_persist_explorer_config(mut self) -> Result<Self> {
+        let persist = !self
+            .config
+            .general
+            .persist_explorer_config_across_directories
+            .unwrap_or(true);
+        self.config
+            .general
+            .persist_explorer_config_across_directories = Some(persist);
+
+        if persist {
+            self.log_info("sorters and filters persist across directories".into())
+        } else {
+            self.log_info("sorters and filters reset on each directory change".into())
+        }
+    }
+
+    fn record(mut self, op: FsOperation) -> Self {
+        self.undo_stack.push(op);
+        if self.undo_stack.len() > MAX_UNDO_STACK_SIZE {
+            self.undo_stack.remove(0);
+        };
+        self.redo_stack.clear();
+        self
+    }
+
+    fn rename(self, name: &str) -> Result<Self> {
+        if let Some(from) = self.focused_node().map(|n| n.absolute_path.clone()) {
+            let to = PathBuf::from(&self.pwd)
+                .join(name)
+                .to_string_lossy()
+                .to_string();
+
+            match FsOperation::move_path(&from, &to) {
+                Ok(op) => Ok(self
+                    .record(op)
+                    .log_success(format!("{} renamed to {}", from, to))?
+                    .explore()?
+                    .enqueue(Task::new(MsgIn::External(ExternalMsg::FocusPath(to)), None))),
+                Err(e) => self.log_error(format!("Failed to rename {}: {}", from, e)),
+            }
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn set_permissions(mut self, mode: String) -> Result<Self> {
+        let nodes: Vec<Node> = self.result().into_iter().cloned().collect();
+        for node in nodes {
+            self = match FsOperation::set_permissions(&node.absolute_path, &mode) {
+                Ok(op) => {
+                    let message = op.to_string();
+                    self.record(op).log_success(message)?
+                }
+                Err(e) => self.log_error(format!(
+                    "Failed to set the permissions of {}: {}",
+                    node.absolute_path, e
+                ))?,
+            };
+        }
+        self.explore()
+    }
+
+    fn set_permissions_from_input(self) -> Result<Self> {
+        let mode = self.input_buffer().unwrap_or_default();
+        self.set_permissions(mode)
+    }
+
+    fn set_owner(mut self, owner: String) -> Result<Self> {
+        let nodes: Vec<Node> = self.result().into_iter().cloned().collect();
+        for node in nodes {
+            self = match FsOperation::set_owner(&node.absolute_path, &owner) {
+                Ok(op) => {
+                    let message = op.to_string();
+                    self.record(op).log_success(message)?
+                }
+                Err(e) => self.log_error(format!(
+                    "Failed to change the owner of {}: {}",
+                    node.absolute_path, e
+                ))?,
+            };
+        }
+        self.explore()
+    }
+
+    fn git_stage(mut self) -> Result<Self> {
+        let nodes: Vec<Node> = self.result().into_iter().cloned().collect();
+        for node in nodes {
+            self = match git_status::stage(&node.absolute_path) {
+                Ok(()) => self.log_success(format!("staged {}", node.absolute_path))?,
+                Err(e) => self.log_error(format!(
+                    "Failed to stage {}: {}",
+                    node.absolute_path,
+                    e.message()
+                ))?,
+            };
+        }
+        self.explore()
+    }
+
+    fn git_unstage(mut self) -> Result<Self> {
+        let nodes: Vec<Node> = self.result().into_iter().cloned().collect();
+        for node in nodes {
+            self = match git_status::unstage(&node.absolute_path) {
+                Ok(()) => self.log_success(format!("unstaged {}", node.absolute_path))?,
+                Err(e) => self.log_error(format!(
+                    "Failed to unstage {}: {}",
+                    node.absolute_path,
+                    e.message()
+                ))?,
+            };
+        }
+        self.explore()
+    }
+
+    fn set_owner_from_input(self) -> Result<Self> {
+        let owner = self.input_buffer().unwrap_or_default();
+        self.set_owner(owner)
+    }
+
+    fn rename_from_input(mut self) -> Result<Self> {
+        if let Some(name) = self.input_buffer() {
+            let name = self.normalize_path(&name);
+            self.rename(&name)
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn create_directory(self, path: &str) -> Result<Self> {
+        let deepest_dir = |p: &Path| p.ancestors().find(|a| a.is_dir()).map(Path::to_path_buf);
+        let pathbuf = PathBuf::from(path);
+        let existing = deepest_dir(&pathbuf);
+
+        if let Err(e) = fs::create_dir_all(&pathbuf) {
+            let reached = deepest_dir(&pathbuf)
+                .filter(|d| Some(d) != existing.as_ref())
+                .map(|r| format!(" (created up to {})", r.to_string_lossy()))
+                .unwrap_or_default();
+            return self.log_error(format!("Failed to create {}: {}{}", path, e, reached));
+        };
+
+        Ok(self
+            .log_success(format!("{} created", path))?
+            .explore()?
+            .enqueue(Task::new(
+                MsgIn::External(ExternalMsg::FocusPath(path.into())),
+                None,
+            )))
+    }
+
+    fn create_directory_from_input(mut self) -> Result<Self> {
+        if let Some(path) = self.input_buffer() {
+            let path = self.normalize_path(&path);
+            self.create_directory(&path)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Find the templates having the same extension as the given path.
+    fn find_templates(&self, path: &str) -> Vec<String> {
+        let templates = &self.config.general.templates;
+        let dir = match templates.directory.as_ref().map(|d| expand_path(d)) {
+            Some(Ok(dir)) => dir,
+            _ => return vec![],
+        };
+
+        let extension = PathBuf::from(path).extension().map(|e| e.to_owned());
+        let mut templates = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.is_file() && p.extension().map(|e| e.to_owned()) == extension)
+                    .map(|p| p.to_string_lossy().to_string())
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default();
+        templates.sort();
+        templates
+    }
+
+    fn create_file(self, path: &str) -> Result<Self> {
+        let templates = self.find_templates(path);
+        let selection = self
+            .config
+            .general
+            .templates
+            .selection
+            .unwrap_or(TemplateSelection::Extension);
+
+        if templates.is_empty() || selection == TemplateSelection::Extension {
+            let template = templates.into_iter().next();
+            self.create_file_from_template(FileFromTemplate {
+                path: path.into(),
+                template,
+            })
         } else {
-            self.input_buffer = Some(input.to_owned());
-        };
-        self.refresh()
+            let to_msg = |template: Option<String>| {
+                vec![ExternalMsg::CreateFileFromTemplate(FileFromTemplate {
+                    path: path.into(),
+                    template,
+                })]
+            };
+
+            let items = std::iter::once(PickerItem::new("(empty)".into(), to_msg(None)))
+                .chain(
+                    templates
+                        .into_iter()
+                        .map(|t| PickerItem::new(t.clone(), to_msg(Some(t)))),
+                )
+                .collect();
+
+            self.open_picker(Picker::new(format!("Template for {}", path), items))
+        }
     }
 
-    fn buffer_input_from_key(self, key: Option<Key>) -> Result<Self> {
-        if let Some(c) = key.and_then(|k| k.to_char()) {
-            self.buffer_input(&c.to_string())
+    fn create_file_from_input(mut self) -> Result<Self> {
+        if let Some(path) = self.input_buffer() {
+            let path = self.normalize_path(&path);
+            self.create_file(&path)
         } else {
             Ok(self)
         }
     }
 
-    fn set_input_buffer(mut self, string: String) -> Result<Self> {
-        self.input_buffer = Some(string);
-        self.msg_out.push_back(MsgOut::Refresh);
-        Ok(self)
+    fn create_file_from_template(self, file: FileFromTemplate) -> Result<Self> {
+        let contents = match file.template.as_ref().map(fs::read) {
+            Some(Ok(contents)) => contents,
+            Some(Err(e)) => {
+                return self.log_error(format!("Failed to read template: {}", e));
+            }
+            None => vec![],
+        };
+
+        let created = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&file.path)
+            .and_then(|mut f| io::Write::write_all(&mut f, &contents));
+
+        match created {
+            Ok(()) => Ok(self
+                .log_success(format!("{} created", file.path))?
+                .explore()?
+                .enqueue(Task::new(
+                    MsgIn::External(ExternalMsg::FocusPath(file.path)),
+                    None,
+                ))),
+            Err(e) => self.log_error(format!("Failed to create {}: {}", file.path, e)),
+        }
     }
 
-    fn remove_input_buffer_last_character(mut self) -> Result<Self> {
-        if let Some(mut buf) = self.input_buffer {
-            buf.pop();
-            self.input_buffer = Some(buf);
+    /// The normalized link path, and the target as it should be stored.
+    fn symlink_paths(&mut self, symlink: &Symlink) -> (String, String) {
+        let link = self.normalize_path(&symlink.link);
+        let target = self.normalize_path(&symlink.target);
+        let target = match Path::new(&link).parent() {
+            Some(dir) if symlink.relative => relative_path(dir, Path::new(&target))
+                .to_string_lossy()
+                .to_string(),
+            _ => target,
         };
-        self.msg_out.push_back(MsgOut::Refresh);
-        Ok(self)
+        (link, target)
     }
 
-    fn remove_input_buffer_last_word(mut self) -> Result<Self> {
-        if let Some(buf) = self.input_buffer {
-            let buf = buf
-                .chars()
-                .into_iter()
-                .rev()
-                .skip_while(|c| !c.is_ascii_alphanumeric())
-                .skip_while(|c| c.is_ascii_alphanumeric())
-                .collect::<Vec<char>>()
-                .into_iter()
-                .rev()
-                .collect::<String>();
-
-            self.input_buffer = Some(buf);
+    fn create_symlink(mut self, symlink: Symlink) -> Result<Self> {
+        let (link, target) = self.symlink_paths(&symlink);
+        let existing = Path::new(&link).symlink_metadata().ok();
+
+        let created = match existing {
+            None => FsOperation::create_symlink(&link, &target),
+            Some(_) if !symlink.force => {
+                return self.log_error(format!(
+                    "Failed to create {}: it already exists, use force to replace it",
+                    link
+                ));
+            }
+            Some(meta) if meta.file_type().is_symlink() => {
+                FsOperation::retarget_symlink(&link, &target)
+            }
+            Some(meta) if meta.is_dir() => {
+                return self.log_error(format!("Failed to create {}: it's a directory", link));
+            }
+            // Trashed, and restored along with undoing the link.
+            Some(_) => FsOperation::trash(&link).and_then(|trashed| {
+                match FsOperation::create_symlink(&link, &target) {
+                    Ok(created) => Ok(FsOperation::Batch(vec![trashed, created])),
+                    Err(e) => {
+                        trashed.undo().unwrap_or_default();
+                        Err(e)
+                    }
+                }
+            }),
         };
-        self.msg_out.push_back(MsgOut::Refresh);
-        Ok(self)
-    }
 
-    fn reset_input_buffer(mut self) -> Result<Self> {
-        self.input_buffer = None;
-        self.msg_out.push_back(MsgOut::Refresh);
-        Ok(self)
+        self.finish_symlink(created, &link)
     }
 
-    fn focus_by_index(mut self, index: usize) -> Result<Self> {
-        if let Some(dir) = self.directory_buffer_mut() {
-            dir.focus = index.min(dir.total.max(1) - 1);
-            self.msg_out.push_back(MsgOut::Refresh);
-        };
-        Ok(self)
+    fn retarget_symlink(mut self, symlink: Symlink) -> Result<Self> {
+        let (link, target) = self.symlink_paths(&symlink);
+        let retargeted = FsOperation::retarget_symlink(&link, &target);
+        self.finish_symlink(retargeted, &link)
     }
 
-    fn focus_by_index_from_input(self) -> Result<Self> {
-        if let Some(index) = self.input_buffer().and_then(|i| i.parse::<usize>().ok()) {
-            self.focus_by_index(index)
-        } else {
-            Ok(self)
+    fn finish_symlink(self, result: Result<FsOperation>, link: &str) -> Result<Self> {
+        match result {
+            Ok(op) => {
+                let message = op.to_string();
+                Ok(self
+                    .record(op)
+                    .log_success(message)?
+                    .explore()?
+                    .enqueue(Task::new(
+                        MsgIn::External(ExternalMsg::FocusPath(link.into())),
+                        None,
+                    )))
+            }
+            Err(e) => self.log_error(format!("Failed to link {}: {}", link, e)),
         }
     }
 
-    fn focus_by_file_name(mut self, name: &str) -> Result<Self> {
-        if let Some(dir_buf) = self.directory_buffer_mut() {
-            if let Some(focus) = dir_buf
-                .clone()
-                .nodes
-                .iter()
-                .enumerate()
-                .find(|(_, n)| n.relative_path == name)
-                .map(|(i, _)| i)
-            {
-                dir_buf.focus = focus;
-                self.msg_out.push_back(MsgOut::Refresh);
-            };
+    fn paste_conflict(&self) -> PasteConflict {
+        self.config
+            .general
+            .paste_conflict
+            .unwrap_or(PasteConflict::Ask)
+    }
+
+    fn copy_selection_here(self) -> Result<Self> {
+        let conflict = self.paste_conflict();
+        self.transfer_selection_here(TransferKind::Copy, conflict)
+    }
+
+    fn move_selection_here(self) -> Result<Self> {
+        let conflict = self.paste_conflict();
+        self.transfer_selection_here(TransferKind::Move, conflict)
+    }
+
+    fn transfer_selection_here(
+        mut self,
+        kind: TransferKind,
+        conflict: PasteConflict,
+    ) -> Result<Self> {
+        if self.transfer.is_some() {
+            return self.log_error("Another copy or move is in progress".into());
         };
-        Ok(self)
+
+        let items: Vec<(String, String)> = self
+            .selection
+            .iter()
+            .map(|n| {
+                let to = PathBuf::from(&self.pwd)
+                    .join(&n.relative_path)
+                    .to_string_lossy()
+                    .to_string();
+                (n.absolute_path.clone(), to)
+            })
+            .collect();
+
+        if items.is_empty() {
+            return Ok(self);
+        };
+
+        self.selection.clear();
+        self.transfer_resolving(kind, items, conflict)
     }
 
-    fn focus_path(self, path: &str) -> Result<Self> {
-        let pathbuf = PathBuf::from(path);
-        if let Some(parent) = pathbuf.parent() {
-            if let Some(filename) = pathbuf.file_name() {
-                self.change_directory(&parent.to_string_lossy().to_string())?
-                    .focus_by_file_name(&filename.to_string_lossy().to_string())
+    /// Copy or move the paths, dealing with the ones that already exist at
+    /// the destination the given way.
+    fn transfer_resolving(
+        mut self,
+        kind: TransferKind,
+        items: Vec<(String, String)>,
+        conflict: PasteConflict,
+    ) -> Result<Self> {
+        let mut pending = PendingTransfer {
+            transfer: Transfer {
+                kind,
+                items: Default::default(),
+                replace: Default::default(),
+            },
+            conflicts: Default::default(),
+        };
+
+        // Two paths of the same name conflict with each other too.
+        let mut conflicts = vec![];
+        for (from, to) in items {
+            if pending.is_taken(&to) {
+                conflicts.push((from, to));
             } else {
-                bail!("invalid path {}", path)
-            }
-        } else {
-            self.change_directory("/")
+                pending.transfer.items.push((from, to));
+            };
         }
+        for item in conflicts {
+            pending.resolve(item, conflict);
+        }
+
+        self.pending_transfer = Some(pending);
+        self.resolve_next_transfer_conflict()
     }
 
-    fn focus_path_from_input(self) -> Result<Self> {
-        if let Some(p) = self.input_buffer() {
-            self.focus_path(&p)
+    /// Ask about the next existing path in a picker, or start the transfer
+    /// once they're all dealt with.
+    fn resolve_next_transfer_conflict(mut self) -> Result<Self> {
+        let pending = match self.pending_transfer.take() {
+            Some(pending) => pending,
+            None => return Ok(self),
+        };
+
+        if let Some((_, to)) = pending.conflicts.front().cloned() {
+            let renamed = PathBuf::from(pending.renamed(&to))
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            let resolve = |conflict| vec![ExternalMsg::ResolveTransferConflict(conflict)];
+            let items = vec![
+                PickerItem::new("Overwrite".into(), resolve(PasteConflict::Overwrite)),
+                PickerItem::new(
+                    format!("Rename to {}", renamed),
+                    resolve(PasteConflict::Rename),
+                ),
+                PickerItem::new("Skip".into(), resolve(PasteConflict::Skip)),
+            ];
+
+            self.pending_transfer = Some(pending);
+            self.open_picker(Picker::new(format!("{} already exists", to), items))
+        } else if pending.transfer.items.is_empty() {
+            self.log_info("Nothing left to copy or move".into())
         } else {
-            Ok(self)
+            self.start_transfer(pending.transfer)
         }
     }
 
-    fn switch_mode(mut self, mode: &str) -> Result<Self> {
-        if let Some(mode) = self.config.modes.get(mode) {
-            self.input_buffer = None;
-            self.mode = mode
-                .to_owned()
-                .sanitized(self.config.general.read_only.unwrap_or_default());
-            self.msg_out.push_back(MsgOut::Refresh);
+    fn resolve_transfer_conflict(mut self, conflict: PasteConflict) -> Result<Self> {
+        if let Some(pending) = self.pending_transfer.as_mut() {
+            if let Some(item) = pending.conflicts.pop_front() {
+                pending.resolve(item, conflict);
+            };
         };
-        Ok(self)
+        self.resolve_next_transfer_conflict()
     }
 
-    fn call(mut self, command: Command) -> Result<Self> {
-        self.msg_out.push_back(MsgOut::Call(command));
+    fn extract_selection(mut self) -> Result<Self> {
+        let paths = self
+            .selection
+            .iter()
+            .map(|n| n.absolute_path.clone())
+            .filter(|p| archive::split(p).is_some())
+            .collect::<Vec<String>>();
+
+        if paths.is_empty() {
+            return self.log_warning("Nothing in an archive is selected".into());
+        };
+
+        let dest = archive::host_dir(&self.pwd);
+        self.msg_out.push_back(MsgOut::Extract(paths, dest));
         Ok(self)
     }
 
-    fn call_silently(mut self, command: Command) -> Result<Self> {
-        self.msg_out.push_back(MsgOut::CallSilently(command));
-        Ok(self)
+    fn start_transfer(mut self, transfer: Transfer) -> Result<Self> {
+        self.transfer = Some(transfer.clone());
+        self.msg_out.push_back(MsgOut::Transfer(transfer));
+        self.refresh()
     }
 
-    fn bash_exec(self, script: String) -> Result<Self> {
-        self.call(Command {
-            command: "bash".into(),
-            args: vec!["-c".into(), script],
-        })
+    fn yank_selection(self) -> Result<Self> {
+        self.fill_paste_register(TransferKind::Copy)
     }
 
-    fn bash_exec_silently(self, script: String) -> Result<Self> {
-        self.call_silently(Command {
-            command: "bash".into(),
-            args: vec!["-c".into(), script],
-        })
+    fn cut_selection(self) -> Result<Self> {
+        self.fill_paste_register(TransferKind::Move)
     }
 
-    fn add_directory(mut self, parent: String, dir: DirectoryBuffer) -> Result<Self> {
-        self.directory_buffers.insert(parent, dir);
-        self.msg_out.push_back(MsgOut::Refresh);
-        Ok(self)
+    fn fill_paste_register(mut self, kind: TransferKind) -> Result<Self> {
+        let paths = self
+            .selection
+            .iter()
+            .map(|n| n.absolute_path.clone())
+            .collect::<Vec<String>>();
+
+        if paths.is_empty() {
+            return self.log_warning("Nothing is selected".into());
+        };
+
+        let done = match kind {
+            TransferKind::Copy => "yanked",
+            TransferKind::Move => "cut",
+        };
+        let message = format!("{} paths {}", paths.len(), done);
+
+        self.paste_register = Some(PasteRegister { kind, paths });
+        self.selection.clear();
+        self.log_info(message)
     }
 
-    fn select(mut self) -> Result<Self> {
-        if let Some(n) = self.focused_node().map(|n| n.to_owned()) {
-            self.selection.insert(n);
-            self.msg_out.push_back(MsgOut::Refresh);
-        }
-        Ok(self)
+    fn paste_into_current(mut self, conflict: PasteConflict) -> Result<Self> {
+        if self.transfer.is_some() {
+            return self.log_error("Another copy or move is in progress".into());
+        };
+
+        let register = match self.paste_register.clone() {
+            Some(register) => register,
+            None => return self.log_warning("Nothing to paste".into()),
+        };
+
+        let pwd = PathBuf::from(&self.pwd);
+        let (items, in_place): (Vec<(String, String)>, _) = register
+            .paths
+            .iter()
+            .filter_map(|from| {
+                Path::new(from)
+                    .file_name()
+                    .map(|name| (from.clone(), pwd.join(name).to_string_lossy().to_string()))
+            })
+            .partition(|(from, to)| register.kind == TransferKind::Copy || from != to);
+
+        // The cut paths pasted where they already are stay as they are.
+        if !in_place.is_empty() {
+            let paths = register
+                .paths
+                .into_iter()
+                .filter(|p| !in_place.iter().any(|(from, _)| from == p))
+                .collect::<Vec<String>>();
+            self.paste_register = if paths.is_empty() {
+                None
+            } else {
+                Some(PasteRegister { paths, ..register })
+            };
+        };
+
+        self.transfer_resolving(register.kind, items, conflict)
     }
 
-    fn select_path(mut self, path: String) -> Result<Self> {
-        let path = PathBuf::from(path);
-        let parent = path.parent().map(|p| p.to_string_lossy().to_string());
-        let filename = path.file_name().map(|p| p.to_string_lossy().to_string());
-        if let (Some(p), Some(n)) = (parent, filename) {
-            self.selection.insert(Node::new(p, n));
-            self.msg_out.push_back(MsgOut::Refresh);
+    fn cancel_transfer(mut self) -> Result<Self> {
+        if self.transfer.is_some() {
+            self.msg_out.push_back(MsgOut::CancelTransfer);
         };
         Ok(self)
     }
 
-    fn select_all(mut self) -> Result<Self> {
-        if let Some(d) = self.directory_buffer() {
-            d.nodes.clone().into_iter().for_each(|n| {
-                self.selection.insert(n);
-            });
-            self.msg_out.push_back(MsgOut::Refresh);
+    fn search_recursively(mut self, pattern: String) -> Result<Self> {
+        if self.search.is_some() {
+            self.msg_out.push_back(MsgOut::CancelSearch);
         };
 
-        Ok(self)
+        self.searches += 1;
+        let search = RecursiveSearch {
+            id: self.searches,
+            pattern: pattern.clone(),
+            root: self.pwd.clone(),
+            max_depth: self
+                .config
+                .general
+                .recursive_search_max_depth
+                .unwrap_or(DEFAULT_RECURSIVE_SEARCH_MAX_DEPTH),
+        };
+        self.search = Some(search.clone());
+        self.msg_out.push_back(MsgOut::SearchRecursively(search));
+        self.open_picker(Picker::new(format!("Searching {}", pattern), vec![]))
     }
 
-    fn un_select(mut self) -> Result<Self> {
-        if let Some(n) = self.focused_node().map(|n| n.to_owned()) {
-            self.selection.retain(|s| s != &n);
-            self.msg_out.push_back(MsgOut::Refresh);
+    fn search_recursively_from_input(self) -> Result<Self> {
+        if let Some(pattern) = self.input_buffer() {
+            self.search_recursively(pattern)
+        } else {
+            Ok(self)
         }
-        Ok(self)
     }
 
-    fn un_select_path(mut self, path: String) -> Result<Self> {
-        self.selection.retain(|n| n.absolute_path != path);
-        self.msg_out.push_back(MsgOut::Refresh);
+    fn cancel_search(mut self) -> Result<Self> {
+        if self.search.take().is_some() {
+            self.msg_out.push_back(MsgOut::CancelSearch);
+        };
         Ok(self)
     }
 
-    fn un_select_all(mut self) -> Result<Self> {
-        if let Some(d) = self.directory_buffer() {
-            d.nodes.clone().into_iter().for_each(|n| {
-                self.selection.retain(|s| s != &n);
-            });
-            self.msg_out.push_back(MsgOut::Refresh);
+    fn add_search_results(mut self, results: SearchResults) -> Result<Self> {
+        let search = match self.search.clone() {
+            Some(search) if search.id == results.id => search,
+            _ => return Ok(self),
         };
 
-        Ok(self)
+        if let Some(picker) = self.picker.as_mut() {
+            picker
+                .items
+                .extend(results.paths.into_iter().map(|relative_path| {
+                    let path = PathBuf::from(&search.root)
+                        .join(&relative_path)
+                        .to_string_lossy()
+                        .to_string();
+                    PickerItem::new(relative_path, vec![ExternalMsg::FocusPath(path)])
+                }));
+
+            if results.done {
+                picker.title = format!("Found {}", search.pattern);
+            };
+        };
+
+        if results.done {
+            self.search = None;
+        };
+        self.refresh()
     }
 
-    fn toggle_selection(mut self) -> Result<Self> {
-        if let Some(n) = self.focused_node() {
-            if self.selection().contains(n) {
-                self = self.un_select()?;
-            } else {
-                self = self.select()?;
+    fn set_transfer_progress(mut self, progress: TransferProgress) -> Result<Self> {
+        if self.transfer.is_some() {
+            self.transfer_progress = Some(progress);
+        };
+        self.refresh()
+    }
+
+    fn finish_transfer_item(mut self, item: TransferredItem) -> Result<Self> {
+        let (verb, done) = match item.kind {
+            TransferKind::Copy => ("copy", "copied"),
+            TransferKind::Move => ("move", "moved"),
+        };
+
+        match item.error {
+            None => {
+                if item.kind == TransferKind::Move {
+                    self = self.record(FsOperation::Move {
+                        from: item.from.clone(),
+                        to: item.to.clone(),
+                    });
+
+                    // The cut paths are forgotten once moved.
+                    if let Some(register) = self.paste_register.as_mut() {
+                        if register.kind == TransferKind::Move {
+                            register.paths.retain(|p| p != &item.from);
+                        };
+                    };
+                    if self
+                        .paste_register
+                        .as_ref()
+                        .map(|r| r.paths.is_empty())
+                        .unwrap_or(false)
+                    {
+                        self.paste_register = None;
+                    };
+                };
+                self.log_success(format!("{} {} to {}", item.from, done, item.to))
             }
+            Some(e) => self.log_error(format!("Failed to {} {}: {}", verb, item.from, e)),
         }
-        Ok(self)
     }
 
-    fn toggle_select_all(self) -> Result<Self> {
-        if let Some(d) = self.directory_buffer() {
-            if d.nodes.iter().all(|n| self.selection.contains(n)) {
-                self.un_select_all()
+    fn finish_transfer(mut self) -> Result<Self> {
+        self.transfer = None;
+        self.transfer_progress = None;
+        self.explore()
+    }
+
+    /// The trashed nodes are restored with a single undo.
+    fn trash(mut self) -> Result<Self> {
+        let nodes: Vec<Node> = self.result().into_iter().cloned().collect();
+        let mut ops = vec![];
+        for node in nodes {
+            self = match FsOperation::trash(&node.absolute_path) {
+                Ok(op) => {
+                    self.selection.remove(&node);
+                    ops.push(op);
+                    self.log_success(format!("{} trashed", node.absolute_path))?
+                }
+                Err(e) => {
+                    self.log_error(format!("Failed to trash {}: {}", node.absolute_path, e))?
+                }
+            };
+        }
+        if let Some(op) = FsOperation::batch(ops) {
+            self = self.record(op);
+        };
+        self.explore()
+    }
+
+    fn delete(mut self) -> Result<Self> {
+        let nodes: Vec<Node> = self.result().into_iter().cloned().collect();
+        for node in nodes {
+            self = match FsOperation::delete(&node.absolute_path) {
+                Ok(op) => {
+                    self.selection.remove(&node);
+                    self.record(op).log_success(format!(
+                        "{} deleted permanently, this can't be undone",
+                        node.absolute_path
+                    ))?
+                }
+                Err(e) => {
+                    self.log_error(format!("Failed to delete {}: {}", node.absolute_path, e))?
+                }
+            };
+        }
+        self.explore()
+    }
+
+    fn undo(mut self) -> Result<Self> {
+        if let Some(op) = self.undo_stack.pop() {
+            if !op.is_undoable() {
+                self.log_error(format!("Cannot undo: {} is permanent.", op))
+            } else if let Err(e) = op.undo() {
+                self.log_error(format!("Failed to undo {}: {}", op, e))
             } else {
-                self.select_all()
+                let msg = format!("Undone: {}", op);
+                self.redo_stack.push(op);
+                self.log_success(msg)?.explore()
             }
         } else {
-            Ok(self)
+            self.log_error("Nothing to undo.".into())
         }
     }
 
-    fn toggle_selection_by_path(self, path: String) -> Result<Self> {
-        if self.selection.iter().any(|n| n.absolute_path == path) {
-            self.select_path(path)
+    fn redo(mut self) -> Result<Self> {
+        if let Some(op) = self.redo_stack.pop() {
+            if let Err(e) = op.redo() {
+                self.log_error(format!("Failed to redo {}: {}", op, e))
+            } else {
+                let msg = format!("Redone: {}", op);
+                self.undo_stack.push(op);
+                self.log_success(msg)?.explore()
+            }
         } else {
-            self.un_select_path(path)
+            self.log_error("Nothing to redo.".into())
         }
     }
 
-    fn clear_selection(mut self) -> Result<Self> {
-        self.selection.clear();
-        self.msg_out.push_back(MsgOut::Refresh);
-        Ok(self)
-    }
-
-    fn add_node_filter(mut self, filter: NodeFilterApplicable) -> Result<Self> {
-        self.explorer_config.filters.replace(filter);
-        Ok(self)
-    }
+    /// Keep the log line, dropping the oldest ones beyond `max_logs`.
+    fn push_log(&mut self, log: Log) {
+        self.logs.push(log);
+        self.logs_total += 1;
 
-    fn add_node_filter_from_input(mut self, filter: NodeFilter) -> Result<Self> {
-        if let Some(input) = self.input_buffer() {
-            self.explorer_config
-                .filters
-                .insert(NodeFilterApplicable::new(filter, input));
+        let max_logs = self.config.general.max_logs.unwrap_or(DEFAULT_MAX_LOGS);
+        if self.logs.len() > max_logs {
+            self.logs.drain(..self.logs.len() - max_logs);
         };
-        Ok(self)
     }
 
-    fn remove_node_filter(mut self, filter: NodeFilterApplicable) -> Result<Self> {
-        self.explorer_config.filters.retain(|f| f != &filter);
-        Ok(self)
+    fn clear_logs(mut self) -> Result<Self> {
+        self.logs.clear();
+        self.refresh()
     }
 
-    fn remove_node_filter_from_input(mut self, filter: NodeFilter) -> Result<Self> {
-        if let Some(input) = self.input_buffer() {
-            let nfa = NodeFilterApplicable::new(filter, input);
-            self.explorer_config.filters.retain(|f| f != &nfa);
-        };
+    fn log_info(mut self, message: String) -> Result<Self> {
+        self.push_log(Log::new(LogLevel::Info, message));
         Ok(self)
     }
 
-    fn toggle_node_filter(self, filter: NodeFilterApplicable) -> Result<Self> {
-        if self.explorer_config.filters.contains(&filter) {
-            self.remove_node_filter(filter)
-        } else {
-            self.add_node_filter(filter)
-        }
+    fn log_styled(mut self, log: StyledLog) -> Result<Self> {
+        let mut line = Log::new(LogLevel::Info, log.text);
+        line.style = Some(log.style);
+        self.push_log(line);
+        Ok(self)
     }
 
-    fn remove_last_node_filter(mut self) -> Result<Self> {
-        self.explorer_config.filters.pop();
+    fn log_success(mut self, message: String) -> Result<Self> {
+        self.push_log(Log::new(LogLevel::Success, message));
         Ok(self)
     }
 
-    fn reset_node_filters(mut self) -> Result<Self> {
-        self.explorer_config.filters.clear();
-
-        if !self.config.general.show_hidden.unwrap_or_default() {
-            self.add_node_filter(NodeFilterApplicable::new(
-                NodeFilter::RelativePathDoesNotStartWith,
-                ".".into(),
-            ))
-        } else {
-            Ok(self)
-        }
-    }
-    fn clear_node_filters(mut self) -> Result<Self> {
-        self.explorer_config.filters.clear();
+    fn log_warning(mut self, message: String) -> Result<Self> {
+        self.push_log(Log::new(LogLevel::Warning, message));
         Ok(self)
     }
 
-    fn add_node_sorter(mut self, sorter: NodeSorterApplicable) -> Result<Self> {
-        self.explorer_config.sorters.replace(sorter);
+    fn log_error(mut self, message: String) -> Result<Self> {
+        self.push_log(Log::new(LogLevel::Error, message));
         Ok(self)
     }
 
-    fn remove_node_sorter(mut self, sorter: NodeSorter) -> Result<Self> {
-        self.explorer_config.sorters.retain(|s| s.sorter != sorter);
+    fn quit(mut self) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::Quit);
         Ok(self)
     }
 
-    fn reverse_node_sorter(mut self, sorter: NodeSorter) -> Result<Self> {
-        self.explorer_config.sorters = self
-            .explorer_config
-            .sorters
-            .into_iter()
-            .map(|s| if s.sorter == sorter { s.reversed() } else { s })
-            .collect();
+    fn print_result_and_quit(mut self) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::PrintResultAndQuit);
         Ok(self)
     }
 
-    fn toggle_node_sorter(self, sorter: NodeSorterApplicable) -> Result<Self> {
-        if self.explorer_config.sorters.contains(&sorter) {
-            self.remove_node_sorter(sorter.sorter)
-        } else {
-            self.add_node_sorter(sorter)
-        }
+    fn print_focused_path_and_quit(mut self) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::PrintFocusedPathAndQuit);
+        Ok(self)
     }
 
-    fn remove_last_node_sorter(mut self) -> Result<Self> {
-        self.explorer_config.sorters.pop();
+    fn set_terminal_title(mut self, title: String) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::SetTerminalTitle(title));
         Ok(self)
     }
 
-    fn reverse_node_sorters(mut self) -> Result<Self> {
-        self.explorer_config.sorters = self
-            .explorer_config
-            .sorters
-            .into_iter()
-            .map(|s| s.reversed())
-            .collect();
+    fn copy_name_to_clipboard(mut self) -> Result<Self> {
+        if let Some(node) = self.focused_node() {
+            let name = node.relative_path.clone();
+            self.msg_out.push_back(MsgOut::CopyToClipboard(name));
+        };
         Ok(self)
     }
 
-    fn reset_node_sorters(mut self) -> Result<Self> {
-        self.explorer_config.sorters = self
+    fn copy_selected_names_to_clipboard(mut self) -> Result<Self> {
+        if self.selection.is_empty() {
+            return self.copy_name_to_clipboard();
+        };
+
+        let separator = self
             .config
             .general
-            .initial_sorting
-            .to_owned()
-            .unwrap_or_default();
+            .selection_separator
+            .clone()
+            .unwrap_or_else(|| "\n".into());
+
+        let names = self
+            .selection
+            .iter()
+            .map(|n| n.relative_path.clone())
+            .collect::<Vec<String>>()
+            .join(&separator);
+
+        self.msg_out.push_back(MsgOut::CopyToClipboard(names));
         Ok(self)
     }
 
-    fn clear_node_sorters(mut self) -> Result<Self> {
-        self.explorer_config.sorters.clear();
+    fn print_selection_and_quit(mut self) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::PrintSelectionAndQuit);
         Ok(self)
     }
 
-    fn log_info(mut self, message: String) -> Result<Self> {
-        self.logs.push(Log::new(LogLevel::Info, message));
+    fn print_app_state_and_quit(mut self) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::PrintAppStateAndQuit);
         Ok(self)
     }
 
-    fn log_success(mut self, message: String) -> Result<Self> {
-        self.logs.push(Log::new(LogLevel::Success, message));
+    fn debug(mut self, path: String) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::Debug(path));
         Ok(self)
     }
 
-    fn log_error(mut self, message: String) -> Result<Self> {
-        self.logs.push(Log::new(LogLevel::Error, message));
-        Ok(self)
+    fn reload_config(mut self) -> Result<Self> {
+        let loaded = discover_config_files(self.config_file.clone())
+            .and_then(|files| load_config(&files).map(|config| (files, config)));
+
+        match loaded {
+            Ok((files, mut config)) => {
+                let invalid_datetime_format = drop_invalid_datetime_format(&mut config);
+                self.config = Box::new(config);
+                self = self.switch_mode("default")?;
+                if let Some(err) = invalid_datetime_format {
+                    self = self.log_error(err)?;
+                };
+                self.log_success(loaded_config_message(&files))?.explore()
+            }
+            Err(e) => self.log_error(format!("Cannot reload the config: {}", e)),
+        }
     }
 
-    fn quit(mut self) -> Result<Self> {
-        self.msg_out.push_back(MsgOut::Quit);
-        Ok(self)
+    fn edit_config(self) -> Result<Self> {
+        let file = discover_config_files(self.config_file.clone())
+            .ok()
+            .and_then(|files| files.into_iter().next())
+            .or_else(|| config_file_locations().into_iter().next());
+
+        let file = match file {
+            Some(file) => file,
+            None => return self.log_error("Cannot find where to put the config file".into()),
+        };
+
+        if let Some(dir) = file.parent() {
+            if let Err(e) = fs::create_dir_all(dir) {
+                return self.log_error(format!("Cannot create {}: {}", dir.to_string_lossy(), e));
+            };
+        };
+
+        self.call(Command {
+            command: "bash".into(),
+            args: vec![
+                "-c".into(),
+                r#"${EDITOR:-vi} "$1""#.into(),
+                "xplr".into(),
+                file.to_string_lossy().to_string(),
+            ],
+        })
+        .map(|app| app.enqueue(Task::new(MsgIn::External(ExternalMsg::ReloadConfig), None)))
     }
 
-    fn print_result_and_quit(mut self) -> Result<Self> {
-        self.msg_out.push_back(MsgOut::PrintResultAndQuit);
+    fn dump_effective_config(mut self, path: String) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::DumpEffectiveConfig(path));
         Ok(self)
     }
 
-    fn print_app_state_and_quit(mut self) -> Result<Self> {
-        self.msg_out.push_back(MsgOut::PrintAppStateAndQuit);
+    fn set_explorer_config(mut self, view: &str) -> Result<Self> {
+        match serde_yaml::from_str::<ExplorerView>(view) {
+            Ok(view) => {
+                let filters = view.filters.clone();
+                self.explorer_config.set_view(view);
+                for filter in filters.iter() {
+                    self = self.list_reference_names(filter)?;
+                }
+                self.explore()
+            }
+            Err(e) => self.log_error(format!("Invalid explorer config: {}", e)),
+        }
+    }
+
+    fn dump_explorer_config(mut self, path: String) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::DumpExplorerConfig(path));
         Ok(self)
     }
 
-    fn debug(mut self, path: String) -> Result<Self> {
-        self.msg_out.push_back(MsgOut::Debug(path));
+    fn save_view(mut self, name: String) -> Result<Self> {
+        if name.is_empty() {
+            return self.log_error("Cannot save a view without a name".into());
+        };
+
+        let view = View {
+            explorer: self.explorer_config.view(),
+            layout_ratio: Some(self.layout_ratio),
+        };
+        self.views = self.views.insert(name.clone(), view);
+        self.log_success(format!("Saved the view {}", name))
+    }
+
+    /// The sorters, the filters and the layout are replaced together, and the
+    /// listing explored once.
+    fn load_view(mut self, name: &str) -> Result<Self> {
+        match self.views.get(name).cloned() {
+            Some(view) => {
+                for filter in view.explorer.filters.iter() {
+                    self = self.list_reference_names(filter)?;
+                }
+                self.explorer_config.set_view(view.explorer);
+                if let Some(ratio) = view.layout_ratio {
+                    self.layout_ratio = ratio.clamp(MIN_LAYOUT_RATIO, MAX_LAYOUT_RATIO);
+                };
+                self.explore()
+            }
+            None => self.log_error(format!("No view named {}", name)),
+        }
+    }
+
+    fn show_views(self) -> Result<Self> {
+        let items = self
+            .views
+            .names()
+            .into_iter()
+            .map(|n| PickerItem::new(n.clone(), vec![ExternalMsg::LoadView(n.clone())]))
+            .collect::<Vec<PickerItem>>();
+
+        if items.is_empty() {
+            self.log_info("No saved views".into())
+        } else {
+            self.open_picker(Picker::new("Views".into(), items))
+        }
+    }
+
+    fn export_listing_json(mut self, path: String) -> Result<Self> {
+        self.msg_out.push_back(MsgOut::ExportListingJson(path));
         Ok(self)
     }
 
@@ -1970,6 +7235,11 @@ impl App {
         self.directory_buffers.get(&self.pwd)
     }
 
+    /// Get a reference to the app's yanked or cut paths.
+    pub fn paste_register(&self) -> Option<&PasteRegister> {
+        self.paste_register.as_ref()
+    }
+
     /// Get a reference to the app's config.
     pub fn config(&self) -> &Config {
         &self.config
@@ -2003,6 +7273,17 @@ impl App {
         self.input_buffer.clone()
     }
 
+    /// Get the selected region of the input buffer, in characters. An empty
+    /// region marks the point where the input is inserted.
+    pub fn input_selection(&self) -> Option<(usize, usize)> {
+        self.input_selection
+    }
+
+    /// Get the text last pasted into the terminal.
+    pub fn last_paste(&self) -> Option<&String> {
+        self.last_paste.as_ref()
+    }
+
     /// Get a reference to the app's pipes.
     pub fn pipe(&self) -> &Pipe {
         &self.pipe
@@ -2018,15 +7299,35 @@ impl App {
         &self.session_path
     }
 
+    /// Get a reference to where the state kept between sessions goes.
+    pub fn state_files(&self) -> &StateFiles {
+        &self.state_files
+    }
+
     pub fn refresh_selection(mut self) -> Result<Self> {
-        self.selection = self
-            .selection
-            .into_iter()
-            .filter(|n| PathBuf::from(&n.absolute_path).exists())
-            .collect();
+        let exists = |n: &Node| {
+            PathBuf::from(&n.absolute_path).exists() || archive::split(&n.absolute_path).is_some()
+        };
+        self.selection.retain(exists);
+        for paths in self.registers.values_mut() {
+            paths.retain(|p| PathBuf::from(p).exists() || archive::split(p).is_some());
+        }
         Ok(self)
     }
 
+    pub fn registers(&self) -> &BTreeMap<char, IndexSet<String>> {
+        &self.registers
+    }
+
+    /// The names of the registers having the node.
+    pub fn registers_of(&self, node: &Node) -> String {
+        self.registers
+            .iter()
+            .filter(|(_, paths)| paths.contains(&node.absolute_path))
+            .map(|(r, _)| r)
+            .collect()
+    }
+
     pub fn result(&self) -> Vec<&Node> {
         if self.selection.is_empty() {
             self.focused_node().map(|n| vec![n]).unwrap_or_default()
@@ -2035,6 +7336,21 @@ impl App {
         }
     }
 
+    /// The nodes of the present working directory, as listed.
+    pub fn listing(&self) -> Vec<ListedNode<'_>> {
+        self.directory_buffer()
+            .map(|d| {
+                d.nodes
+                    .iter()
+                    .map(|node| ListedNode {
+                        node,
+                        is_selected: self.selection.contains(node),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     pub fn directory_nodes_str(&self) -> String {
         self.directory_buffer()
             .map(|d| {
@@ -2055,6 +7371,22 @@ impl App {
             .join("")
     }
 
+    /// The selected paths, each followed by the configured separator.
+    pub fn selection_str_separated(&self) -> String {
+        let separator = self
+            .config
+            .general
+            .selection_separator
+            .clone()
+            .unwrap_or_else(|| "\n".into());
+
+        self.selection
+            .iter()
+            .map(|n| format!("{}{}", n.absolute_path, separator))
+            .collect::<Vec<String>>()
+            .join("")
+    }
+
     pub fn result_str(&self) -> String {
         self.result()
             .into_iter()
@@ -2073,14 +7405,30 @@ impl App {
         &self.logs
     }
 
-    pub fn global_help_menu_str(&self) -> String {
+    /// The number of lines ever logged, including the pruned and cleared
+    /// ones.
+    pub fn logs_total(&self) -> usize {
+        self.logs_total
+    }
+
+    /// The lines logged since the total was the given number, as far as
+    /// they're kept.
+    pub fn logs_since(&self, total: usize) -> &[Log] {
+        let new = self.logs_total.saturating_sub(total).min(self.logs.len());
+        &self.logs[self.logs.len() - new..]
+    }
+
+    /// All the builtin and custom modes, with their names.
+    fn modes(&self) -> Vec<(String, Mode)> {
         let builtin = self.config().modes.builtin.clone();
         let custom = self.config().modes.custom.clone();
 
-        [
+        vec![
             (builtin.default.name.clone(), builtin.default),
             (builtin.number.name.clone(), builtin.number),
             (builtin.go_to.name.clone(), builtin.go_to),
+            (builtin.go_to_path.name.clone(), builtin.go_to_path),
+            (builtin.jump.name.clone(), builtin.jump),
             (builtin.search.name.clone(), builtin.search),
             (builtin.selection_ops.name.clone(), builtin.selection_ops),
             (builtin.action.name.clone(), builtin.action),
@@ -2091,14 +7439,39 @@ impl App {
                 builtin.create_directory,
             ),
             (builtin.rename.name.clone(), builtin.rename),
+            (
+                builtin.set_permissions.name.clone(),
+                builtin.set_permissions,
+            ),
+            (builtin.set_owner.name.clone(), builtin.set_owner),
             (builtin.delete.name.clone(), builtin.delete),
             (builtin.sort.name.clone(), builtin.sort),
             (builtin.filter.name.clone(), builtin.filter),
-            (builtin.relative_path_does_contain.name.clone(), builtin.relative_path_does_contain),
-            (builtin.relative_path_does_not_contain.name.clone(), builtin.relative_path_does_not_contain),
+            (
+                builtin.relative_path_does_contain.name.clone(),
+                builtin.relative_path_does_contain,
+            ),
+            (
+                builtin.relative_path_does_not_contain.name.clone(),
+                builtin.relative_path_does_not_contain,
+            ),
+            (builtin.picker.name.clone(), builtin.picker),
+            (
+                builtin.command_palette.name.clone(),
+                builtin.command_palette,
+            ),
+            (builtin.confirm.name.clone(), builtin.confirm),
+            (builtin.record_macro.name.clone(), builtin.record_macro),
+            (builtin.play_macro.name.clone(), builtin.play_macro),
         ]
+        .into_iter()
+        .chain(custom.into_iter().collect::<BTreeMap<String, Mode>>())
+        .collect()
+    }
+
+    pub fn global_help_menu_str(&self) -> String {
+        self.modes()
         .iter()
-        .chain(custom.into_iter().collect::<Vec<(String, Mode)>>().iter())
         .map(|(name, mode)| {
             let help = mode
                 .help_menu()
@@ -2140,6 +7513,177 @@ impl App {
         &self.history
     }
 
+    /// Get a reference to the app's favorite directories.
+    pub fn favorites(&self) -> &Favorites {
+        &self.favorites
+    }
+
+    pub fn views(&self) -> &Views {
+        &self.views
+    }
+
+    /// Get a reference to the app's directory scores.
+    pub fn directory_scores(&self) -> &DirectoryScores {
+        &self.directory_scores
+    }
+
+    /// Get a reference to the app's recent directories.
+    pub fn recent_directories(&self) -> &RecentDirectories {
+        &self.recent_directories
+    }
+
+    /// Get a reference to the app's tabs. The state of the active tab is only
+    /// updated when switching to another tab.
+    pub fn tabs(&self) -> &Vec<Tab> {
+        &self.tabs
+    }
+
+    /// Get the index of the active tab.
+    pub fn tab_index(&self) -> usize {
+        self.tab_index
+    }
+
+    /// Get a reference to the app's picker.
+    pub fn picker(&self) -> Option<&Picker> {
+        self.picker.as_ref()
+    }
+
+    /// Get a reference to the progress of the running copy or move.
+    pub fn transfer_progress(&self) -> Option<&TransferProgress> {
+        self.transfer_progress.as_ref()
+    }
+
+    /// Get a reference to the output of the command the focused file was
+    /// piped through.
+    pub fn command_output(&self) -> Option<&CommandOutput> {
+        self.command_output.as_ref()
+    }
+
+    /// Remember where the table rows were drawn, to map mouse clicks to nodes.
+    pub fn with_table_area(mut self, area: TableArea) -> Self {
+        self.table_area = Some(area);
+        self
+    }
+
+    /// The spinner frame to show while background workers are running.
+    pub fn spinner(&self) -> Option<String> {
+        let frames = self
+            .config
+            .general
+            .spinner
+            .frames
+            .clone()
+            .unwrap_or_default();
+        if worker::is_running() && !frames.is_empty() {
+            frames.get(self.spinner_frame % frames.len()).cloned()
+        } else {
+            None
+        }
+    }
+
+    /// Get a reference to the app's pending confirmation.
+    pub fn confirmation(&self) -> Option<&Confirmation> {
+        self.confirmation.as_ref()
+    }
+
+    /// Whether the pane listing the nodes of the focused directory is shown,
+    /// either toggled on or by the layout.
+    pub fn preview_children(&self) -> bool {
+        self.preview_children || self.layout().preview.unwrap_or_default()
+    }
+
+    /// The first line of the help menu shown over the screen, if it's shown.
+    pub fn full_help(&self) -> Option<usize> {
+        self.full_help
+    }
+
+    /// The first line of the errors shown over the screen, if they're shown.
+    pub fn errors_shown(&self) -> Option<usize> {
+        self.errors_shown
+    }
+
+    pub fn size_unit_base(&self) -> SizeUnitBase {
+        self.config
+            .general
+            .size_unit_base
+            .unwrap_or(SizeUnitBase::Binary)
+    }
+
+    /// The error logs still kept, oldest first.
+    pub fn errors(&self) -> Vec<&Log> {
+        self.logs
+            .iter()
+            .filter(|l| l.level == LogLevel::Error)
+            .collect()
+    }
+
+    /// The prefix typed ahead so far, while typing ahead.
+    pub fn type_ahead(&self) -> Option<&str> {
+        self.type_ahead.as_ref().map(|t| t.prefix.as_str())
+    }
+
+    /// The focused directory to list in the preview pane, if it's shown.
+    pub fn preview_path(&self) -> Option<String> {
+        self.focused_node()
+            .filter(|_| self.preview_children())
+            .filter(|n| n.is_dir || n.canonical.as_ref().map(|c| c.is_dir).unwrap_or(false))
+            .map(|n| n.absolute_path.clone())
+    }
+
+    /// Get the listing of the focused directory, once it's explored.
+    pub fn preview(&self) -> Option<&DirectoryBuffer> {
+        self.preview
+            .as_ref()
+            .filter(|p| self.preview_path().as_ref() == Some(&p.parent))
+    }
+
+    /// Get the width of the primary pane in percentage.
+    pub fn layout_ratio(&self) -> u16 {
+        self.layout_ratio
+    }
+
+    /// The name of the current layout.
+    pub fn layout_name(&self) -> &String {
+        &self.layout
+    }
+
+    /// The current layout, showing all the panes but the preview if it's
+    /// missing from the config.
+    pub fn layout(&self) -> LayoutConfig {
+        LayoutConfig {
+            sort_and_filter: Some(true),
+            selection: Some(true),
+            help_menu: Some(true),
+            preview: Some(false),
+        }
+        .extend(
+            self.config
+                .layouts
+                .get(&self.layout)
+                .cloned()
+                .unwrap_or_default(),
+        )
+    }
+
+    /// The git status of the node, if it's enabled and there's any.
+    pub fn git_status(&self, node: &Node) -> Option<GitStatus> {
+        if self.config.general.git_status.unwrap_or_default() {
+            self.git_statuses.get(&node.absolute_path).copied()
+        } else {
+            None
+        }
+    }
+
+    /// Get a reference to the app's computed file hashes.
+    pub fn file_hashes(&self) -> &HashMap<String, FileHash> {
+        &self.file_hashes
+    }
+
+    /// Get a reference to the app's computed directory sizes.
+    pub fn directory_sizes(&self) -> &HashMap<String, DirectorySize> {
+        &self.directory_sizes
+    }
+
     pub fn history_str(&self) -> String {
         self.history
             .paths
@@ -0,0 +1,285 @@
+use crate::app::{Node, Task};
+use crate::batch_runner;
+use flate2::read::GzDecoder;
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The most archives the entries are kept for at once.
+const MAX_INDEXES: usize = 16;
+
+lazy_static! {
+    static ref INDEXES: Mutex<HashMap<PathBuf, Index>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Kind {
+    fn of(path: &Path) -> Option<Self> {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        let mime = mime_guess::from_path(path)
+            .first()
+            .map(|m| m.essence_str().to_string())
+            .unwrap_or_default();
+
+        if mime == "application/zip" || name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if mime == "application/x-tar" || name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+struct Entry {
+    path: PathBuf,
+    is_dir: bool,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// The entries of an archive as of the given modification time and size of
+/// the archive.
+struct Index {
+    modified: Option<SystemTime>,
+    len: u64,
+    entries: Arc<Vec<Entry>>,
+}
+
+/// The path of the entry with the `.` components dropped, or `None` if it
+/// would point outside of the archive.
+fn enclosed(path: &Path) -> Option<PathBuf> {
+    let mut enclosed = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(c) => enclosed.push(c),
+            Component::CurDir => {}
+            _ => return None,
+        };
+    }
+    Some(enclosed)
+}
+
+/// Call the function with each directory and regular file in the archive,
+/// along with a reader of its content.
+fn walk(
+    archive: &Path,
+    f: &mut dyn FnMut(Entry, &mut dyn Read) -> io::Result<()>,
+) -> io::Result<()> {
+    let file = fs::File::open(archive)?;
+    let kind = Kind::of(archive).ok_or_else(|| io::Error::other("not an archive"))?;
+
+    if kind == Kind::Zip {
+        let mut zip = zip::ZipArchive::new(file).map_err(io::Error::other)?;
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i).map_err(io::Error::other)?;
+            if let Some(path) = enclosed(Path::new(file.name())) {
+                let entry = Entry {
+                    path,
+                    is_dir: file.is_dir(),
+                    size: file.size(),
+                    modified: None,
+                };
+                f(entry, &mut file)?;
+            };
+        }
+    } else {
+        let reader: Box<dyn Read> = if kind == Kind::TarGz {
+            Box::new(GzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+
+        for file in tar::Archive::new(reader).entries()? {
+            let mut file = file?;
+            let header = file.header();
+            let is_dir = header.entry_type().is_dir();
+            if !is_dir && !header.entry_type().is_file() {
+                continue;
+            };
+
+            let size = header.size()?;
+            let modified = header
+                .mtime()
+                .ok()
+                .map(|t| UNIX_EPOCH + Duration::from_secs(t));
+
+            if let Some(path) = enclosed(&file.path()?) {
+                let entry = Entry {
+                    path,
+                    is_dir,
+                    size,
+                    modified,
+                };
+                f(entry, &mut file)?;
+            };
+        }
+    };
+    Ok(())
+}
+
+/// The archive and the path inside it, if the path is in a zip or tar
+/// archive, or is one.
+pub fn split(path: &str) -> Option<(PathBuf, PathBuf)> {
+    let path = Path::new(path);
+    let archive = path.ancestors().find(|p| p.symlink_metadata().is_ok())?;
+    if archive.is_file() && Kind::of(archive).is_some() {
+        let inner = path.strip_prefix(archive).ok()?.to_path_buf();
+        Some((archive.to_path_buf(), inner))
+    } else {
+        None
+    }
+}
+
+/// The given directory or, if it's in an archive, the directory holding the
+/// archive.
+pub fn host_dir(dir: &str) -> String {
+    match split(dir) {
+        Some((archive, _)) => archive
+            .parent()
+            .unwrap_or(&archive)
+            .to_string_lossy()
+            .to_string(),
+        None => dir.to_string(),
+    }
+}
+
+/// Change the working directory to the [`host_dir`] of the given directory.
+pub fn set_current_dir(dir: &str) -> io::Result<()> {
+    env::set_current_dir(host_dir(dir))
+}
+
+/// The entries of the archive, read once for as long as the archive isn't
+/// modified, so that going through its directories doesn't decompress it
+/// again each time.
+fn entries(archive: &Path) -> io::Result<Arc<Vec<Entry>>> {
+    let meta = fs::metadata(archive)?;
+    let (modified, len) = (meta.modified().ok(), meta.len());
+
+    if let Some(index) = INDEXES.lock().unwrap().get(archive) {
+        if index.modified == modified && index.len == len {
+            return Ok(index.entries.clone());
+        };
+    };
+
+    let mut entries = vec![];
+    walk(archive, &mut |entry, _| {
+        entries.push(entry);
+        Ok(())
+    })?;
+    let entries = Arc::new(entries);
+
+    let mut indexes = INDEXES.lock().unwrap();
+    if indexes.len() >= MAX_INDEXES && !indexes.contains_key(archive) {
+        indexes.clear();
+    };
+    indexes.insert(
+        archive.to_path_buf(),
+        Index {
+            modified,
+            len,
+            entries: entries.clone(),
+        },
+    );
+    Ok(entries)
+}
+
+/// List the nodes directly under the path inside the archive.
+pub fn list(archive: &Path, inner: &Path) -> io::Result<Vec<Node>> {
+    let mut children = BTreeMap::new();
+    for entry in entries(archive)?.iter() {
+        if let Ok(rest) = entry.path.strip_prefix(inner) {
+            let mut components = rest.components();
+            if let Some(name) = components.next() {
+                let name = name.as_os_str().to_string_lossy().to_string();
+                if components.next().is_none() {
+                    children.insert(name, (entry.is_dir, entry.size, entry.modified));
+                } else {
+                    // Not every archive has entries for the directories.
+                    children.entry(name).or_insert((true, 0, None));
+                };
+            };
+        };
+    }
+
+    if children.is_empty() && inner.components().next().is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} not found in the archive", inner.to_string_lossy()),
+        ));
+    };
+
+    let parent = archive.join(inner).to_string_lossy().to_string();
+    Ok(children
+        .into_iter()
+        .map(|(name, (is_dir, size, modified))| {
+            Node::in_archive(parent.clone(), name, is_dir, size, modified)
+        })
+        .collect())
+}
+
+fn extract_path(path: &str, dest: &Path) -> io::Result<()> {
+    let (archive, inner) = split(path)
+        .filter(|(_, inner)| inner.components().next().is_some())
+        .ok_or_else(|| io::Error::other("not in an archive"))?;
+    let base = inner.parent().unwrap_or(&inner).to_path_buf();
+
+    let mut found = false;
+    walk(&archive, &mut |entry, reader| {
+        if !entry.path.starts_with(&inner) {
+            return Ok(());
+        };
+        found = true;
+
+        let to = dest.join(entry.path.strip_prefix(&base).unwrap_or(&entry.path));
+        if entry.is_dir {
+            fs::create_dir_all(&to)
+        } else {
+            if let Some(parent) = to.parent() {
+                fs::create_dir_all(parent)?;
+            };
+            let mut writer = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&to)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", to.display(), e)))?;
+            io::copy(reader, &mut writer).map(|_| ())
+        }
+    })?;
+
+    if found {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "not found in the archive",
+        ))
+    }
+}
+
+/// Extract the paths inside archives, with whatever is under them, into the
+/// destination directory in the background, without overwriting anything.
+/// See [`batch_runner::for_each`](batch_runner::for_each).
+pub fn extract(paths: Vec<String>, dest: String, tx: Sender<Task>) {
+    let summary = format!("extracted {} paths into {}", paths.len(), dest);
+    let step = move |path: &str| extract_path(path, Path::new(&dest)).map_err(|e| e.to_string());
+    batch_runner::for_each(paths, step, summary, tx);
+}
@@ -0,0 +1,57 @@
+use crate::app::{ExternalMsg, Task};
+use crate::worker::{self, send};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::Sender;
+
+fn run(command: &str, path: &str) -> Result<(), String> {
+    let output = Command::new("bash")
+        .args(["-c", command, "xplr", path])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match stderr.lines().rev().find(|l| !l.trim().is_empty()) {
+            Some(line) => Err(format!("{}: {}", output.status, line.trim())),
+            None => Err(output.status.to_string()),
+        }
+    }
+}
+
+/// Run the step in the background once per path, one after another. A
+/// failure is logged and doesn't stop the rest, and the summary is logged at
+/// the end with the number of failures.
+pub fn for_each<F>(paths: Vec<String>, step: F, summary: String, tx: Sender<Task>)
+where
+    F: Fn(&str) -> Result<(), String> + Send + 'static,
+{
+    worker::spawn(move || {
+        let mut failed = 0;
+        for path in paths.iter() {
+            if let Err(e) = step(path) {
+                failed += 1;
+                send(&tx, ExternalMsg::LogError(format!("{}: {}", path, e)));
+            };
+        }
+
+        let summary = format!("{}, {} failed", summary, failed);
+        if failed == 0 {
+            send(&tx, ExternalMsg::LogSuccess(summary));
+        } else {
+            send(&tx, ExternalMsg::LogError(summary));
+        };
+        send(&tx, ExternalMsg::Explore);
+    });
+}
+
+/// Run the bash command once per path with the path as `$1`, like
+/// [`for_each`](for_each).
+pub fn run_on_each(command: String, paths: Vec<String>, tx: Sender<Task>) {
+    let summary = format!("ran {:?} on {} paths", command, paths.len());
+    for_each(paths, move |path| run(&command, path), summary, tx);
+}
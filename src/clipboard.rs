@@ -0,0 +1,51 @@
+use anyhow::{bail, Result};
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The commands to try, in order, for copying text to the system clipboard.
+fn candidates() -> Vec<(&'static str, Vec<&'static str>)> {
+    if cfg!(target_os = "macos") {
+        vec![("pbcopy", vec![])]
+    } else if env::var_os("WAYLAND_DISPLAY").is_some() {
+        vec![("wl-copy", vec![])]
+    } else {
+        vec![
+            ("xclip", vec!["-selection", "clipboard"]),
+            ("xsel", vec!["--clipboard", "--input"]),
+        ]
+    }
+}
+
+fn copy_with(command: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    };
+
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        bail!("{} exited with {}", command, status)
+    }
+}
+
+/// Copy the text to the system clipboard using the first clipboard command
+/// that works.
+pub fn copy(text: &str) -> Result<()> {
+    let mut errors = vec![];
+    for (command, args) in candidates() {
+        match copy_with(command, &args, text) {
+            Ok(()) => return Ok(()),
+            Err(e) => errors.push(format!("{}: {}", command, e)),
+        }
+    }
+    bail!("cannot copy to the clipboard ({})", errors.join(", "))
+}
@@ -1,16 +1,95 @@
+use crate::app::Command;
 use crate::app::ExternalMsg;
+use crate::app::GitStatus;
 use crate::app::HelpMenuLine;
+use crate::app::Node;
 use crate::app::NodeFilter;
 use crate::app::NodeSorter;
 use crate::app::NodeSorterApplicable;
 use crate::default_config;
-use crate::ui::Style;
-use anyhow::Result;
+use crate::input::Key;
+use anyhow::{bail, Result};
 use indexmap::IndexSet;
-use serde::{Deserialize, Serialize};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
 use tui::layout::Constraint as TuiConstraint;
+use tui::style::{Color, Modifier, Style as TuiStyle};
+
+lazy_static! {
+    pub static ref NO_COLOR: bool = env::var("NO_COLOR").ok().map(|_| true).unwrap_or(false);
+    pub static ref DEFAULT_STYLE: TuiStyle = TuiStyle::default();
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub add_modifier: Option<Modifier>,
+    pub sub_modifier: Option<Modifier>,
+}
+
+impl Style {
+    pub fn extend(mut self, other: Self) -> Self {
+        self.fg = other.fg.or(self.fg);
+        self.bg = other.bg.or(self.bg);
+        self.add_modifier = other.add_modifier.or(self.add_modifier);
+        self.sub_modifier = other.sub_modifier.or(self.sub_modifier);
+        self
+    }
+}
+
+impl From<TuiStyle> for Style {
+    fn from(s: TuiStyle) -> Self {
+        Self {
+            fg: s.fg,
+            bg: s.bg,
+            add_modifier: Some(s.add_modifier),
+            sub_modifier: Some(s.sub_modifier),
+        }
+    }
+}
+
+impl Into<TuiStyle> for Style {
+    fn into(self) -> TuiStyle {
+        if *NO_COLOR {
+            *DEFAULT_STYLE
+        } else {
+            TuiStyle {
+                fg: self.fg,
+                bg: self.bg,
+                add_modifier: self.add_modifier.unwrap_or_else(Modifier::empty),
+                sub_modifier: self.sub_modifier.unwrap_or_else(Modifier::empty),
+            }
+        }
+    }
+}
+
+/// Serialize the map sorted by its keys, so that the dumped config is stable.
+fn ordered_map<K, V, S>(map: &HashMap<K, V>, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    K: Ord + Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// A condition on the state of the app, checked before running an action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActionCondition {
+    FocusIsDirectory,
+    FocusIsFile,
+    SelectionIsEmpty,
+    SelectionIsNotEmpty,
+    ReadOnly,
+    NotReadOnly,
+}
 
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -20,28 +99,76 @@ pub struct Action {
 
     #[serde(default)]
     pub messages: Vec<ExternalMsg>,
+
+    /// When extending an existing action, append the messages to it instead
+    /// of replacing them.
+    #[serde(default)]
+    pub append_messages: Option<bool>,
+
+    /// Run the messages only when this condition holds.
+    #[serde(default)]
+    pub condition: Option<ActionCondition>,
+
+    /// The action to fall through to when the condition doesn't hold.
+    #[serde(default)]
+    pub otherwise: Option<Box<Action>>,
 }
 
 impl Action {
-    pub fn sanitized(self, read_only: bool) -> Option<Self> {
-        if self.messages.is_empty() {
-            None
-        } else if read_only {
-            if self.messages.iter().all(|m| m.is_read_only()) {
-                Some(self)
-            } else {
-                None
-            }
+    /// The action, or its fallback, that can run in the given mode. An action
+    /// without messages is dropped so that the key falls through, while an
+    /// action of only `NoOp` is kept to swallow the key.
+    pub fn sanitized(mut self, read_only: bool) -> Option<Self> {
+        self.otherwise = self
+            .otherwise
+            .and_then(|a| a.sanitized(read_only))
+            .map(Box::new);
+
+        // An action that can never run leaves its place to the fallback.
+        if self.messages.is_empty()
+            || (read_only && !self.messages.iter().all(|m| m.is_read_only()))
+        {
+            self.otherwise.map(|a| *a)
         } else {
             Some(self)
         }
     }
 
+    /// The first action in the chain whose condition holds, if any.
+    pub fn applicable(&self, is_met: &dyn Fn(ActionCondition) -> bool) -> Option<&Self> {
+        if self.condition.map(is_met).unwrap_or(true) {
+            Some(self)
+        } else {
+            self.otherwise.as_ref().and_then(|a| a.applicable(is_met))
+        }
+    }
+
     pub fn extend(mut self, other: Self) -> Self {
-        self.help = other.help.or(self.help);
-        self.messages = other.messages;
+        // An explicitly empty help clears the inherited one.
+        self.help = match other.help {
+            Some(help) if help.is_empty() => None,
+            Some(help) => Some(help),
+            None => self.help,
+        };
+        if other.append_messages.unwrap_or_default() {
+            self.messages.extend(other.messages);
+        } else {
+            self.messages = other.messages;
+        }
+        self.condition = other.condition.or(self.condition);
+        self.otherwise = match (self.otherwise, other.otherwise) {
+            (Some(this), Some(other)) => Some(Box::new(this.extend(*other))),
+            (this, other) => other.or(this),
+        };
         self
     }
+
+    fn extend_option(this: Option<Self>, other: Option<Self>) -> Option<Self> {
+        match (this, other) {
+            (Some(this), Some(other)) => Some(this.extend(other)),
+            (this, other) => other.or(this),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -50,14 +177,26 @@ pub struct NodeTypeConfig {
     #[serde(default)]
     pub style: Style,
 
-    #[serde(default)]
+    #[serde(default, serialize_with = "ordered_map")]
     pub meta: HashMap<String, String>,
+
+    /// The command to open the nodes of this type with. The path of the node
+    /// is passed as the last argument.
+    #[serde(default)]
+    pub open_command: Option<Command>,
+
+    /// The icon, e.g. a Nerd Font glyph, available as `{{{icon}}}` in the
+    /// table row formats.
+    #[serde(default)]
+    pub icon: Option<String>,
 }
 
 impl NodeTypeConfig {
     pub fn extend(mut self, other: Self) -> Self {
         self.style = self.style.extend(other.style);
         self.meta.extend(other.meta);
+        self.open_command = other.open_command.or(self.open_command);
+        self.icon = other.icon.or(self.icon);
         self
     }
 }
@@ -74,17 +213,53 @@ pub struct NodeTypesConfig {
     #[serde(default)]
     pub symlink: NodeTypeConfig,
 
-    #[serde(default)]
+    #[serde(default, serialize_with = "ordered_map")]
     pub mime_essence: HashMap<String, NodeTypeConfig>,
 
-    #[serde(default)]
+    #[serde(default, serialize_with = "ordered_map")]
     pub extension: HashMap<String, NodeTypeConfig>,
 
-    #[serde(default)]
+    #[serde(default, serialize_with = "ordered_map")]
     pub special: HashMap<String, NodeTypeConfig>,
 }
 
 impl NodeTypesConfig {
+    /// The node types matching the given node, in the order of precedence:
+    /// special, extension, mime essence, then symlink, directory or file.
+    pub fn matching<'a>(&'a self, node: &Node) -> impl Iterator<Item = &'a NodeTypeConfig> {
+        let kind = if node.is_symlink {
+            &self.symlink
+        } else if node.is_dir {
+            &self.directory
+        } else {
+            &self.file
+        };
+
+        self.special
+            .get(&node.relative_path)
+            .into_iter()
+            .chain(self.extension.get(&node.extension))
+            .chain(self.mime_essence.get(&node.mime_essence))
+            .chain(std::iter::once(kind))
+    }
+
+    /// The node type used to display the given node.
+    pub fn get(&self, node: &Node) -> &NodeTypeConfig {
+        self.matching(node).next().unwrap_or(&self.file)
+    }
+
+    /// The icon of the given node, if any is configured. The `icon` in `meta`
+    /// is still honored for the older configs.
+    pub fn icon(&self, node: &Node) -> Option<&String> {
+        self.matching(node)
+            .find_map(|t| t.icon.as_ref().or_else(|| t.meta.get("icon")))
+    }
+
+    /// The command to open the given node with, if any is configured.
+    pub fn open_command(&self, node: &Node) -> Option<&Command> {
+        self.matching(node).find_map(|t| t.open_command.as_ref())
+    }
+
     fn extend(mut self, other: Self) -> Self {
         self.directory = self.directory.extend(other.directory);
         self.file = self.file.extend(other.file);
@@ -189,6 +364,9 @@ impl Into<TuiConstraint> for Constraint {
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct TableConfig {
+    #[serde(default)]
+    pub title: UiElement,
+
     #[serde(default)]
     pub header: TableRowConfig,
 
@@ -206,16 +384,22 @@ pub struct TableConfig {
 
     #[serde(default)]
     pub col_widths: Option<Vec<Constraint>>,
+
+    /// The sorter of each column, used by `SortByColumn`.
+    #[serde(default)]
+    pub col_sorters: Option<Vec<Option<NodeSorter>>>,
 }
 
 impl TableConfig {
     pub fn extend(mut self, other: Self) -> Self {
+        self.title = self.title.extend(other.title);
         self.header = self.header.extend(other.header);
         self.row = self.row.extend(other.row);
         self.style = self.style.extend(other.style);
         self.tree = other.tree.or(self.tree);
         self.col_spacing = other.col_spacing.or(self.col_spacing);
         self.col_widths = other.col_widths.or(self.col_widths);
+        self.col_sorters = other.col_sorters.or(self.col_sorters);
         self
     }
 }
@@ -229,6 +413,9 @@ pub struct LogsConfig {
     #[serde(default)]
     pub success: UiElement,
 
+    #[serde(default)]
+    pub warning: UiElement,
+
     #[serde(default)]
     pub error: UiElement,
 }
@@ -237,11 +424,76 @@ impl LogsConfig {
     pub fn extend(mut self, other: Self) -> Self {
         self.info = self.info.extend(other.info);
         self.success = self.success.extend(other.success);
+        self.warning = self.warning.extend(other.warning);
         self.error = self.error.extend(other.error);
         self
     }
 }
 
+/// How the git status of the nodes is shown, with `format` as the
+/// `gitStatus` placeholder and `style` on the row.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GitStatusUiConfig {
+    #[serde(default)]
+    pub modified: UiElement,
+
+    #[serde(default)]
+    pub added: UiElement,
+
+    #[serde(default)]
+    pub untracked: UiElement,
+
+    #[serde(default)]
+    pub ignored: UiElement,
+}
+
+impl GitStatusUiConfig {
+    pub fn extend(mut self, other: Self) -> Self {
+        self.modified = self.modified.extend(other.modified);
+        self.added = self.added.extend(other.added);
+        self.untracked = self.untracked.extend(other.untracked);
+        self.ignored = self.ignored.extend(other.ignored);
+        self
+    }
+
+    pub fn get(&self, status: GitStatus) -> &UiElement {
+        match status {
+            GitStatus::Modified => &self.modified,
+            GitStatus::Added => &self.added,
+            GitStatus::Untracked => &self.untracked,
+            GitStatus::Ignored => &self.ignored,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TemplateSelection {
+    /// Use the first template having the same extension as the new file.
+    Extension,
+
+    /// Pick one of the templates having the same extension as the new file.
+    Picker,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplatesConfig {
+    #[serde(default)]
+    pub directory: Option<String>,
+
+    #[serde(default)]
+    pub selection: Option<TemplateSelection>,
+}
+
+impl TemplatesConfig {
+    pub fn extend(mut self, other: Self) -> Self {
+        self.directory = other.directory.or(self.directory);
+        self.selection = other.selection.or(self.selection);
+        self
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct SortDirectionIdentifiersUi {
@@ -269,11 +521,20 @@ pub struct SortAndFilterUi {
     #[serde(default)]
     pub sort_direction_identifiers: SortDirectionIdentifiersUi,
 
-    #[serde(default)]
+    #[serde(default, serialize_with = "ordered_map")]
     pub sorter_identifiers: HashMap<NodeSorter, UiElement>,
 
-    #[serde(default)]
+    #[serde(default, serialize_with = "ordered_map")]
     pub filter_identifiers: HashMap<NodeFilter, UiElement>,
+
+    #[serde(default)]
+    pub quick_filter_identifier: UiElement,
+
+    #[serde(default)]
+    pub reversed_identifier: UiElement,
+
+    #[serde(default)]
+    pub filters_suspended_identifier: UiElement,
 }
 
 impl SortAndFilterUi {
@@ -284,19 +545,245 @@ impl SortAndFilterUi {
             .extend(other.sort_direction_identifiers);
         self.sorter_identifiers.extend(other.sorter_identifiers);
         self.filter_identifiers.extend(other.filter_identifiers);
+        self.quick_filter_identifier = self
+            .quick_filter_identifier
+            .extend(other.quick_filter_identifier);
+        self.reversed_identifier = self.reversed_identifier.extend(other.reversed_identifier);
+        self.filters_suspended_identifier = self
+            .filters_suspended_identifier
+            .extend(other.filters_suspended_identifier);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TreeViewConfig {
+    /// The number of levels listed below the present working directory.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+
+    /// Stop listing after this many nodes, so that huge trees don't hang.
+    #[serde(default)]
+    pub max_nodes: Option<usize>,
+}
+
+impl TreeViewConfig {
+    pub fn extend(mut self, other: Self) -> Self {
+        self.max_depth = other.max_depth.or(self.max_depth);
+        self.max_nodes = other.max_nodes.or(self.max_nodes);
         self
     }
 }
 
+/// The spinner shown while something is running in the background.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpinnerConfig {
+    #[serde(default)]
+    pub frames: Option<Vec<String>>,
+
+    /// The interval between the frames, in milliseconds.
+    #[serde(default)]
+    pub interval: Option<u64>,
+
+    #[serde(default)]
+    pub style: Style,
+}
+
+impl SpinnerConfig {
+    pub fn extend(mut self, other: Self) -> Self {
+        self.frames = other.frames.or(self.frames);
+        self.interval = other.interval.or(self.interval);
+        self.style = self.style.extend(other.style);
+        self
+    }
+}
+
+/// What to do when copying or moving onto paths that already exist.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PasteConflict {
+    /// Ask what to do with each of them.
+    Ask,
+
+    /// Copy or move them with new names, counting up like `name (1).ext`.
+    Rename,
+
+    /// Leave them alone, and don't copy or move the paths onto them.
+    Skip,
+
+    /// Replace them, directories as a whole.
+    Overwrite,
+}
+
+/// How the sizes are shown.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeUnitBase {
+    /// Multiples of 1000, like `kB` and `MB`.
+    Si,
+
+    /// Multiples of 1024, like `KiB` and `MiB`.
+    Binary,
+}
+
+/// Whether to show the hidden nodes, i.e. the ones starting with a `.`.
+///
+/// For backward compatibility, `true` and `false` are read as `Show` and
+/// `Hide`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+pub enum HiddenMode {
+    Show,
+    Hide,
+    Only,
+}
+
+impl HiddenMode {
+    /// The next mode in the `Show` -> `Hide` -> `Only` cycle.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Show => Self::Hide,
+            Self::Hide => Self::Only,
+            Self::Only => Self::Show,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HiddenMode {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        enum Name {
+            Show,
+            Hide,
+            Only,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Bool(bool),
+            Name(Name),
+        }
+
+        Repr::deserialize(deserializer)
+            .map(|r| match r {
+                Repr::Bool(true) | Repr::Name(Name::Show) => Self::Show,
+                Repr::Bool(false) | Repr::Name(Name::Hide) => Self::Hide,
+                Repr::Name(Name::Only) => Self::Only,
+            })
+            .map_err(|_| {
+                serde::de::Error::custom(
+                    "expected one of `Show`, `Hide`, `Only`, `true` or `false`",
+                )
+            })
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct GeneralConfig {
     #[serde(default)]
-    pub show_hidden: Option<bool>,
+    pub show_hidden: Option<HiddenMode>,
 
     #[serde(default)]
     pub read_only: Option<bool>,
 
+    #[serde(default)]
+    pub datetime_format: Option<String>,
+
+    #[serde(default)]
+    pub templates: TemplatesConfig,
+
+    #[serde(default)]
+    pub selection_separator: Option<String>,
+
+    /// The most nodes that can be selected at once. Zero means no limit.
+    #[serde(default)]
+    pub max_selection: Option<usize>,
+
+    #[serde(default)]
+    pub paste_conflict: Option<PasteConflict>,
+
+    /// The directory `GoHome` goes to, with `~` and the environment variables
+    /// expanded. Defaults to `$HOME`.
+    #[serde(default)]
+    pub home_directory: Option<String>,
+
+    /// The path of a named pipe, created if missing, to read the messages
+    /// from as JSON, one per line, throughout the session. A pipe created by
+    /// xplr is removed when it exits.
+    #[serde(default)]
+    pub control_fifo: Option<String>,
+
+    /// The command to diff two files with, getting the paths as `$1` and
+    /// `$2`. The files are diffed internally when unset.
+    #[serde(default)]
+    pub diff_command: Option<String>,
+
+    /// The action to run after entering a new directory, with the commands
+    /// getting its path as `$XPLR_PWD`.
+    #[serde(default)]
+    pub on_directory_change: Option<Action>,
+
+    /// The action to run after the focus moves, with the commands getting
+    /// the focused path as `$XPLR_FOCUS_PATH`.
+    #[serde(default)]
+    pub on_focus_change: Option<Action>,
+
+    /// The milliseconds the focus has to rest before `on_focus_change` runs,
+    /// so that scrolling through the nodes doesn't run it for each of them.
+    #[serde(default)]
+    pub focus_change_debounce: Option<u64>,
+
+    /// The milliseconds to wait for the next key of a key sequence, before
+    /// the keys typed so far run their own bindings.
+    #[serde(default)]
+    pub key_sequence_timeout: Option<u64>,
+
+    /// The milliseconds after which the next key typed ahead starts a new
+    /// prefix.
+    #[serde(default)]
+    pub type_ahead_timeout: Option<u64>,
+
+    /// Whether the prefix typed ahead has to match the case of the names.
+    #[serde(default)]
+    pub type_ahead_case_sensitive: Option<bool>,
+
+    #[serde(default)]
+    pub spinner: SpinnerConfig,
+
+    /// The interval in milliseconds between the ticks that trigger the
+    /// `on_tick` action of the current mode. Ticking is disabled when unset.
+    #[serde(default)]
+    pub tick_interval: Option<u64>,
+
+    #[serde(default)]
+    pub enable_mouse: Option<bool>,
+
+    /// Style the help menu text between `*` bold and between `_` italic.
+    #[serde(default)]
+    pub help_markup: Option<bool>,
+
+    /// Set the terminal title to the present working directory.
+    #[serde(default)]
+    pub set_terminal_title: Option<bool>,
+
+    #[serde(default)]
+    pub max_recent_directories: Option<usize>,
+
+    /// The most log lines kept, dropping the oldest ones first.
+    #[serde(default)]
+    pub max_logs: Option<usize>,
+
+    #[serde(default)]
+    pub tree_view: TreeViewConfig,
+
+    #[serde(default)]
+    pub recursive_search_max_depth: Option<usize>,
+
     #[serde(default)]
     pub cursor: UiElement,
 
@@ -309,6 +796,40 @@ pub struct GeneralConfig {
     #[serde(default)]
     pub table: TableConfig,
 
+    #[serde(default)]
+    pub tab: UiElement,
+
+    #[serde(default)]
+    pub active_tab: UiElement,
+
+    /// Show the components of the present working directory on a line above
+    /// the table.
+    #[serde(default)]
+    pub show_breadcrumb: Option<bool>,
+
+    /// Draw the panes without borders and titles, leaving more room for the
+    /// listing.
+    #[serde(default)]
+    pub compact: Option<bool>,
+
+    /// Show the sizes in multiples of 1000 with `si`, or of 1024 with
+    /// `binary`, the default. `ToggleSizeUnitBase` switches between them.
+    #[serde(default)]
+    pub size_unit_base: Option<SizeUnitBase>,
+
+    /// Look up the git status of the nodes when exploring a directory in a
+    /// git repository.
+    #[serde(default)]
+    pub git_status: Option<bool>,
+
+    #[serde(default)]
+    pub git_status_ui: GitStatusUiConfig,
+
+    /// The style of the breadcrumb, with the `format` separating its
+    /// components.
+    #[serde(default)]
+    pub breadcrumb: UiElement,
+
     #[serde(default)]
     pub default_ui: UiConfig,
 
@@ -323,21 +844,73 @@ pub struct GeneralConfig {
 
     #[serde(default)]
     pub initial_sorting: Option<IndexSet<NodeSorterApplicable>>,
+
+    /// Keep the sorters and filters when changing the directory, instead of
+    /// resetting them to the initial ones.
+    #[serde(default)]
+    pub persist_explorer_config_across_directories: Option<bool>,
+
+    /// The names of the messages not worth repeating with `RepeatLastAction`.
+    /// Actions made up of only these messages are not remembered.
+    #[serde(default)]
+    pub repeat_ignored_messages: Option<IndexSet<String>>,
 }
 
 impl GeneralConfig {
     pub fn extend(mut self, other: Self) -> Self {
         self.show_hidden = other.show_hidden.or(self.show_hidden);
         self.read_only = other.read_only.or(self.read_only);
+        self.datetime_format = other.datetime_format.or(self.datetime_format);
+        self.templates = self.templates.extend(other.templates);
+        self.selection_separator = other.selection_separator.or(self.selection_separator);
+        self.max_selection = other.max_selection.or(self.max_selection);
+        self.paste_conflict = other.paste_conflict.or(self.paste_conflict);
+        self.home_directory = other.home_directory.or(self.home_directory);
+        self.diff_command = other.diff_command.or(self.diff_command);
+        self.control_fifo = other.control_fifo.or(self.control_fifo);
+        self.on_directory_change =
+            Action::extend_option(self.on_directory_change, other.on_directory_change);
+        self.on_focus_change = Action::extend_option(self.on_focus_change, other.on_focus_change);
+        self.focus_change_debounce = other.focus_change_debounce.or(self.focus_change_debounce);
+        self.key_sequence_timeout = other.key_sequence_timeout.or(self.key_sequence_timeout);
+        self.type_ahead_timeout = other.type_ahead_timeout.or(self.type_ahead_timeout);
+        self.type_ahead_case_sensitive = other
+            .type_ahead_case_sensitive
+            .or(self.type_ahead_case_sensitive);
+        self.spinner = self.spinner.extend(other.spinner);
+        self.tick_interval = other.tick_interval.or(self.tick_interval);
+        self.enable_mouse = other.enable_mouse.or(self.enable_mouse);
+        self.help_markup = other.help_markup.or(self.help_markup);
+        self.set_terminal_title = other.set_terminal_title.or(self.set_terminal_title);
+        self.max_recent_directories = other.max_recent_directories.or(self.max_recent_directories);
+        self.max_logs = other.max_logs.or(self.max_logs);
+        self.tree_view = self.tree_view.extend(other.tree_view);
+        self.recursive_search_max_depth = other
+            .recursive_search_max_depth
+            .or(self.recursive_search_max_depth);
         self.cursor = self.cursor.extend(other.cursor);
         self.prompt = self.prompt.extend(other.prompt);
         self.logs = self.logs.extend(other.logs);
         self.table = self.table.extend(other.table);
+        self.tab = self.tab.extend(other.tab);
+        self.active_tab = self.active_tab.extend(other.active_tab);
+        self.show_breadcrumb = other.show_breadcrumb.or(self.show_breadcrumb);
+        self.compact = other.compact.or(self.compact);
+        self.size_unit_base = other.size_unit_base.or(self.size_unit_base);
+        self.git_status = other.git_status.or(self.git_status);
+        self.git_status_ui = self.git_status_ui.extend(other.git_status_ui);
+        self.breadcrumb = self.breadcrumb.extend(other.breadcrumb);
         self.default_ui = self.default_ui.extend(other.default_ui);
         self.focus_ui = self.focus_ui.extend(other.focus_ui);
         self.selection_ui = self.selection_ui.extend(other.selection_ui);
         self.sort_and_filter_ui = self.sort_and_filter_ui.extend(other.sort_and_filter_ui);
         self.initial_sorting = other.initial_sorting.or(self.initial_sorting);
+        self.persist_explorer_config_across_directories = other
+            .persist_explorer_config_across_directories
+            .or(self.persist_explorer_config_across_directories);
+        self.repeat_ignored_messages = other
+            .repeat_ignored_messages
+            .or(self.repeat_ignored_messages);
         self
     }
 }
@@ -360,11 +933,75 @@ pub struct KeyBindings {
     #[serde(default)]
     pub on_special_character: Option<Action>,
 
+    #[serde(default)]
+    pub on_paste: Option<Action>,
+
+    #[serde(default)]
+    pub on_tick: Option<Action>,
+
     #[serde(default)]
     pub default: Option<Action>,
+
+    /// The key sequences of the `on_key` and `remaps` entries, parsed once
+    /// the config is loaded instead of on every key press.
+    #[serde(skip)]
+    key_sequences: HashMap<String, Vec<String>>,
 }
 
 impl KeyBindings {
+    /// The names of the keys of an `on_key` or `remaps` entry, more than one
+    /// for a key sequence. The keys of a sequence are separated by spaces,
+    /// like `ctrl-x ctrl-s`, or typed out, like `gg`, if they're characters.
+    pub fn key_sequence(key: &str) -> Vec<String> {
+        let is_key_name = key.chars().count() == 1
+            || serde_yaml::from_value::<Key>(serde_yaml::Value::String(key.into())).is_ok();
+        if key.contains(char::is_whitespace) {
+            key.split_whitespace().map(String::from).collect()
+        } else if !is_key_name {
+            key.chars().map(|c| Key::from(c).to_string()).collect()
+        } else {
+            vec![key.into()]
+        }
+    }
+
+    /// Parse the key sequences of the entries ahead of the key presses.
+    pub fn with_key_sequences(mut self) -> Self {
+        self.key_sequences = self
+            .on_key
+            .keys()
+            .chain(self.remaps.keys())
+            .map(|k| (k.clone(), Self::key_sequence(k)))
+            .collect();
+        self
+    }
+
+    /// The key sequence of the entry, as parsed at load if it was.
+    fn parsed_key_sequence(&self, key: &str) -> Cow<'_, [String]> {
+        match self.key_sequences.get(key) {
+            Some(seq) => Cow::Borrowed(seq),
+            None => Cow::Owned(Self::key_sequence(key)),
+        }
+    }
+
+    /// Whether any entry is a longer key sequence starting with the given
+    /// keys.
+    pub fn is_key_sequence_prefix(&self, keys: &[String]) -> bool {
+        self.on_key.keys().chain(self.remaps.keys()).any(|k| {
+            let seq = self.parsed_key_sequence(k);
+            seq.len() > keys.len() && seq.iter().zip(keys).all(|(a, b)| a == b)
+        })
+    }
+
+    /// The action bound to exactly the key sequence, remapped or not.
+    pub fn key_sequence_action(&self, keys: &[String]) -> Option<&Action> {
+        let matches = |k: &String| *self.parsed_key_sequence(k) == *keys;
+        self.remaps
+            .iter()
+            .find(|(k, _)| matches(k))
+            .and_then(|(_, to)| self.on_key.get(to))
+            .or_else(|| self.on_key.iter().find(|(k, _)| matches(k)).map(|(_, a)| a))
+    }
+
     pub fn sanitized(mut self, read_only: bool) -> Self {
         if read_only {
             self.on_key = self
@@ -378,6 +1015,8 @@ impl KeyBindings {
             self.on_special_character = self
                 .on_special_character
                 .and_then(|a| a.sanitized(read_only));
+            self.on_paste = self.on_paste.and_then(|a| a.sanitized(read_only));
+            self.on_tick = self.on_tick.and_then(|a| a.sanitized(read_only));
             self.default = self.default.and_then(|a| a.sanitized(read_only));
             self.remaps = self
                 .remaps
@@ -393,11 +1032,21 @@ impl KeyBindings {
 
     pub fn extend(mut self, other: Self) -> Self {
         self.remaps.extend(other.remaps);
-        self.on_key.extend(other.on_key);
-        self.on_alphabet = other.on_alphabet.or(self.on_alphabet);
-        self.on_number = other.on_number.or(self.on_number);
-        self.on_special_character = other.on_special_character.or(self.on_special_character);
-        self.default = other.default.or(self.default);
+        self.key_sequences.extend(other.key_sequences);
+        for (key, action) in other.on_key {
+            let action = match self.on_key.remove(&key) {
+                Some(a) => a.extend(action),
+                None => action,
+            };
+            self.on_key.insert(key, action);
+        }
+        self.on_alphabet = Action::extend_option(self.on_alphabet, other.on_alphabet);
+        self.on_number = Action::extend_option(self.on_number, other.on_number);
+        self.on_special_character =
+            Action::extend_option(self.on_special_character, other.on_special_character);
+        self.on_paste = Action::extend_option(self.on_paste, other.on_paste);
+        self.on_tick = Action::extend_option(self.on_tick, other.on_tick);
+        self.default = Action::extend_option(self.default, other.default);
         self
     }
 }
@@ -414,12 +1063,22 @@ pub struct Mode {
     #[serde(default)]
     pub extra_help: Option<String>,
 
+    /// Overrides the global `read_only` setting for this mode.
+    #[serde(default)]
+    pub read_only: Option<bool>,
+
+    /// The mode whose key bindings this one's are layered on top of, like
+    /// `input` for the cursor and editing keys of the input modes.
+    #[serde(default)]
+    pub extends: Option<String>,
+
     #[serde(default)]
     pub key_bindings: KeyBindings,
 }
 
 impl Mode {
     pub fn sanitized(mut self, read_only: bool) -> Self {
+        let read_only = self.read_only.unwrap_or(read_only);
         self.key_bindings = self.key_bindings.sanitized(read_only);
         self
     }
@@ -427,6 +1086,8 @@ impl Mode {
     pub fn extend(mut self, other: Self) -> Self {
         self.help = other.help.or(self.help);
         self.extra_help = other.extra_help.or(self.extra_help);
+        self.read_only = other.read_only.or(self.read_only);
+        self.extends = other.extends.or(self.extends);
         self.key_bindings = self.key_bindings.extend(other.key_bindings);
         self
     }
@@ -479,6 +1140,20 @@ impl Mode {
                             .map(|a| ("[spcl chars]", a.help.clone()))
                             .filter_map(|(k, mh)| mh.map(|h| HelpMenuLine::KeyMap(k.into(), h))),
                     )
+                    .chain(
+                        self.key_bindings
+                            .on_paste
+                            .iter()
+                            .map(|a| ("[paste]", a.help.clone()))
+                            .filter_map(|(k, mh)| mh.map(|h| HelpMenuLine::KeyMap(k.into(), h))),
+                    )
+                    .chain(
+                        self.key_bindings
+                            .on_tick
+                            .iter()
+                            .map(|a| ("[tick]", a.help.clone()))
+                            .filter_map(|(k, mh)| mh.map(|h| HelpMenuLine::KeyMap(k.into(), h))),
+                    )
                     .chain(
                         self.key_bindings
                             .default
@@ -515,9 +1190,21 @@ pub struct BuiltinModesConfig {
     #[serde(default)]
     pub go_to: Mode,
 
+    #[serde(default)]
+    pub go_to_path: Mode,
+
+    #[serde(default)]
+    pub jump: Mode,
+
     #[serde(default)]
     pub rename: Mode,
 
+    #[serde(default)]
+    pub set_permissions: Mode,
+
+    #[serde(default)]
+    pub set_owner: Mode,
+
     #[serde(default)]
     pub delete: Mode,
 
@@ -538,6 +1225,25 @@ pub struct BuiltinModesConfig {
 
     #[serde(default)]
     pub sort: Mode,
+
+    #[serde(default)]
+    pub picker: Mode,
+
+    #[serde(default)]
+    pub command_palette: Mode,
+
+    #[serde(default)]
+    pub confirm: Mode,
+
+    #[serde(default)]
+    pub record_macro: Mode,
+
+    #[serde(default)]
+    pub play_macro: Mode,
+
+    /// The cursor and editing keys shared by the input modes.
+    #[serde(default)]
+    pub input: Mode,
 }
 
 impl BuiltinModesConfig {
@@ -545,10 +1251,14 @@ impl BuiltinModesConfig {
         self.default = self.default.extend(other.default);
         self.selection_ops = self.selection_ops.extend(other.selection_ops);
         self.go_to = self.go_to.extend(other.go_to);
+        self.go_to_path = self.go_to_path.extend(other.go_to_path);
+        self.jump = self.jump.extend(other.jump);
         self.create = self.create.extend(other.create);
         self.create_file = self.create_file.extend(other.create_file);
         self.create_directory = self.create_directory.extend(other.create_directory);
         self.rename = self.rename.extend(other.rename);
+        self.set_permissions = self.set_permissions.extend(other.set_permissions);
+        self.set_owner = self.set_owner.extend(other.set_owner);
         self.delete = self.delete.extend(other.delete);
         self.number = self.number.extend(other.number);
         self.action = self.action.extend(other.action);
@@ -561,9 +1271,46 @@ impl BuiltinModesConfig {
             .relative_path_does_not_contain
             .extend(other.relative_path_does_not_contain);
         self.sort = self.sort.extend(other.sort);
+        self.picker = self.picker.extend(other.picker);
+        self.command_palette = self.command_palette.extend(other.command_palette);
+        self.confirm = self.confirm.extend(other.confirm);
+        self.record_macro = self.record_macro.extend(other.record_macro);
+        self.play_macro = self.play_macro.extend(other.play_macro);
+        self.input = self.input.extend(other.input);
         self
     }
 
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut Mode> {
+        vec![
+            &mut self.default,
+            &mut self.selection_ops,
+            &mut self.create,
+            &mut self.create_directory,
+            &mut self.create_file,
+            &mut self.number,
+            &mut self.go_to,
+            &mut self.go_to_path,
+            &mut self.jump,
+            &mut self.rename,
+            &mut self.set_permissions,
+            &mut self.set_owner,
+            &mut self.delete,
+            &mut self.action,
+            &mut self.search,
+            &mut self.filter,
+            &mut self.relative_path_does_contain,
+            &mut self.relative_path_does_not_contain,
+            &mut self.sort,
+            &mut self.picker,
+            &mut self.command_palette,
+            &mut self.confirm,
+            &mut self.record_macro,
+            &mut self.play_macro,
+            &mut self.input,
+        ]
+        .into_iter()
+    }
+
     pub fn get(&self, name: &str) -> Option<&Mode> {
         match name {
             "default" => Some(&self.default),
@@ -577,9 +1324,17 @@ impl BuiltinModesConfig {
             "number" => Some(&self.number),
             "go to" => Some(&self.go_to),
             "go_to" => Some(&self.go_to),
+            "go to path" => Some(&self.go_to_path),
+            "go_to_path" => Some(&self.go_to_path),
+            "jump" => Some(&self.jump),
             "rename" => Some(&self.rename),
+            "set permissions" => Some(&self.set_permissions),
+            "set_permissions" => Some(&self.set_permissions),
+            "set owner" => Some(&self.set_owner),
+            "set_owner" => Some(&self.set_owner),
             "delete" => Some(&self.delete),
             "action" => Some(&self.action),
+            "action to" => Some(&self.action),
             "search" => Some(&self.search),
             "sort" => Some(&self.sort),
             "filter" => Some(&self.filter),
@@ -587,6 +1342,15 @@ impl BuiltinModesConfig {
             "relative path does contain" => Some(&self.relative_path_does_contain),
             "relative_path_does_not_contain" => Some(&self.relative_path_does_not_contain),
             "relative path does not contain" => Some(&self.relative_path_does_not_contain),
+            "picker" => Some(&self.picker),
+            "command palette" => Some(&self.command_palette),
+            "command_palette" => Some(&self.command_palette),
+            "confirm" => Some(&self.confirm),
+            "record macro" => Some(&self.record_macro),
+            "record_macro" => Some(&self.record_macro),
+            "play macro" => Some(&self.play_macro),
+            "play_macro" => Some(&self.play_macro),
+            "input" => Some(&self.input),
             _ => None,
         }
     }
@@ -598,13 +1362,16 @@ pub struct ModesConfig {
     #[serde(default)]
     pub builtin: BuiltinModesConfig,
 
-    #[serde(default)]
+    #[serde(default, serialize_with = "ordered_map")]
     pub custom: HashMap<String, Mode>,
 }
 
 impl ModesConfig {
     pub fn get(&self, name: &str) -> Option<&Mode> {
-        self.builtin.get(name).or_else(|| self.custom.get(name))
+        self.builtin
+            .get(name)
+            .or_else(|| self.custom.get(name))
+            .or_else(|| self.custom.values().find(|m| m.name == name))
     }
 
     pub fn extend(mut self, other: Self) -> Self {
@@ -612,6 +1379,115 @@ impl ModesConfig {
         self.custom.extend(other.custom);
         self
     }
+
+    /// Layer the key bindings of each mode on top of those of the mode it
+    /// extends, once all the config files are layered, and parse their key
+    /// sequences.
+    pub fn resolved(mut self) -> Self {
+        let modes = self.clone();
+        for mode in self.builtin.iter_mut().chain(self.custom.values_mut()) {
+            mode.key_bindings = modes
+                .key_bindings(mode, &mut vec![mode.name.clone()])
+                .with_key_sequences();
+            mode.extends = None;
+        }
+        self
+    }
+
+    /// The key bindings of the mode with those of the modes it extends. A
+    /// mode extending itself, even through others, stops there.
+    fn key_bindings(&self, mode: &Mode, seen: &mut Vec<String>) -> KeyBindings {
+        let base = mode
+            .extends
+            .as_ref()
+            .filter(|name| !seen.contains(name))
+            .and_then(|name| {
+                seen.push(name.clone());
+                self.get(name)
+            });
+
+        match base {
+            Some(base) => self
+                .key_bindings(base, seen)
+                .extend(mode.key_bindings.clone()),
+            None => mode.key_bindings.clone(),
+        }
+    }
+}
+
+/// The panes to draw around the table. The input and logs line is always
+/// drawn below it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutConfig {
+    #[serde(default)]
+    pub sort_and_filter: Option<bool>,
+
+    #[serde(default)]
+    pub selection: Option<bool>,
+
+    #[serde(default)]
+    pub help_menu: Option<bool>,
+
+    /// Show the preview of the focused directory even when
+    /// `TogglePreviewPaneShowsChildren` didn't turn it on.
+    #[serde(default)]
+    pub preview: Option<bool>,
+}
+
+impl LayoutConfig {
+    pub fn extend(mut self, other: Self) -> Self {
+        self.sort_and_filter = other.sort_and_filter.or(self.sort_and_filter);
+        self.selection = other.selection.or(self.selection);
+        self.help_menu = other.help_menu.or(self.help_menu);
+        self.preview = other.preview.or(self.preview);
+        self
+    }
+}
+
+/// Find the config files to load, highest precedence first:
+///
+/// 1. The file passed with `--config`, which must exist.
+/// 2. `$XDG_CONFIG_HOME/xplr/config.yml`
+/// 3. `~/.config/xplr/config.yml`
+///
+/// The missing files at the other locations are skipped.
+pub fn discover_config_files(explicit: Option<PathBuf>) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+
+    if let Some(file) = explicit {
+        if !file.is_file() {
+            bail!("config file {} not found", file.to_string_lossy())
+        };
+        files.push(file);
+    };
+
+    for file in config_file_locations() {
+        if file.is_file() && !files.contains(&file) {
+            files.push(file);
+        };
+    }
+
+    Ok(files)
+}
+
+/// The locations looked at for the config files, highest precedence first.
+/// The platform's config directory comes last, e.g.
+/// `~/Library/Application Support` on macOS.
+pub fn config_file_locations() -> Vec<PathBuf> {
+    let xdg = env::var_os("XDG_CONFIG_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from);
+    let home = dirs::home_dir().map(|d| d.join(".config"));
+
+    let mut locations = vec![];
+    for dir in xdg.into_iter().chain(home).chain(dirs::config_dir()) {
+        let file = dir.join("xplr").join("config.yml");
+        if !locations.contains(&file) {
+            locations.push(file);
+        };
+    }
+    locations
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -627,6 +1503,15 @@ pub struct Config {
 
     #[serde(default)]
     pub modes: ModesConfig,
+
+    /// The layouts to switch between with `SwitchLayout` and `CycleLayout`.
+    /// The one named `default` is used at first.
+    #[serde(default)]
+    pub layouts: BTreeMap<String, LayoutConfig>,
+
+    /// Named sequences of messages to run in place of `CallFunction`.
+    #[serde(default, serialize_with = "ordered_map")]
+    pub functions: HashMap<String, Vec<ExternalMsg>>,
 }
 
 impl Default for Config {
@@ -636,16 +1521,31 @@ impl Default for Config {
             general: default_config::general(),
             node_types: default_config::node_types(),
             modes: default_config::modes(),
+            layouts: default_config::layouts(),
+            functions: default_config::functions(),
         }
     }
 }
 
 impl Config {
-    pub fn extended(mut self) -> Self {
-        let default = Self::default();
-        self.general = default.general.extend(self.general);
-        self.node_types = default.node_types.extend(self.node_types);
-        self.modes = default.modes.extend(self.modes);
+    pub fn extended(self) -> Self {
+        Self::default().extend(self)
+    }
+
+    /// Layer the other config on top of this one.
+    pub fn extend(mut self, other: Self) -> Self {
+        self.version = other.version;
+        self.general = self.general.extend(other.general);
+        self.node_types = self.node_types.extend(other.node_types);
+        self.modes = self.modes.extend(other.modes);
+        for (name, layout) in other.layouts {
+            let layout = match self.layouts.remove(&name) {
+                Some(base) => base.extend(layout),
+                None => layout,
+            };
+            self.layouts.insert(name, layout);
+        }
+        self.functions.extend(other.functions);
         self
     }
 
@@ -665,6 +1565,7 @@ impl Config {
 
     pub fn is_compatible(&self) -> Result<bool> {
         let result = match self.parsed_version()? {
+            (0, 5, 5) => true,
             (0, 5, 4) => true,
             (0, 5, 3) => true,
             (0, 5, 2) => true,
@@ -0,0 +1,54 @@
+use crate::app::Task;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What a task waits for. A newer wait replaces the pending one of the same
+/// kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wait {
+    FocusChange,
+    KeySequence,
+}
+
+/// Send each task once its delay passes without a newer one of the same kind,
+/// all the waits sharing one thread.
+pub fn keep_debouncing(tx_msg_in: Sender<Task>, rx_debouncer: Receiver<(Wait, Duration, Task)>) {
+    thread::spawn(move || {
+        let mut pending: Vec<(Wait, Instant, Task)> = vec![];
+        let mut disconnected = false;
+        loop {
+            let now = Instant::now();
+            let (due, waiting): (Vec<_>, Vec<_>) =
+                pending.into_iter().partition(|(_, at, _)| *at <= now);
+            pending = waiting;
+            for (_, _, task) in due {
+                if tx_msg_in.send(task).is_err() {
+                    return;
+                };
+            }
+
+            let next = pending.iter().map(|(_, at, _)| *at).min();
+            let received = match (next, disconnected) {
+                (None, true) => return,
+                (Some(at), true) => {
+                    thread::sleep(at.saturating_duration_since(now));
+                    continue;
+                }
+                (Some(at), false) => rx_debouncer.recv_timeout(at.saturating_duration_since(now)),
+                (None, false) => rx_debouncer
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match received {
+                Ok((wait, delay, task)) => {
+                    pending.retain(|(w, _, _)| *w != wait);
+                    pending.push((wait, Instant::now() + delay, task));
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => disconnected = true,
+            };
+        }
+    });
+}
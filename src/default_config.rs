@@ -1,7 +1,10 @@
 const DEFAULT_CONFIG_YAML: &str = include_str!("config.yml");
 
+use crate::app::ExternalMsg;
 use crate::config::{self, Config};
 use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 
 lazy_static! {
     static ref DEFAULT_CONFIG: Config = serde_yaml::from_str(DEFAULT_CONFIG_YAML).unwrap();
@@ -22,3 +25,11 @@ pub fn node_types() -> config::NodeTypesConfig {
 pub fn modes() -> config::ModesConfig {
     DEFAULT_CONFIG.modes.clone()
 }
+
+pub fn layouts() -> BTreeMap<String, config::LayoutConfig> {
+    DEFAULT_CONFIG.layouts.clone()
+}
+
+pub fn functions() -> HashMap<String, Vec<ExternalMsg>> {
+    DEFAULT_CONFIG.functions.clone()
+}
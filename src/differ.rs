@@ -0,0 +1,162 @@
+use crate::app::{CommandOutput, ExternalMsg, InternalMsg, MsgIn, Task};
+use crate::output_capturer::MAX_OUTPUT_BYTES;
+use crate::worker;
+use std::fs;
+use std::io::{self, Read};
+use std::process::Command;
+use std::sync::mpsc::Sender;
+
+/// The lines of context around the changes.
+const CONTEXT: usize = 3;
+
+/// The internal diff compares at most this many pairs of lines.
+const MAX_COMPARISONS: usize = 4_000_000;
+
+/// Whether the file looks binary, i.e. has a NUL byte near the start.
+fn is_binary(path: &str) -> io::Result<bool> {
+    let mut buf = vec![];
+    fs::File::open(path)?.take(8192).read_to_end(&mut buf)?;
+    Ok(buf.contains(&0))
+}
+
+/// The unified diff of the lines of the texts, or `None` if they're too long
+/// to compare.
+pub fn line_diff(a: &str, b: &str) -> Option<String> {
+    let a = a.lines().collect::<Vec<&str>>();
+    let b = b.lines().collect::<Vec<&str>>();
+
+    // Only the part between the common prefix and suffix needs comparing.
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (n, m) = (a.len() - prefix - suffix, b.len() - prefix - suffix);
+    if n * m > MAX_COMPARISONS {
+        return None;
+    };
+
+    // The length of the longest common subsequence of a[i..] and b[j..].
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[prefix + i] == b[prefix + j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // The edits as (kind, index in a, index in b).
+    let mut ops = (0..prefix).map(|i| (' ', i, i)).collect::<Vec<_>>();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a[prefix + i] == b[prefix + j] {
+            ops.push((' ', prefix + i, prefix + j));
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', prefix + i, prefix + j));
+            i += 1;
+        } else {
+            ops.push(('+', prefix + i, prefix + j));
+            j += 1;
+        }
+    }
+    ops.extend((0..suffix).map(|k| (' ', prefix + n + k, prefix + m + k)));
+
+    let changes = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (kind, _, _))| *kind != ' ')
+        .map(|(k, _)| k)
+        .collect::<Vec<usize>>();
+
+    let mut diff = String::new();
+    let mut k = 0;
+    while k < changes.len() {
+        let start = changes[k].saturating_sub(CONTEXT);
+        let mut end = changes[k] + CONTEXT + 1;
+        k += 1;
+        while k < changes.len() && changes[k] <= end + CONTEXT {
+            end = changes[k] + CONTEXT + 1;
+            k += 1;
+        }
+
+        let (_, i, j) = ops[start];
+        diff.push_str(&format!("@@ -{} +{} @@\n", i + 1, j + 1));
+        for (kind, i, j) in &ops[start..end.min(ops.len())] {
+            let line = if *kind == '+' { b[*j] } else { a[*i] };
+            diff.push_str(&format!("{}{}\n", kind, line));
+        }
+    }
+    Some(diff)
+}
+
+fn run(command: &str, a: &str, b: &str) -> io::Result<(bool, String)> {
+    let output = Command::new("bash")
+        .args(["-c", command, "xplr", a, b])
+        .output()?;
+
+    // Diff commands exit with 1 when the files differ.
+    if output.stdout.is_empty() {
+        Ok((
+            output.status.success(),
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ))
+    } else {
+        Ok((true, String::from_utf8_lossy(&output.stdout).to_string()))
+    }
+}
+
+fn diff(a: &str, b: &str, command: Option<&str>) -> Result<(bool, String), String> {
+    for path in [a, b] {
+        if is_binary(path).map_err(|e| format!("{}: {}", path, e))? {
+            return Err(format!("{} is a binary file, not diffing it", path));
+        };
+    }
+
+    match command {
+        Some(command) => run(command, a, b).map_err(|e| e.to_string()),
+        None => {
+            let read = |p| fs::read_to_string(p).map_err(|e| format!("{}: {}", p, e));
+            match line_diff(&read(a)?, &read(b)?) {
+                Some(d) if d.is_empty() => Ok((true, "The files are identical".into())),
+                Some(d) => Ok((true, format!("--- {}\n+++ {}\n{}", a, b, d))),
+                None => Err("The files are too long to compare, set a diff_command".into()),
+            }
+        }
+    }
+}
+
+/// Diff the text files in the background with the command, getting the paths
+/// as `$1` and `$2`, or internally if there's no command, and show the result.
+pub fn compare(a: String, b: String, command: Option<String>, tx: Sender<Task>) {
+    worker::spawn(move || {
+        let msg = match diff(&a, &b, command.as_deref()) {
+            Ok((success, mut text)) => {
+                let truncated = text.len() as u64 > MAX_OUTPUT_BYTES;
+                if truncated {
+                    let mut end = MAX_OUTPUT_BYTES as usize;
+                    while !text.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    text.truncate(end);
+                };
+
+                MsgIn::Internal(InternalMsg::SetCommandOutput(CommandOutput {
+                    command: command.unwrap_or_else(|| "diff".into()),
+                    path: format!("{} {}", a, b),
+                    success,
+                    text,
+                    truncated,
+                }))
+            }
+            Err(e) => MsgIn::External(ExternalMsg::LogWarning(e)),
+        };
+        tx.send(Task::new(msg, None)).unwrap();
+    });
+}
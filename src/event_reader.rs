@@ -1,19 +1,36 @@
 use crate::app::Task;
-use crate::app::{ExternalMsg, InternalMsg, MsgIn};
+use crate::app::{ExternalMsg, InternalMsg, MouseAction, MouseInput, MsgIn};
 use crate::input::Key;
-use crossterm::event::{self, Event};
+use crossterm::event::{self, Event, MouseButton, MouseEventKind};
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-pub fn keep_reading(tx_msg_in: Sender<Task>, rx_event_reader: Receiver<bool>) {
+/// Two clicks on the same cell within this interval make a double click.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+pub fn keep_reading(
+    tx_msg_in: Sender<Task>,
+    rx_event_reader: Receiver<bool>,
+    tick_interval: Option<Duration>,
+) {
     thread::spawn(move || {
         let mut is_paused = false;
+        let mut ticked_at = Instant::now();
+        let mut last_click: Option<(Instant, u16, u16)> = None;
         loop {
             if let Ok(paused) = rx_event_reader.try_recv() {
                 is_paused = paused;
             };
 
+            if let Some(interval) = tick_interval {
+                if !is_paused && ticked_at.elapsed() >= interval {
+                    ticked_at = Instant::now();
+                    let msg = MsgIn::Internal(InternalMsg::Tick);
+                    tx_msg_in.send(Task::new(msg, None)).unwrap();
+                };
+            };
+
             if !is_paused && event::poll(std::time::Duration::from_millis(1)).unwrap() {
                 match event::read() {
                     Ok(Event::Key(key)) => {
@@ -22,6 +39,45 @@ pub fn keep_reading(tx_msg_in: Sender<Task>, rx_event_reader: Receiver<bool>) {
                         tx_msg_in.send(Task::new(msg, Some(key))).unwrap();
                     }
 
+                    Ok(Event::Paste(text)) => {
+                        let msg = MsgIn::Internal(InternalMsg::HandlePaste(text));
+                        tx_msg_in.send(Task::new(msg, None)).unwrap();
+                    }
+
+                    Ok(Event::Mouse(mouse)) => {
+                        let action = match mouse.kind {
+                            MouseEventKind::Down(MouseButton::Left) => {
+                                let is_double = last_click
+                                    .map(|(at, column, row)| {
+                                        at.elapsed() <= DOUBLE_CLICK_INTERVAL
+                                            && column == mouse.column
+                                            && row == mouse.row
+                                    })
+                                    .unwrap_or(false);
+
+                                if is_double {
+                                    last_click = None;
+                                    Some(MouseAction::DoubleClick)
+                                } else {
+                                    last_click = Some((Instant::now(), mouse.column, mouse.row));
+                                    Some(MouseAction::Click)
+                                }
+                            }
+                            MouseEventKind::ScrollUp => Some(MouseAction::ScrollUp),
+                            MouseEventKind::ScrollDown => Some(MouseAction::ScrollDown),
+                            _ => None,
+                        };
+
+                        if let Some(action) = action {
+                            let msg = MsgIn::Internal(InternalMsg::HandleMouse(MouseInput {
+                                action,
+                                column: mouse.column,
+                                row: mouse.row,
+                            }));
+                            tx_msg_in.send(Task::new(msg, None)).unwrap();
+                        };
+                    }
+
                     Ok(Event::Resize(_, _)) => {
                         let msg = MsgIn::External(ExternalMsg::Refresh);
                         tx_msg_in.send(Task::new(msg, None)).unwrap();
@@ -1,9 +1,99 @@
 use crate::app::{DirectoryBuffer, ExplorerConfig, ExternalMsg, InternalMsg, MsgIn, Node, Task};
+use crate::archive;
+use crate::worker;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::Sender;
-use std::thread;
 
+/// The most nodes listed in the preview of a directory.
+pub const MAX_PREVIEW_NODES: usize = 1000;
+
+/// List the filtered and sorted nodes of the directory, with the number of
+/// nodes before filtering. No more than the given number of nodes is read.
+fn list(config: &ExplorerConfig, parent: &str, max: usize) -> io::Result<(Vec<Node>, usize)> {
+    if let Some((archive, inner)) = archive::split(parent) {
+        let mut nodes = archive::list(&archive, &inner)?;
+        nodes.truncate(max);
+        return Ok(filter_and_sort(config, nodes));
+    };
+
+    let nodes = fs::read_dir(parent)?
+        .take(max)
+        .filter_map(|d| {
+            d.ok().map(|e| {
+                e.path()
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            })
+        })
+        .map(|name| Node::new(parent.to_string(), name))
+        .collect::<Vec<Node>>();
+
+    Ok(filter_and_sort(config, nodes))
+}
+
+fn filter_and_sort(config: &ExplorerConfig, nodes: Vec<Node>) -> (Vec<Node>, usize) {
+    let unfiltered_total = nodes.len();
+    let mut nodes = nodes
+        .into_iter()
+        .filter(|n| config.filter(n))
+        .collect::<Vec<Node>>();
+    nodes.sort_by(|a, b| config.sort(a, b));
+    (nodes, unfiltered_total)
+}
+
+/// Add the nodes to the tree at the given depth, each followed by its subtree
+/// if it's expanded. Symlinks to directories aren't followed. Returns false if
+/// the tree got cut short at the maximum number of nodes.
+fn grow(
+    config: &ExplorerConfig,
+    depth: usize,
+    nodes: Vec<Node>,
+    max_nodes: usize,
+    tree: &mut Vec<Node>,
+) -> bool {
+    for mut node in nodes {
+        if tree.len() >= max_nodes {
+            return false;
+        };
+
+        node.depth = depth;
+        let path = node.absolute_path.clone();
+        let descend = node.is_dir && config.is_expanded(&node);
+        tree.push(node);
+
+        if descend {
+            if let Ok((children, _)) = list(config, &path, usize::MAX) {
+                if !grow(config, depth + 1, children, max_nodes, tree) {
+                    return false;
+                };
+            };
+        };
+    }
+    true
+}
+
+/// List the nodes of the directory, each followed by its subtree if it's
+/// expanded, with the number of nodes before filtering. The tree, even with
+/// only a few directories expanded in the flat listing, is cut short at the
+/// maximum number of nodes, which is the third value.
+pub fn list_tree(config: &ExplorerConfig, parent: &str) -> io::Result<(Vec<Node>, usize, bool)> {
+    let (nodes, unfiltered_total) = list(config, parent, usize::MAX)?;
+    let mut tree = vec![];
+    let whole = grow(config, 0, nodes, config.max_nodes(), &mut tree);
+    Ok((tree, unfiltered_total, !whole))
+}
+
+/// List the nodes of the directory for the preview pane. Only the first nodes
+/// read, as many as are shown at most, are filtered and sorted.
+pub fn list_preview(config: &ExplorerConfig, parent: &str) -> io::Result<(Vec<Node>, usize)> {
+    list(config, parent, MAX_PREVIEW_NODES)
+}
+
+/// List the directory in the background, focusing the given path, which is
+/// either relative to the directory or absolute.
 pub fn explore(
     config: ExplorerConfig,
     parent: String,
@@ -13,39 +103,37 @@ pub fn explore(
     let path = PathBuf::from(&parent);
     let path_cloned = path.clone();
     let tx_cloned = tx.clone();
-    let config_cloned = config.clone();
-
-    thread::spawn(move || {
-        fs::read_dir(&path)
-            .map(|dirs| {
-                let mut nodes = dirs
-                    .filter_map(|d| {
-                        d.ok().map(|e| {
-                            e.path()
-                                .file_name()
-                                .map(|n| n.to_string_lossy().to_string())
-                                .unwrap_or_default()
-                        })
-                    })
-                    .map(|name| Node::new(parent.clone(), name))
-                    .filter(|n| config.filter(n))
-                    .collect::<Vec<Node>>();
-                nodes.sort_by(|a, b| config.sort(a, b));
-                nodes
+    let config_cloned = config.clone().flat();
+
+    worker::spawn(move || {
+        list_tree(&config, &parent)
+            .map(|(nodes, unfiltered_total, cut_short)| {
+                if cut_short {
+                    tx.send(Task::new(
+                        MsgIn::External(ExternalMsg::LogWarning(format!(
+                            "The tree is cut short at {} nodes",
+                            config.max_nodes()
+                        ))),
+                        None,
+                    ))
+                    .unwrap();
+                };
+                (nodes, unfiltered_total)
             })
-            .map(|nodes| {
+            .map(|(nodes, unfiltered_total)| {
                 let focus_index = if let Some(focus) = focused_path {
                     nodes
                         .iter()
                         .enumerate()
-                        .find(|(_, n)| n.relative_path == focus)
+                        .find(|(_, n)| Path::new(&n.absolute_path) == path.join(&focus))
                         .map(|(i, _)| i)
                         .unwrap_or(0)
                 } else {
                     0
                 };
 
-                let dir = DirectoryBuffer::new(parent.clone(), nodes, focus_index);
+                let dir =
+                    DirectoryBuffer::new(parent.clone(), nodes, unfiltered_total, focus_index);
 
                 tx.send(Task::new(
                     MsgIn::Internal(InternalMsg::AddDirectory(parent, dir)),
@@ -73,3 +161,18 @@ pub fn explore(
         );
     }
 }
+
+/// List the directory in the background for the preview pane, without its
+/// subtree or its parents. Unreadable directories are left without a preview.
+pub fn preview(config: ExplorerConfig, parent: String, tx: Sender<Task>) {
+    worker::spawn(move || {
+        if let Ok((nodes, unfiltered_total)) = list_preview(&config, &parent) {
+            let dir = DirectoryBuffer::new(parent, nodes, unfiltered_total, 0);
+            tx.send(Task::new(
+                MsgIn::Internal(InternalMsg::SetPreview(dir)),
+                None,
+            ))
+            .unwrap();
+        };
+    });
+}
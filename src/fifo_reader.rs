@@ -0,0 +1,62 @@
+use crate::app::{ExternalMsg, Task};
+use crate::worker::send;
+use nix::sys::stat::Mode;
+use nix::unistd;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::thread;
+
+/// Create the named pipe at the given path, unless there's one already.
+/// Tells whether it was created, for only such a pipe to be removed at the
+/// end.
+pub fn create(path: &str) -> io::Result<bool> {
+    match Path::new(path).symlink_metadata() {
+        Ok(meta) if meta.file_type().is_fifo() => Ok(false),
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a named pipe", path),
+        )),
+        Err(_) => {
+            unistd::mkfifo(path, Mode::S_IRUSR | Mode::S_IWUSR)?;
+            Ok(true)
+        }
+    }
+}
+
+/// Read the messages written to the named pipe as JSON, one per line, for as
+/// long as the app runs. The pipe is reopened each time the writers close it.
+/// Malformed lines are logged and skipped.
+pub fn keep_reading(path: String, tx: Sender<Task>) {
+    thread::spawn(move || loop {
+        // Opening blocks until there's a writer.
+        let file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                send(&tx, ExternalMsg::LogError(format!("{}: {}", path, e)));
+                return;
+            }
+        };
+
+        for line in BufReader::new(file).lines() {
+            let line = match line {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => line,
+                Err(e) => {
+                    send(&tx, ExternalMsg::LogError(format!("{}: {}", path, e)));
+                    break;
+                }
+            };
+
+            match serde_json::from_str::<ExternalMsg>(&line) {
+                Ok(msg) => send(&tx, msg),
+                Err(e) => send(
+                    &tx,
+                    ExternalMsg::LogError(format!("{}: skipped {}: {}", path, line.trim(), e)),
+                ),
+            };
+        }
+    });
+}
@@ -0,0 +1,287 @@
+use crate::app::{
+    InternalMsg, MsgIn, Task, Transfer, TransferKind, TransferProgress, TransferredItem,
+};
+use crate::worker;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Progress is reported at most this often, so that small transfers finish
+/// before a progress bar ever shows up.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+struct Reporter<'a> {
+    progress: TransferProgress,
+    reported_at: Instant,
+    cancelled: &'a AtomicBool,
+    tx: &'a Sender<Task>,
+}
+
+impl Reporter<'_> {
+    fn check(&self) -> io::Result<()> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            Err(io::Error::other("cancelled"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn advance(&mut self, bytes: u64) -> io::Result<()> {
+        self.progress.done += bytes;
+        if self.reported_at.elapsed() >= PROGRESS_INTERVAL {
+            self.reported_at = Instant::now();
+            send(
+                self.tx,
+                InternalMsg::SetTransferProgress(self.progress.clone()),
+            );
+        };
+        self.check()
+    }
+}
+
+fn send(tx: &Sender<Task>, msg: InternalMsg) {
+    tx.send(Task::new(MsgIn::Internal(msg), None)).unwrap();
+}
+
+/// The total size of the regular files under the given path, without
+/// following symlinks.
+fn size(path: &Path) -> u64 {
+    match path.symlink_metadata() {
+        Ok(meta) if meta.is_dir() => fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|e| size(&e.path()))
+            .sum(),
+        Ok(meta) if meta.is_file() => meta.len(),
+        _ => 0,
+    }
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    if path.symlink_metadata()?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+fn copy(from: &Path, to: &Path, reporter: &mut Reporter) -> io::Result<()> {
+    reporter.check()?;
+    let meta = from.symlink_metadata()?;
+    if meta.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy(&entry.path(), &to.join(entry.file_name()), reporter)?;
+        }
+        fs::set_permissions(to, meta.permissions())
+    } else if meta.file_type().is_symlink() {
+        symlink(fs::read_link(from)?, to)
+    } else if meta.is_file() {
+        let mut reader = fs::File::open(from)?;
+        let mut writer = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(to)?;
+
+        let mut buf = vec![0; BUFFER_SIZE];
+        loop {
+            let len = reader.read(&mut buf)?;
+            if len == 0 {
+                break;
+            };
+            writer.write_all(&buf[..len])?;
+            reporter.advance(len as u64)?;
+        }
+        fs::set_permissions(to, meta.permissions())
+    } else {
+        Err(io::Error::other(format!(
+            "cannot copy special file {}",
+            from.to_string_lossy()
+        )))
+    }
+}
+
+/// A path next to the given one that doesn't exist, to put things aside.
+fn sibling(path: &Path, purpose: &str) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut count = 0;
+    loop {
+        let candidate = path.with_file_name(format!(
+            ".{}.xplr-{}-{}-{}",
+            name,
+            purpose,
+            std::process::id(),
+            count
+        ));
+        if candidate.symlink_metadata().is_err() {
+            return candidate;
+        };
+        count += 1;
+    }
+}
+
+/// Put the new path in place of the existing one, which is only removed once
+/// the new one took its place.
+fn replace_with(new: &Path, existing: &Path) -> io::Result<()> {
+    let old = sibling(existing, "old");
+    fs::rename(existing, &old)?;
+    if let Err(e) = fs::rename(new, existing) {
+        fs::rename(&old, existing)?;
+        return Err(e);
+    };
+    remove(&old)
+}
+
+fn transfer_path(
+    kind: TransferKind,
+    from: &Path,
+    to: &Path,
+    replace: bool,
+    reporter: &mut Reporter,
+) -> io::Result<()> {
+    if to.symlink_metadata().is_err() {
+        return transfer_new_path(kind, from, to, reporter);
+    };
+
+    if !replace {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.to_string_lossy()),
+        ));
+    };
+
+    // Replacing the path itself or one of its parents would lose it.
+    if from.starts_with(to) {
+        return Err(io::Error::other(
+            "cannot replace a path with itself or a directory containing it",
+        ));
+    };
+
+    // Transfer next to the existing path first, so that it's left alone if
+    // the transfer fails.
+    let new = sibling(to, "new");
+    transfer_new_path(kind, from, &new, reporter)?;
+    if let Err(err) = replace_with(&new, to) {
+        let restored = match kind {
+            TransferKind::Copy => remove(&new),
+            TransferKind::Move => fs::rename(&new, from),
+        };
+        return match restored {
+            Ok(()) => Err(err),
+            Err(e) => Err(io::Error::new(
+                err.kind(),
+                format!("{}, and it's left at {}: {}", err, new.to_string_lossy(), e),
+            )),
+        };
+    };
+    Ok(())
+}
+
+fn transfer_new_path(
+    kind: TransferKind,
+    from: &Path,
+    to: &Path,
+    reporter: &mut Reporter,
+) -> io::Result<()> {
+    if to.starts_with(from) {
+        return Err(io::Error::other(
+            "cannot copy or move a directory into itself",
+        ));
+    };
+
+    // Moving within the same filesystem is instant, else fall back to copying.
+    let bytes = size(from);
+    if kind == TransferKind::Move && fs::rename(from, to).is_ok() {
+        reporter.progress.done += bytes;
+        return Ok(());
+    };
+
+    if let Err(err) = copy(from, to, reporter) {
+        // Don't leave a partial copy behind.
+        return match remove(to) {
+            Ok(()) => Err(err),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(err),
+            Err(e) => Err(io::Error::new(
+                err.kind(),
+                format!(
+                    "{}, and failed to clean up the partial copy {}: {}",
+                    err,
+                    to.to_string_lossy(),
+                    e
+                ),
+            )),
+        };
+    };
+
+    if kind == TransferKind::Move {
+        remove(from)?;
+    };
+    Ok(())
+}
+
+/// Copy or move the paths on a worker thread, reporting the progress, the
+/// result of each path, and the end of the transfer back to the app.
+pub fn transfer(transfer: Transfer, cancelled: Arc<AtomicBool>, tx: Sender<Task>) {
+    worker::spawn(move || {
+        let kind = transfer.kind;
+        let total = transfer
+            .items
+            .iter()
+            .map(|(from, _)| size(Path::new(from)))
+            .sum();
+
+        let mut reporter = Reporter {
+            progress: TransferProgress {
+                kind,
+                current: Default::default(),
+                done: 0,
+                total,
+            },
+            reported_at: Instant::now(),
+            cancelled: &cancelled,
+            tx: &tx,
+        };
+
+        for (from, to) in transfer.items {
+            reporter.progress.current = from.clone();
+            let replace = transfer.replace.contains(&to);
+            let error = reporter
+                .check()
+                .and_then(|_| {
+                    transfer_path(
+                        kind,
+                        Path::new(&from),
+                        Path::new(&to),
+                        replace,
+                        &mut reporter,
+                    )
+                })
+                .err()
+                .map(|e| e.to_string());
+
+            send(
+                &tx,
+                InternalMsg::FinishTransferItem(TransferredItem {
+                    kind,
+                    from,
+                    to,
+                    error,
+                }),
+            );
+        }
+
+        send(&tx, InternalMsg::FinishTransfer);
+    });
+}
@@ -0,0 +1,534 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::fs::{chown, symlink, MetadataExt, PermissionsExt};
+use std::path::{Component, Path, PathBuf};
+
+/// A filesystem operation performed by xplr, recorded so that it can be
+/// undone and redone.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FsOperation {
+    Move {
+        from: String,
+        to: String,
+    },
+    Trash {
+        path: String,
+        trashed: String,
+    },
+    Delete {
+        path: String,
+    },
+    SetPermissions {
+        path: String,
+        from: u32,
+        to: u32,
+    },
+    SetOwner {
+        path: String,
+        from: (u32, u32),
+        to: (u32, u32),
+    },
+    Symlink {
+        link: String,
+        target: String,
+        previous: Option<String>,
+    },
+    /// Operations done at once, e.g. on the selection, undone at once.
+    Batch(Vec<FsOperation>),
+}
+
+impl FsOperation {
+    /// Move or rename a path without overwriting the target.
+    pub fn move_path(from: &str, to: &str) -> Result<Self> {
+        rename(Path::new(from), Path::new(to))?;
+        Ok(Self::Move {
+            from: from.into(),
+            to: to.into(),
+        })
+    }
+
+    /// Move a path into the XDG trash directory.
+    pub fn trash(path: &str) -> Result<Self> {
+        let trashed = trash_files_dir()?.join(trash_name(Path::new(path))?);
+        move_to_trash(Path::new(path), &trashed)?;
+        Ok(Self::Trash {
+            path: path.into(),
+            trashed: trashed.to_string_lossy().to_string(),
+        })
+    }
+
+    /// Permanently delete a path. This can't be undone.
+    pub fn delete(path: &str) -> Result<Self> {
+        let pathbuf = PathBuf::from(path);
+        if pathbuf.symlink_metadata()?.is_dir() {
+            fs::remove_dir_all(&pathbuf)?;
+        } else {
+            fs::remove_file(&pathbuf)?;
+        };
+        Ok(Self::Delete { path: path.into() })
+    }
+
+    /// Change the permissions of a path to the given octal or symbolic mode.
+    /// Like `chmod`, a symlink is followed to its target.
+    #[cfg(unix)]
+    pub fn set_permissions(path: &str, mode: &str) -> Result<Self> {
+        let meta = fs::metadata(path)?;
+        let from = meta.permissions().mode() & 0o7777;
+        let to = parse_mode(mode, from, meta.is_dir())?;
+        chmod(path, to)?;
+        Ok(Self::SetPermissions {
+            path: path.into(),
+            from,
+            to,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn set_permissions(_path: &str, _mode: &str) -> Result<Self> {
+        bail!("setting the permissions is unsupported on this platform")
+    }
+
+    /// Change the owner of a path to the given `user`, `user:group` or
+    /// `:group`.
+    #[cfg(unix)]
+    pub fn set_owner(path: &str, owner: &str) -> Result<Self> {
+        let meta = fs::metadata(path)?;
+        let from = (meta.uid(), meta.gid());
+        let to = parse_owner(owner, from)?;
+        set_owner_ids(path, to)?;
+        Ok(Self::SetOwner {
+            path: path.into(),
+            from,
+            to,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn set_owner(_path: &str, _owner: &str) -> Result<Self> {
+        bail!("changing the owner is unsupported on this platform")
+    }
+
+    /// Create a symlink pointing to the target, without overwriting anything.
+    pub fn create_symlink(link: &str, target: &str) -> Result<Self> {
+        if Path::new(link).symlink_metadata().is_ok() {
+            bail!("{} already exists", link)
+        };
+        symlink(target, link)?;
+        Ok(Self::Symlink {
+            link: link.into(),
+            target: target.into(),
+            previous: None,
+        })
+    }
+
+    /// Point an existing symlink to another target.
+    pub fn retarget_symlink(link: &str, target: &str) -> Result<Self> {
+        let previous = read_link(link)?.to_string_lossy().to_string();
+        replace_symlink(link, target)?;
+        Ok(Self::Symlink {
+            link: link.into(),
+            target: target.into(),
+            previous: Some(previous),
+        })
+    }
+
+    /// The operations as one, if there's more than one.
+    pub fn batch(mut ops: Vec<Self>) -> Option<Self> {
+        if ops.len() > 1 {
+            Some(Self::Batch(ops))
+        } else {
+            ops.pop()
+        }
+    }
+
+    pub fn is_undoable(&self) -> bool {
+        match self {
+            Self::Delete { .. } => false,
+            Self::Batch(ops) => ops.iter().all(|op| op.is_undoable()),
+            _ => true,
+        }
+    }
+
+    pub fn undo(&self) -> Result<()> {
+        match self {
+            Self::Move { from, to } => rename(Path::new(to), Path::new(from)),
+            Self::Trash { path, trashed } => {
+                let trashed = PathBuf::from(trashed);
+                move_across_filesystems(&trashed, Path::new(path))?;
+                fs::remove_file(trash_info_path(&trashed)?)?;
+                Ok(())
+            }
+            Self::Delete { path } => bail!("cannot undo the permanent deletion of {}", path),
+            Self::Batch(ops) => ops.iter().rev().try_for_each(|op| op.undo()),
+            Self::SetPermissions { path, from, .. } => chmod(path, *from),
+            Self::SetOwner { path, from, .. } => set_owner_ids(path, *from),
+            Self::Symlink {
+                link,
+                previous: None,
+                ..
+            } => {
+                read_link(link)?;
+                fs::remove_file(link)?;
+                Ok(())
+            }
+            Self::Symlink {
+                link,
+                previous: Some(previous),
+                ..
+            } => replace_symlink(link, previous),
+        }
+    }
+
+    pub fn redo(&self) -> Result<()> {
+        match self {
+            Self::Move { from, to } => rename(Path::new(from), Path::new(to)),
+            Self::Trash { path, trashed } => move_to_trash(Path::new(path), Path::new(trashed)),
+            Self::Delete { path } => bail!("cannot redo the permanent deletion of {}", path),
+            Self::Batch(ops) => ops.iter().try_for_each(|op| op.redo()),
+            Self::SetPermissions { path, to, .. } => chmod(path, *to),
+            Self::SetOwner { path, to, .. } => set_owner_ids(path, *to),
+            Self::Symlink {
+                link,
+                target,
+                previous: None,
+            } => Self::create_symlink(link, target).map(|_| ()),
+            Self::Symlink { link, target, .. } => replace_symlink(link, target),
+        }
+    }
+}
+
+impl fmt::Display for FsOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Move { from, to } => write!(f, "move {} to {}", from, to),
+            Self::Trash { path, .. } => write!(f, "trash {}", path),
+            Self::Delete { path } => write!(f, "delete {}", path),
+            Self::SetPermissions { path, to, .. } => {
+                write!(f, "set the permissions of {} to {:04o}", path, to)
+            }
+            Self::SetOwner { path, to, .. } => {
+                write!(f, "change the owner of {} to {}:{}", path, to.0, to.1)
+            }
+            Self::Symlink { link, target, .. } => write!(f, "link {} to {}", link, target),
+            Self::Batch(ops) => write!(
+                f,
+                "{}",
+                ops.iter()
+                    .map(|op| op.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn chmod(path: &str, mode: u32) -> Result<()> {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn chmod(_path: &str, _mode: u32) -> Result<()> {
+    bail!("setting the permissions is unsupported on this platform")
+}
+
+#[cfg(unix)]
+fn set_owner_ids(path: &str, (uid, gid): (u32, u32)) -> Result<()> {
+    chown(path, Some(uid), Some(gid))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_owner_ids(_path: &str, _ids: (u32, u32)) -> Result<()> {
+    bail!("changing the owner is unsupported on this platform")
+}
+
+#[cfg(not(unix))]
+fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(_target: P, _link: Q) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symlinks are unsupported on this platform",
+    ))
+}
+
+/// Apply an octal mode like `755`, or a comma separated list of symbolic
+/// modes like `u+x,go-w` to the current mode, as `chmod` does.
+pub fn parse_mode(spec: &str, mode: u32, is_dir: bool) -> Result<u32> {
+    let invalid = || anyhow!("invalid mode {:?}", spec);
+
+    if !spec.is_empty() && spec.len() <= 4 && spec.chars().all(|c| ('0'..='7').contains(&c)) {
+        return Ok(u32::from_str_radix(spec, 8)?);
+    };
+
+    let mut mode = mode & 0o7777;
+    for clause in spec.split(',') {
+        let (who, mut ops) = clause.split_at(clause.find(['+', '-', '=']).ok_or_else(invalid)?);
+
+        // The special bits go with the class they apply to.
+        let mut mask = 0;
+        for c in who.chars() {
+            mask |= match c {
+                'u' => 0o4700,
+                'g' => 0o2070,
+                'o' => 0o1007,
+                'a' => 0o7777,
+                _ => return Err(invalid()),
+            };
+        }
+        if who.is_empty() {
+            mask = 0o7777;
+        };
+
+        while let Some(op) = ops.chars().next() {
+            let end = ops[1..].find(['+', '-', '=']).map_or(ops.len(), |i| i + 1);
+            let mut bits = 0;
+            for c in ops[1..end].chars() {
+                bits |= match c {
+                    'r' => 0o444,
+                    'w' => 0o222,
+                    'x' => 0o111,
+                    'X' if is_dir || mode & 0o111 != 0 => 0o111,
+                    'X' => 0,
+                    's' => 0o6000,
+                    't' => 0o1000,
+                    _ => return Err(invalid()),
+                };
+            }
+            let bits = bits & mask;
+
+            mode = match op {
+                '+' => mode | bits,
+                '-' => mode & !bits,
+                _ => (mode & !mask) | bits,
+            };
+            ops = &ops[end..];
+        }
+    }
+
+    Ok(mode)
+}
+
+/// Resolve `user`, `user:group` or `:group`, as names or ids, keeping the
+/// current ids for the omitted parts.
+#[cfg(unix)]
+pub fn parse_owner(spec: &str, (uid, gid): (u32, u32)) -> Result<(u32, u32)> {
+    let (user, group) = match spec.split_once(':') {
+        Some((user, group)) => (user, group),
+        None => (spec, ""),
+    };
+
+    if user.is_empty() && group.is_empty() {
+        bail!("invalid owner {:?}", spec)
+    };
+
+    let uid = if user.is_empty() {
+        uid
+    } else {
+        match user.parse() {
+            Ok(uid) => uid,
+            Err(_) => match users::get_user_by_name(user) {
+                Some(u) => u.uid(),
+                None => bail!("no such user {}", user),
+            },
+        }
+    };
+
+    let gid = if group.is_empty() {
+        gid
+    } else {
+        match group.parse() {
+            Ok(gid) => gid,
+            Err(_) => match users::get_group_by_name(group) {
+                Some(g) => g.gid(),
+                None => bail!("no such group {}", group),
+            },
+        }
+    };
+
+    Ok((uid, gid))
+}
+
+fn read_link(link: &str) -> Result<PathBuf> {
+    if !Path::new(link).symlink_metadata()?.file_type().is_symlink() {
+        bail!("{} is not a symlink", link)
+    };
+    Ok(fs::read_link(link)?)
+}
+
+fn replace_symlink(link: &str, target: &str) -> Result<()> {
+    read_link(link)?;
+    fs::remove_file(link)?;
+    symlink(target, link)?;
+    Ok(())
+}
+
+/// The path of `to` relative to the directory `from`. Both must be absolute.
+pub fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from = from.components().collect::<Vec<Component>>();
+    let to = to.components().collect::<Vec<Component>>();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    for c in &to[common..] {
+        path.push(c);
+    }
+
+    if path.as_os_str().is_empty() {
+        ".".into()
+    } else {
+        path
+    }
+}
+
+fn rename(from: &Path, to: &Path) -> Result<()> {
+    if to.symlink_metadata().is_ok() {
+        bail!("{} already exists", to.to_string_lossy())
+    };
+    fs::rename(from, to)?;
+    Ok(())
+}
+
+/// Move a path, falling back to copying then removing it when it's on
+/// another filesystem, where it can't be renamed. Nothing is overwritten.
+pub fn move_across_filesystems(from: &Path, to: &Path) -> Result<()> {
+    if to.symlink_metadata().is_ok() {
+        bail!("{} already exists", to.to_string_lossy())
+    };
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            if let Err(e) = copy_recursively(from, to) {
+                // Don't leave a partial copy behind.
+                if to.symlink_metadata().is_ok() {
+                    remove(to).unwrap_or_default();
+                };
+                return Err(e.into());
+            };
+            remove(from)?;
+            Ok(())
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Copy a path with its permissions, without following symlinks.
+fn copy_recursively(from: &Path, to: &Path) -> io::Result<()> {
+    let meta = from.symlink_metadata()?;
+    if meta.file_type().is_symlink() {
+        symlink(fs::read_link(from)?, to)
+    } else if meta.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursively(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::set_permissions(to, meta.permissions())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    if path.symlink_metadata()?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+fn trash_files_dir() -> Result<PathBuf> {
+    match dirs::data_dir() {
+        Some(d) => Ok(d.join("Trash").join("files")),
+        None => bail!("cannot find the trash directory"),
+    }
+}
+
+fn trash_info_path(trashed: &Path) -> Result<PathBuf> {
+    match (
+        trashed.parent().and_then(|p| p.parent()),
+        trashed.file_name(),
+    ) {
+        (Some(trash), Some(name)) => Ok(trash
+            .join("info")
+            .join(format!("{}.trashinfo", name.to_string_lossy()))),
+        _ => bail!("invalid trash path {}", trashed.to_string_lossy()),
+    }
+}
+
+/// Find a name that's not already taken in the trash.
+fn trash_name(path: &Path) -> Result<String> {
+    let name = match path.file_name() {
+        Some(n) => n.to_string_lossy().to_string(),
+        None => bail!("cannot trash {}", path.to_string_lossy()),
+    };
+
+    let files = trash_files_dir()?;
+    let mut candidate = name.clone();
+    let mut count = 1;
+    while files.join(&candidate).symlink_metadata().is_ok() {
+        candidate = format!("{}.{}", name, count);
+        count += 1;
+    }
+    Ok(candidate)
+}
+
+/// The given path if it's not taken, else the first free one of `name (1).ext`,
+/// `name (2).ext`, and so on.
+pub fn available_path(path: &Path) -> PathBuf {
+    available_path_except(path, &HashSet::new())
+}
+
+/// Like `available_path`, but also avoids the reserved paths, e.g. the ones
+/// already picked for the other paths of a batch.
+pub fn available_path_except(path: &Path, reserved: &HashSet<PathBuf>) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut candidate = path.to_path_buf();
+    let mut count = 1;
+    while candidate.symlink_metadata().is_ok() || reserved.contains(&candidate) {
+        candidate = path.with_file_name(format!("{} ({}){}", stem, count, extension));
+        count += 1;
+    }
+    candidate
+}
+
+fn move_to_trash(path: &Path, trashed: &Path) -> Result<()> {
+    let info = trash_info_path(trashed)?;
+    if let Some(dir) = trashed.parent() {
+        fs::create_dir_all(dir)?;
+    };
+    if let Some(dir) = info.parent() {
+        fs::create_dir_all(dir)?;
+    };
+
+    fs::write(
+        &info,
+        format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            path.to_string_lossy(),
+            Local::now().format("%Y-%m-%dT%H:%M:%S")
+        ),
+    )?;
+
+    if let Err(e) = move_across_filesystems(path, trashed) {
+        fs::remove_file(&info).unwrap_or_default();
+        return Err(e);
+    };
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use crate::app::{ExternalMsg, GitStatus, InternalMsg, MsgIn, Task};
+use crate::worker;
+use git2::{Repository, Status, StatusOptions};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+
+fn status_of(status: Status) -> Option<GitStatus> {
+    if status.intersects(
+        Status::WT_MODIFIED
+            | Status::WT_DELETED
+            | Status::WT_RENAMED
+            | Status::WT_TYPECHANGE
+            | Status::INDEX_MODIFIED
+            | Status::INDEX_DELETED
+            | Status::INDEX_RENAMED
+            | Status::INDEX_TYPECHANGE
+            | Status::CONFLICTED,
+    ) {
+        Some(GitStatus::Modified)
+    } else if status.contains(Status::INDEX_NEW) {
+        Some(GitStatus::Added)
+    } else if status.contains(Status::WT_NEW) {
+        Some(GitStatus::Untracked)
+    } else if status.contains(Status::IGNORED) {
+        Some(GitStatus::Ignored)
+    } else {
+        None
+    }
+}
+
+/// The git status of the paths under the directory, by absolute path. A
+/// directory gets the most notable status of the paths in it. Only the
+/// directory is queried, and it's empty outside a repository.
+pub fn statuses(dir: &str) -> Result<HashMap<String, GitStatus>, git2::Error> {
+    let mut statuses = HashMap::new();
+    let repo = match Repository::discover(dir) {
+        Ok(repo) => repo,
+        Err(_) => return Ok(statuses),
+    };
+    let workdir = match repo.workdir() {
+        Some(workdir) => workdir
+            .canonicalize()
+            .map_err(|e| git2::Error::from_str(&e.to_string()))?,
+        None => return Ok(statuses),
+    };
+    let pwd = Path::new(dir);
+    let dir = pwd
+        .canonicalize()
+        .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .include_ignored(true)
+        .recurse_ignored_dirs(false)
+        .disable_pathspec_match(true);
+    if let Ok(relative) = dir.strip_prefix(&workdir) {
+        if !relative.as_os_str().is_empty() {
+            opts.pathspec(relative);
+        };
+    };
+
+    for entry in repo.statuses(Some(&mut opts))?.iter() {
+        let status = match status_of(entry.status()) {
+            Some(status) => status,
+            None => continue,
+        };
+        // Untracked and ignored directories end with a `/`, dropped when
+        // collecting the components.
+        let path = workdir
+            .join(OsStr::from_bytes(entry.path_bytes()))
+            .components()
+            .collect::<PathBuf>();
+
+        // Keyed under the directory as given, which may not be canonical.
+        for relative in path
+            .ancestors()
+            .filter_map(|p| p.strip_prefix(&dir).ok())
+            .take_while(|p| !p.as_os_str().is_empty())
+        {
+            let path = pwd.join(relative).to_string_lossy().to_string();
+            let status = statuses
+                .get(&path)
+                .map_or(status, |s: &GitStatus| status.max(*s));
+            statuses.insert(path, status);
+        }
+    }
+
+    Ok(statuses)
+}
+
+/// The repository of the path, with the path relative to its working
+/// directory.
+fn open(path: &str) -> Result<(Repository, PathBuf), git2::Error> {
+    let path = Path::new(path);
+    let (dir, name) = if path.is_dir() {
+        (path, None)
+    } else {
+        (path.parent().unwrap_or(path), path.file_name())
+    };
+    let repo = Repository::discover(dir).map_err(|_| {
+        git2::Error::from_str(&format!(
+            "{} isn't in a git repository",
+            dir.to_string_lossy()
+        ))
+    })?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("the repository has no working directory"))?
+        .canonicalize()
+        .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+
+    // The file may be gone, when staging its deletion.
+    let mut canonical = dir
+        .canonicalize()
+        .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+    if let Some(name) = name {
+        canonical.push(name);
+    };
+    let relative = canonical
+        .strip_prefix(&workdir)
+        .map_err(|e| git2::Error::from_str(&e.to_string()))?
+        .to_path_buf();
+    Ok((repo, relative))
+}
+
+/// Add the changes of the path, recursively for a directory, to the index,
+/// deleted files included.
+pub fn stage(path: &str) -> Result<(), git2::Error> {
+    let (repo, relative) = open(path)?;
+    let mut index = repo.index()?;
+    index.add_all(
+        [&relative],
+        git2::IndexAddOption::DISABLE_PATHSPEC_MATCH,
+        None,
+    )?;
+    index.update_all([&relative], None)?;
+    index.write()
+}
+
+/// Reset the path in the index to what it is in `HEAD`, or take it out of the
+/// index if there's no commit yet.
+pub fn unstage(path: &str) -> Result<(), git2::Error> {
+    let (repo, relative) = open(path)?;
+    let head = repo.head();
+    match head {
+        Ok(head) => {
+            let commit = head.peel_to_commit()?;
+            repo.reset_default(Some(commit.as_object()), [&relative])?;
+        }
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => {
+            let mut index = repo.index()?;
+            index.remove_all([&relative], None)?;
+            index.write()?;
+        }
+        Err(e) => return Err(e),
+    };
+    Ok(())
+}
+
+pub fn compute(dir: String, tx: Sender<Task>) {
+    worker::spawn(move || {
+        let msg = match statuses(&dir) {
+            Ok(statuses) => MsgIn::Internal(InternalMsg::SetGitStatuses(dir, statuses)),
+            Err(e) => MsgIn::External(ExternalMsg::LogError(format!(
+                "Failed to get the git status of {}: {}",
+                dir, e
+            ))),
+        };
+        tx.send(Task::new(msg, None)).unwrap();
+    });
+}
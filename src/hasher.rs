@@ -0,0 +1,56 @@
+use crate::app::{ExternalMsg, FileHash, HashAlgorithm, InternalMsg, MsgIn, Task};
+use crate::worker;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read};
+use std::sync::mpsc::Sender;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Hash the file chunk by chunk, so that large files aren't loaded whole.
+fn digest<D: Digest>(path: &str) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = D::new();
+    let mut buf = vec![0; BUFFER_SIZE];
+    loop {
+        let len = file.read(&mut buf)?;
+        if len == 0 {
+            break;
+        };
+        hasher.update(&buf[..len]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+pub fn hash(path: String, algorithm: HashAlgorithm, tx: Sender<Task>) {
+    worker::spawn(move || {
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        let digest = match algorithm {
+            HashAlgorithm::Md5 => digest::<Md5>(&path),
+            HashAlgorithm::Sha1 => digest::<Sha1>(&path),
+            HashAlgorithm::Sha256 => digest::<Sha256>(&path),
+        };
+
+        let msg = match digest {
+            Ok(digest) => MsgIn::Internal(InternalMsg::SetFileHash(
+                path,
+                FileHash {
+                    algorithm,
+                    modified,
+                    digest,
+                },
+            )),
+            Err(e) => MsgIn::External(ExternalMsg::LogError(format!(
+                "Failed to hash {}: {}",
+                path, e
+            ))),
+        };
+        tx.send(Task::new(msg, None)).unwrap();
+    });
+}
@@ -3,13 +3,27 @@
 #![allow(clippy::unnecessary_wraps)]
 
 pub mod app;
+pub mod archive;
 pub mod auto_refresher;
+pub mod batch_runner;
+pub mod clipboard;
 pub mod config;
+pub mod debouncer;
 pub mod default_config;
+pub mod differ;
 pub mod event_reader;
 pub mod explorer;
+pub mod fifo_reader;
+pub mod file_transfer;
+pub mod fs_ops;
+pub mod git_status;
+pub mod hasher;
 pub mod input;
+pub mod output_capturer;
 pub mod pipe_reader;
 pub mod pwd_watcher;
+pub mod recursive_search;
 pub mod runner;
+pub mod size_calculator;
 pub mod ui;
+pub mod worker;
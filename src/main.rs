@@ -6,7 +6,24 @@ use xplr::app;
 use xplr::runner;
 
 fn main() {
-    let mut pwd = PathBuf::from(env::args().nth(1).unwrap_or_else(|| ".".into()))
+    let mut path = None;
+    let mut config_file = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            config_file = args.next().map(PathBuf::from).or_else(|| {
+                eprintln!("error: --config requires a file path");
+                std::process::exit(1);
+            });
+        } else if let Some(file) = arg.strip_prefix("--config=") {
+            config_file = Some(PathBuf::from(file));
+        } else if path.is_none() {
+            path = Some(arg);
+        }
+    }
+
+    let mut pwd = PathBuf::from(path.unwrap_or_else(|| ".".into()))
         .canonicalize()
         .unwrap_or_default();
     let mut focused_path = None;
@@ -21,7 +38,7 @@ fn main() {
         pwd = pwd.parent().map(|p| p.into()).unwrap_or_default();
     }
 
-    let app = app::App::create(pwd).unwrap_or_else(|e| {
+    let app = app::App::create_with_config(pwd, config_file).unwrap_or_else(|e| {
         eprintln!("error: {}", e);
         std::process::exit(1);
     });
@@ -0,0 +1,77 @@
+use crate::app::{CommandOutput, InternalMsg, MsgIn, Task};
+use crate::worker;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::Sender;
+use std::thread;
+
+/// The output beyond this many bytes is dropped.
+pub const MAX_OUTPUT_BYTES: u64 = 64 * 1024;
+
+fn read_capped<R: Read>(reader: R) -> io::Result<(String, bool)> {
+    let mut buf = vec![];
+    reader.take(MAX_OUTPUT_BYTES + 1).read_to_end(&mut buf)?;
+    let truncated = buf.len() as u64 > MAX_OUTPUT_BYTES;
+    buf.truncate(MAX_OUTPUT_BYTES as usize);
+    Ok((String::from_utf8_lossy(&buf).to_string(), truncated))
+}
+
+fn run(command: &str, path: &str) -> io::Result<(bool, String, bool)> {
+    let stdin = if Path::new(path).is_file() {
+        Stdio::from(fs::File::open(path)?)
+    } else {
+        Stdio::null()
+    };
+
+    let mut child = Command::new("bash")
+        .args(["-c", command, "xplr", path])
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Read stderr on the side so that neither of the pipes fills up.
+    let stderr = child
+        .stderr
+        .take()
+        .map(|e| thread::spawn(move || read_capped(e)));
+    let stdout = child.stdout.take().map(read_capped).transpose()?;
+
+    // Dropping the pipes stops the command if it keeps writing past the cap.
+    let status = child.wait()?;
+    let stderr = stderr.and_then(|t| t.join().ok()).transpose()?;
+
+    // A command stopped for writing past the cap fails with a broken pipe.
+    let (text, truncated) = stdout.unwrap_or_default();
+    if status.success() || truncated {
+        Ok((true, text, truncated))
+    } else {
+        let (text, truncated) = stderr.unwrap_or_default();
+        Ok((false, text, truncated))
+    }
+}
+
+/// Run the command in the background with the file at the given path as its
+/// stdin and `$1`, and send back the captured output.
+pub fn capture(command: String, path: String, tx: Sender<Task>) {
+    worker::spawn(move || {
+        let (success, text, truncated) =
+            run(&command, &path).unwrap_or_else(|e| (false, e.to_string(), false));
+
+        let output = CommandOutput {
+            command,
+            path,
+            success,
+            text,
+            truncated,
+        };
+
+        tx.send(Task::new(
+            MsgIn::Internal(InternalMsg::SetCommandOutput(output)),
+            None,
+        ))
+        .unwrap();
+    });
+}
@@ -0,0 +1,74 @@
+use crate::app::{InternalMsg, MsgIn, RecursiveSearch, SearchResults, Task};
+use crate::worker;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The results found are sent at most this often, so that the UI isn't
+/// refreshed for every single match.
+const RESULTS_INTERVAL: Duration = Duration::from_millis(100);
+
+fn send(tx: &Sender<Task>, search: &RecursiveSearch, paths: Vec<String>, done: bool) {
+    let results = SearchResults {
+        id: search.id,
+        pattern: search.pattern.clone(),
+        paths,
+        done,
+    };
+    tx.send(Task::new(
+        MsgIn::Internal(InternalMsg::AddSearchResults(results)),
+        None,
+    ))
+    .unwrap();
+}
+
+/// Walk the directories under the root breadth first, without following
+/// symlinks, and send back the paths relative to the root of the nodes whose
+/// names contain the pattern, ignoring case.
+pub fn search(search: RecursiveSearch, cancelled: Arc<AtomicBool>, tx: Sender<Task>) {
+    worker::spawn(move || {
+        let pattern = search.pattern.to_lowercase();
+        let mut queue = VecDeque::from(vec![(PathBuf::new(), 1)]);
+        let mut found = vec![];
+        let mut sent_at = Instant::now();
+
+        while let Some((dir, depth)) = queue.pop_front() {
+            if cancelled.load(Ordering::Relaxed) {
+                return;
+            };
+
+            let entries = match fs::read_dir(PathBuf::from(&search.root).join(&dir)) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+
+            for entry in entries.flatten() {
+                let relative_path = dir.join(entry.file_name());
+                if entry
+                    .file_name()
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .contains(&pattern)
+                {
+                    found.push(relative_path.to_string_lossy().to_string());
+                };
+
+                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                if is_dir && depth < search.max_depth {
+                    queue.push_back((relative_path, depth + 1));
+                };
+            }
+
+            if !found.is_empty() && sent_at.elapsed() >= RESULTS_INTERVAL {
+                sent_at = Instant::now();
+                send(&tx, &search, std::mem::take(&mut found), false);
+            };
+        }
+
+        send(&tx, &search, found, true);
+    });
+}
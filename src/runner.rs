@@ -1,27 +1,78 @@
 #![allow(clippy::too_many_arguments)]
 
 use crate::app;
+use crate::archive;
 use crate::auto_refresher;
+use crate::batch_runner;
+use crate::clipboard;
+use crate::config::SizeUnitBase;
+use crate::debouncer;
+use crate::differ;
 use crate::event_reader;
 use crate::explorer;
+use crate::fifo_reader;
+use crate::file_transfer;
+use crate::git_status;
+use crate::hasher;
+use crate::output_capturer;
 use crate::pipe_reader;
 use crate::pwd_watcher;
+use crate::recursive_search;
+use crate::size_calculator;
 use crate::ui;
+use crate::worker;
 use anyhow::Result;
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+};
 use crossterm::execute;
 use crossterm::terminal as term;
 use handlebars::{handlebars_helper, Handlebars};
-use humansize::{file_size_opts as options, FileSize};
 use std::fs;
 use std::io;
 use std::io::prelude::*;
+use std::path::Path;
 use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
 use termion::get_tty;
 use tui::backend::CrosstermBackend;
 use tui::Terminal;
+use users::UsersCache;
 
-handlebars_helper!(to_humansize: |size: i64| size.file_size(options::CONVENTIONAL).unwrap_or_default());
+handlebars_helper!(to_humansize_si: |size: u64| ui::human_size(size, SizeUnitBase::Si));
+handlebars_helper!(to_humansize_binary: |size: u64| ui::human_size(size, SizeUnitBase::Binary));
+
+fn register_humansize(hb: &mut Handlebars, base: SizeUnitBase) {
+    match base {
+        SizeUnitBase::Si => hb.register_helper("humansize", Box::new(to_humansize_si)),
+        SizeUnitBase::Binary => hb.register_helper("humansize", Box::new(to_humansize_binary)),
+    };
+}
+
+/// The escapes to save the terminal title before changing it, and to restore
+/// it on quit. The terminals without a title stack ignore them.
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
+/// Set the terminal title, saving the original first.
+fn set_title<W: Write>(w: &mut W, title: &str, saved: &mut bool) -> io::Result<()> {
+    if !*saved {
+        write!(w, "{}", PUSH_TITLE)?;
+        *saved = true;
+    };
+    execute!(w, term::SetTitle(title))
+}
+
+/// Write the file, creating the parent directories if needed.
+fn save(file: &Path, content: &str) -> io::Result<()> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    };
+    fs::write(file, content)
+}
 
 fn call(app: &app::App, cmd: app::Command, silent: bool) -> io::Result<ExitStatus> {
     let input_buffer = app.input_buffer().unwrap_or_default();
@@ -52,8 +103,10 @@ fn call(app: &app::App, cmd: app::Command, silent: bool) -> io::Result<ExitStatu
     Command::new(cmd.command.clone())
         .env("XPLR_APP_VERSION", app.version())
         .env("XPLR_CONFIG_VERSION", &app.config().version)
-        .env("XPLR_PID", &app.pid().to_string())
+        .env("XPLR_PID", app.pid().to_string())
         .env("XPLR_INPUT_BUFFER", input_buffer)
+        .env("XPLR_PASTE", app.last_paste().cloned().unwrap_or_default())
+        .env("XPLR_PWD", app.pwd())
         .env("XPLR_FOCUS_PATH", app.focused_node_str())
         .env("XPLR_FOCUS_INDEX", focus_index)
         .env("XPLR_SESSION_PATH", session_path)
@@ -66,6 +119,7 @@ fn call(app: &app::App, cmd: app::Command, silent: bool) -> io::Result<ExitStatu
         .env("XPLR_PIPE_GLOBAL_HELP_MENU_OUT", pipe_global_help_menu_out)
         .env("XPLR_PIPE_DIRECTORY_NODES_OUT", pipe_directory_nodes_out)
         .env("XPLR_PIPE_LOGS_OUT", pipe_logs_out)
+        .current_dir(archive::host_dir(app.pwd()))
         .stdin(stdin)
         .stdout(stdout)
         .stderr(stderr)
@@ -77,21 +131,28 @@ pub fn run(mut app: app::App, focused_path: Option<String>) -> Result<Option<Str
     let (tx_msg_in, rx_msg_in) = mpsc::channel();
     let (tx_event_reader, rx_event_reader) = mpsc::channel();
     let (tx_pwd_watcher, rx_pwd_watcher) = mpsc::channel();
+    let (tx_debouncer, rx_debouncer) = mpsc::channel();
 
     fs::write(&app.pipe().global_help_menu_out, app.global_help_menu_str())?;
 
     explorer::explore(
-        app.explorer_config().clone(),
+        app.explorer_config()
+            .clone()
+            .with_selection(app.selection()),
         app.pwd().clone(),
         focused_path,
         tx_msg_in.clone(),
     );
+    if app.config().general.git_status.unwrap_or_default() {
+        git_status::compute(app.pwd().clone(), tx_msg_in.clone());
+    };
 
     let mut hb = Handlebars::new();
-    hb.register_helper("humansize", Box::new(to_humansize));
+    let mut size_unit_base = app.size_unit_base();
+    register_humansize(&mut hb, size_unit_base);
     hb.register_template_string(
         app::TEMPLATE_TABLE_ROW,
-        &app.config()
+        app.config()
             .general
             .table
             .row
@@ -104,22 +165,86 @@ pub fn run(mut app: app::App, focused_path: Option<String>) -> Result<Option<Str
             .join("\t"),
     )?;
 
+    if let Some(title) = &app.config().general.table.title.format {
+        hb.register_template_string(app::TEMPLATE_TABLE_TITLE, title)?;
+    };
+
+    if let Some(tab) = &app.config().general.tab.format {
+        hb.register_template_string(app::TEMPLATE_TAB, tab)?;
+    };
+
+    if let Some(tab) = &app.config().general.active_tab.format {
+        hb.register_template_string(app::TEMPLATE_ACTIVE_TAB, tab)?;
+    };
+
+    let users = UsersCache::new();
+
     let mut result = Ok(None);
 
+    // Directory size calculations get cancelled when the working directory changes.
+    let mut size_calculation_cancelled = Arc::new(AtomicBool::new(false));
+
+    // Only the running copy or move gets cancelled.
+    let mut transfer_cancelled = Arc::new(AtomicBool::new(false));
+
+    // Only the running recursive search gets cancelled.
+    let mut search_cancelled = Arc::new(AtomicBool::new(false));
+
     term::enable_raw_mode()?;
-    let mut stdout = get_tty()?;
+    let stdout = get_tty()?;
     // let mut stdout = stdout.lock();
-    execute!(stdout, term::EnterAlternateScreen)?;
     // let stdout = MouseTerminal::from(stdout);
-    let backend = CrosstermBackend::new(stdout);
+    let mut backend = CrosstermBackend::new(stdout);
+    execute!(backend, term::EnterAlternateScreen, EnableBracketedPaste)?;
+    let enable_mouse = app.config().general.enable_mouse.unwrap_or(false);
+    if enable_mouse {
+        execute!(backend, EnableMouseCapture)?;
+    };
     let mut terminal = Terminal::new(backend)?;
     terminal.hide_cursor()?;
 
+    let title_from_pwd = app.config().general.set_terminal_title.unwrap_or(false);
+    let mut title_saved = false;
+    let mut previewed = None;
+    if title_from_pwd {
+        set_title(terminal.backend_mut(), app.pwd(), &mut title_saved)?;
+    };
+
     // Threads
     auto_refresher::start_auto_refreshing(tx_msg_in.clone());
     pipe_reader::keep_reading(app.pipe().msg_in.clone(), tx_msg_in.clone());
-    event_reader::keep_reading(tx_msg_in.clone(), rx_event_reader);
+
+    // Only the pipe created here is removed at the end, not one that was
+    // there already, nor whatever was in the way.
+    let mut control_fifo = None;
+    if let Some(fifo) = app.config().general.control_fifo.clone() {
+        match fifo_reader::create(&fifo) {
+            Ok(created) => {
+                fifo_reader::keep_reading(fifo.clone(), tx_msg_in.clone());
+                if created {
+                    control_fifo = Some(fifo);
+                };
+            }
+            Err(e) => {
+                let msg = app::ExternalMsg::LogError(format!("control fifo: {}", e));
+                tx_msg_in.send(app::Task::new(app::MsgIn::External(msg), None))?;
+            }
+        };
+    };
+    event_reader::keep_reading(
+        tx_msg_in.clone(),
+        rx_event_reader,
+        app.config()
+            .general
+            .tick_interval
+            .map(Duration::from_millis),
+    );
     pwd_watcher::keep_watching(app.pwd(), tx_msg_in.clone(), rx_pwd_watcher)?;
+    debouncer::keep_debouncing(tx_msg_in.clone(), rx_debouncer);
+    worker::keep_spinning(
+        Duration::from_millis(app.config().general.spinner.interval.unwrap_or(100)),
+        tx_msg_in.clone(),
+    );
 
     'outer: for task in rx_msg_in {
         let last_app = app.clone();
@@ -152,43 +277,202 @@ pub fn run(mut app: app::App, focused_path: Option<String>) -> Result<Option<Str
                     break 'outer;
                 }
 
+                app::MsgOut::PrintFocusedPathAndQuit => {
+                    result = Ok(app.focused_node().map(|n| format!("{}\n", n.absolute_path)));
+                    break 'outer;
+                }
+
+                app::MsgOut::PrintSelectionAndQuit => {
+                    result = Ok(Some(app.selection_str_separated()));
+                    break 'outer;
+                }
+
                 app::MsgOut::PrintAppStateAndQuit => {
                     let out = serde_yaml::to_string(&app)?;
                     result = Ok(Some(out));
                     break 'outer;
                 }
 
+                app::MsgOut::CopyToClipboard(text) => {
+                    let msg = match clipboard::copy(&text) {
+                        Ok(()) => app::ExternalMsg::LogSuccess(format!("copied {}", text)),
+                        Err(e) => app::ExternalMsg::LogError(e.to_string()),
+                    };
+                    tx_msg_in.send(app::Task::new(app::MsgIn::External(msg), None))?;
+                }
+
+                app::MsgOut::SetTerminalTitle(title) => {
+                    set_title(terminal.backend_mut(), &title, &mut title_saved)?;
+                }
+
                 app::MsgOut::Debug(path) => {
                     fs::write(&path, serde_yaml::to_string(&app)?)?;
                 }
 
+                app::MsgOut::DumpEffectiveConfig(path) => {
+                    let msg = match fs::write(&path, serde_yaml::to_string(app.config())?) {
+                        Ok(()) => app::ExternalMsg::LogSuccess(format!(
+                            "wrote the effective config to {}",
+                            path
+                        )),
+                        Err(e) => app::ExternalMsg::LogError(format!("{}: {}", path, e)),
+                    };
+                    tx_msg_in.send(app::Task::new(app::MsgIn::External(msg), None))?;
+                }
+
+                app::MsgOut::DumpExplorerConfig(path) => {
+                    let view = app.explorer_config().view();
+                    let written = serde_yaml::to_string(&view)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|yaml| Ok(fs::write(&path, yaml)?));
+                    let msg = match written {
+                        Ok(()) => app::ExternalMsg::LogSuccess(format!(
+                            "wrote the explorer config to {}",
+                            path
+                        )),
+                        Err(e) => app::ExternalMsg::LogError(format!("{}: {}", path, e)),
+                    };
+                    tx_msg_in.send(app::Task::new(app::MsgIn::External(msg), None))?;
+                }
+
+                app::MsgOut::ExportListingJson(path) => {
+                    let written = serde_json::to_string_pretty(&app.listing())
+                        .map_err(anyhow::Error::from)
+                        .and_then(|json| Ok(fs::write(&path, json)?));
+                    let msg = match written {
+                        Ok(()) => app::ExternalMsg::LogSuccess(format!(
+                            "exported the listing to {}",
+                            path
+                        )),
+                        Err(e) => app::ExternalMsg::LogError(format!("{}: {}", path, e)),
+                    };
+                    tx_msg_in.send(app::Task::new(app::MsgIn::External(msg), None))?;
+                }
+
                 app::MsgOut::ClearScreen => {
                     terminal.clear()?;
                 }
 
                 app::MsgOut::Explore => {
+                    previewed = None;
                     explorer::explore(
-                        app.explorer_config().clone(),
+                        app.explorer_config()
+                            .clone()
+                            .with_selection(app.selection()),
                         app.pwd().clone(),
-                        app.focused_node().map(|n| n.relative_path.clone()),
+                        app.focused_node().map(|n| n.absolute_path.clone()),
                         tx_msg_in.clone(),
                     );
+                    if app.config().general.git_status.unwrap_or_default() {
+                        git_status::compute(app.pwd().clone(), tx_msg_in.clone());
+                    };
                 }
 
                 app::MsgOut::Refresh => {
                     app = app.refresh_selection()?;
                     if app.pwd() != last_app.pwd() {
-                        tx_pwd_watcher.send(app.pwd().clone())?;
+                        if title_from_pwd {
+                            set_title(terminal.backend_mut(), app.pwd(), &mut title_saved)?;
+                        };
+
+                        size_calculation_cancelled.store(true, Ordering::Relaxed);
+                        size_calculation_cancelled = Arc::new(AtomicBool::new(false));
+
+                        tx_pwd_watcher.send(archive::host_dir(app.pwd()))?;
                         explorer::explore(
-                            app.explorer_config().clone(),
+                            app.explorer_config()
+                                .clone()
+                                .with_selection(app.selection()),
                             app.pwd().clone(),
-                            app.focused_node().map(|n| n.relative_path.clone()),
+                            app.focused_node().map(|n| n.absolute_path.clone()),
                             tx_msg_in.clone(),
                         );
+                        if app.config().general.git_status.unwrap_or_default() {
+                            git_status::compute(app.pwd().clone(), tx_msg_in.clone());
+                        };
+                    };
+
+                    let preview_path = app.preview_path();
+                    if preview_path != previewed {
+                        if let Some(path) = preview_path.clone() {
+                            explorer::preview(
+                                app.explorer_config().clone(),
+                                path,
+                                tx_msg_in.clone(),
+                            );
+                        };
+                        previewed = preview_path;
+                    };
+
+                    if app.size_unit_base() != size_unit_base {
+                        size_unit_base = app.size_unit_base();
+                        register_humansize(&mut hb, size_unit_base);
                     };
 
                     // UI
-                    terminal.draw(|f| ui::draw(f, &app, &hb))?;
+                    terminal.draw(|f| ui::draw(f, &app, &hb, &users))?;
+                    let table_area = ui::table_area(terminal.size()?, &app);
+                    app = app.with_table_area(table_area);
+                }
+
+                app::MsgOut::ComputeDirectorySize(path) => {
+                    size_calculator::calculate(
+                        path,
+                        size_calculation_cancelled.clone(),
+                        tx_msg_in.clone(),
+                    );
+                }
+
+                app::MsgOut::ComputeHash(path, algorithm) => {
+                    hasher::hash(path, algorithm, tx_msg_in.clone());
+                }
+
+                app::MsgOut::Extract(paths, dest) => {
+                    archive::extract(paths, dest, tx_msg_in.clone());
+                }
+
+                app::MsgOut::DiffFiles(a, b, command) => {
+                    differ::compare(a, b, command, tx_msg_in.clone());
+                }
+
+                app::MsgOut::DebounceFocusChange(change, delay) => {
+                    let msg = app::MsgIn::Internal(app::InternalMsg::RunFocusChangeHook(change));
+                    tx_debouncer.send((
+                        debouncer::Wait::FocusChange,
+                        delay,
+                        app::Task::new(msg, None),
+                    ))?;
+                }
+
+                app::MsgOut::DebounceKeySequence(sequence, delay) => {
+                    let msg = app::MsgIn::Internal(app::InternalMsg::ResolveKeySequence(sequence));
+                    tx_debouncer.send((
+                        debouncer::Wait::KeySequence,
+                        delay,
+                        app::Task::new(msg, None),
+                    ))?;
+                }
+
+                app::MsgOut::Transfer(transfer) => {
+                    file_transfer::transfer(
+                        transfer,
+                        transfer_cancelled.clone(),
+                        tx_msg_in.clone(),
+                    );
+                }
+
+                app::MsgOut::CancelTransfer => {
+                    transfer_cancelled.store(true, Ordering::Relaxed);
+                    transfer_cancelled = Arc::new(AtomicBool::new(false));
+                }
+
+                app::MsgOut::SearchRecursively(search) => {
+                    recursive_search::search(search, search_cancelled.clone(), tx_msg_in.clone());
+                }
+
+                app::MsgOut::CancelSearch => {
+                    search_cancelled.store(true, Ordering::Relaxed);
+                    search_cancelled = Arc::new(AtomicBool::new(false));
                 }
 
                 app::MsgOut::CallSilently(cmd) => {
@@ -212,11 +496,58 @@ pub fn run(mut app: app::App, focused_path: Option<String>) -> Result<Option<Str
                     tx_event_reader.send(false)?;
                 }
 
+                app::MsgOut::CaptureCommandOutput(cmd, path) => {
+                    output_capturer::capture(cmd, path, tx_msg_in.clone());
+                }
+
+                app::MsgOut::ForEachSelection(cmd, paths) => {
+                    batch_runner::run_on_each(cmd, paths, tx_msg_in.clone());
+                }
+
+                app::MsgOut::OpenShell(cmd) => {
+                    tx_event_reader.send(true)?;
+
+                    terminal.clear()?;
+                    terminal.set_cursor(0, 0)?;
+                    execute!(
+                        terminal.backend_mut(),
+                        DisableBracketedPaste,
+                        term::LeaveAlternateScreen
+                    )?;
+                    if enable_mouse {
+                        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+                    };
+                    term::disable_raw_mode()?;
+                    terminal.show_cursor()?;
+
+                    if let Err(e) = call(&app, cmd, false) {
+                        let msg = app::MsgIn::External(app::ExternalMsg::LogError(e.to_string()));
+                        tx_msg_in.send(app::Task::new(msg, None))?;
+                    };
+
+                    term::enable_raw_mode()?;
+                    execute!(
+                        terminal.backend_mut(),
+                        term::EnterAlternateScreen,
+                        EnableBracketedPaste
+                    )?;
+                    if enable_mouse {
+                        execute!(terminal.backend_mut(), EnableMouseCapture)?;
+                    };
+                    terminal.clear()?;
+                    terminal.hide_cursor()?;
+                    tx_event_reader.send(false)?;
+                }
+
                 app::MsgOut::Call(cmd) => {
                     tx_event_reader.send(true)?;
 
                     terminal.clear()?;
                     terminal.set_cursor(0, 0)?;
+                    execute!(terminal.backend_mut(), DisableBracketedPaste)?;
+                    if enable_mouse {
+                        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+                    };
                     term::disable_raw_mode()?;
                     terminal.show_cursor()?;
 
@@ -237,6 +568,10 @@ pub fn run(mut app: app::App, focused_path: Option<String>) -> Result<Option<Str
 
                     terminal.clear()?;
                     term::enable_raw_mode()?;
+                    execute!(terminal.backend_mut(), EnableBracketedPaste)?;
+                    if enable_mouse {
+                        execute!(terminal.backend_mut(), EnableMouseCapture)?;
+                    };
                     terminal.hide_cursor()?;
                     tx_event_reader.send(false)?;
                 }
@@ -263,11 +598,10 @@ pub fn run(mut app: app::App, focused_path: Option<String>) -> Result<Option<Str
             fs::write(&app.pipe().directory_nodes_out, app.directory_nodes_str())?;
         };
 
-        if app.logs().len() != last_app.logs().len() {
+        if app.logs_total() != last_app.logs_total() {
             let new_logs = app
-                .logs()
+                .logs_since(last_app.logs_total())
                 .iter()
-                .skip(last_app.logs().len())
                 .map(|l| format!("{}\n", l))
                 .collect::<Vec<String>>()
                 .join("");
@@ -279,18 +613,78 @@ pub fn run(mut app: app::App, focused_path: Option<String>) -> Result<Option<Str
             file.write_all(new_logs.as_bytes())?;
         };
 
+        let mut unsaved = vec![];
+
+        if let Some(file) = &app.state_files().recent_directories {
+            if app.recent_directories() != last_app.recent_directories() {
+                let content = app.recent_directories().to_file_content();
+                if let Err(e) = save(file, &content) {
+                    unsaved.push(format!("Cannot save the recent directories: {}", e));
+                };
+            };
+        };
+
+        if let Some(file) = &app.state_files().directory_scores {
+            if app.directory_scores() != last_app.directory_scores() {
+                let content = app.directory_scores().to_file_content();
+                if let Err(e) = save(file, &content) {
+                    unsaved.push(format!("Cannot save the directory scores: {}", e));
+                };
+            };
+        };
+
+        if let Some(file) = &app.state_files().favorites {
+            if app.favorites() != last_app.favorites() {
+                let content = app.favorites().to_file_content();
+                if let Err(e) = save(file, &content) {
+                    unsaved.push(format!("Cannot save the favorites: {}", e));
+                };
+            };
+        };
+
+        if let Some(file) = &app.state_files().views {
+            if app.views() != last_app.views() {
+                let content = app.views().to_file_content();
+                if let Err(e) = save(file, &content) {
+                    unsaved.push(format!("Cannot save the views: {}", e));
+                };
+            };
+        };
+
+        for err in unsaved {
+            let msg = app::MsgIn::External(app::ExternalMsg::LogError(err));
+            tx_msg_in.send(app::Task::new(msg, None))?;
+        }
+
         if app.result() != last_app.result() {
             fs::write(&app.pipe().result_out, app.result_str())?;
         };
     }
 
+    if title_saved {
+        write!(terminal.backend_mut(), "{}", POP_TITLE)?;
+    };
+
     terminal.clear()?;
     terminal.set_cursor(0, 0)?;
-    execute!(terminal.backend_mut(), term::LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        term::LeaveAlternateScreen,
+        DisableBracketedPaste
+    )?;
+    if enable_mouse {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    };
     term::disable_raw_mode()?;
     terminal.show_cursor()?;
 
     fs::remove_dir_all(app.session_path())?;
+    if let Some(fifo) = control_fifo {
+        // The terminal is restored by now, so the error can go to stderr.
+        if let Err(e) = fs::remove_file(&fifo) {
+            eprintln!("error: {}: {}", fifo, e);
+        };
+    };
 
     result
 }
@@ -0,0 +1,42 @@
+use crate::app::{DirectorySize, InternalMsg, MsgIn, Task};
+use crate::worker;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+/// Sum the sizes of the regular files under the given path. Symlinks are not
+/// followed to avoid counting the same files twice. Returns `None` if the walk
+/// was cancelled.
+fn walk(path: &Path, cancelled: &AtomicBool) -> Option<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path).into_iter().flatten().flatten() {
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        if let Ok(meta) = entry.path().symlink_metadata() {
+            if meta.is_dir() {
+                size += walk(&entry.path(), cancelled)?;
+            } else if meta.is_file() {
+                size += meta.len();
+            }
+        }
+    }
+    Some(size)
+}
+
+pub fn calculate(path: String, cancelled: Arc<AtomicBool>, tx: Sender<Task>) {
+    worker::spawn(move || {
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if let Some(size) = walk(Path::new(&path), &cancelled) {
+            let dir_size = DirectorySize { modified, size };
+            tx.send(Task::new(
+                MsgIn::Internal(InternalMsg::SetDirectorySize(path, dir_size)),
+                None,
+            ))
+            .unwrap();
+        }
+    });
+}
@@ -1,69 +1,26 @@
 use crate::app;
 use crate::app::HelpMenuLine;
 use crate::app::{Node, ResolvedNode};
+use crate::config::LogsConfig;
+use crate::config::SizeUnitBase;
+use chrono::{DateTime, Local};
 use handlebars::Handlebars;
-use lazy_static::lazy_static;
+use humansize::{file_size_opts as options, FileSize};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::env;
+use std::path::PathBuf;
+use std::time::SystemTime;
 use tui::backend::Backend;
 use tui::layout::Rect;
 use tui::layout::{Constraint as TuiConstraint, Direction, Layout};
-use tui::style::{Color, Modifier, Style as TuiStyle};
+use tui::style::{Modifier, Style as TuiStyle};
 use tui::text::{Span, Spans};
-use tui::widgets::{Block, Borders, Cell, List, ListItem, Paragraph, Row, Table};
+use tui::widgets::{
+    Block, Borders, Cell, Clear, Gauge, List, ListItem, ListState, Paragraph, Row, Table, Wrap,
+};
 use tui::Frame;
-
-lazy_static! {
-    pub static ref NO_COLOR: bool = env::var("NO_COLOR").ok().map(|_| true).unwrap_or(false);
-    pub static ref DEFAULT_STYLE: TuiStyle = TuiStyle::default();
-}
-
-#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
-#[serde(deny_unknown_fields)]
-pub struct Style {
-    pub fg: Option<Color>,
-    pub bg: Option<Color>,
-    pub add_modifier: Option<Modifier>,
-    pub sub_modifier: Option<Modifier>,
-}
-
-impl Style {
-    pub fn extend(mut self, other: Self) -> Self {
-        self.fg = other.fg.or(self.fg);
-        self.bg = other.bg.or(self.bg);
-        self.add_modifier = other.add_modifier.or(self.add_modifier);
-        self.sub_modifier = other.sub_modifier.or(self.sub_modifier);
-        self
-    }
-}
-
-impl From<TuiStyle> for Style {
-    fn from(s: TuiStyle) -> Self {
-        Self {
-            fg: s.fg,
-            bg: s.bg,
-            add_modifier: Some(s.add_modifier),
-            sub_modifier: Some(s.sub_modifier),
-        }
-    }
-}
-
-impl Into<TuiStyle> for Style {
-    fn into(self) -> TuiStyle {
-        if *NO_COLOR {
-            *DEFAULT_STYLE
-        } else {
-            TuiStyle {
-                fg: self.fg,
-                bg: self.bg,
-                add_modifier: self.add_modifier.unwrap_or_else(Modifier::empty),
-                sub_modifier: self.sub_modifier.unwrap_or_else(Modifier::empty),
-            }
-        }
-    }
-}
+use users::{Groups, Users, UsersCache};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -91,6 +48,32 @@ impl From<ResolvedNode> for ResolvedNodeUiMetadata {
     }
 }
 
+/// The permissions as shown by `ls -l`, e.g. `rwxr-xr-x`.
+fn permissions_str(mode: u32) -> String {
+    let class = |shift: u32, special: u32, set: char, unset: char| {
+        let bits = mode >> shift;
+        let x = match (bits & 1 != 0, mode & special != 0) {
+            (true, true) => set,
+            (false, true) => unset,
+            (true, false) => 'x',
+            (false, false) => '-',
+        };
+        format!(
+            "{}{}{}",
+            if bits & 4 != 0 { 'r' } else { '-' },
+            if bits & 2 != 0 { 'w' } else { '-' },
+            x
+        )
+    };
+
+    format!(
+        "{}{}{}",
+        class(6, 0o4000, 's', 'S'),
+        class(3, 0o2000, 's', 'S'),
+        class(0, 0o1000, 't', 'T')
+    )
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct NodeUiMetadata {
@@ -104,22 +87,35 @@ struct NodeUiMetadata {
     pub is_dir: bool,
     pub is_file: bool,
     pub is_readonly: bool,
+    pub permissions: String,
+    pub octal_permissions: String,
+    pub owner: String,
+    pub group: String,
     pub mime_essence: String,
     pub size: u64,
+    pub modified: Option<String>,
+    pub created: Option<String>,
+    pub accessed: Option<String>,
     pub canonical: Option<ResolvedNodeUiMetadata>,
     pub symlink: Option<ResolvedNodeUiMetadata>,
 
     // Extra
     pub index: usize,
     pub relative_index: usize,
+    pub hybrid_index: String,
     pub is_before_focus: bool,
     pub is_after_focus: bool,
     pub tree: String,
+    pub icon: String,
     pub prefix: String,
     pub suffix: String,
     pub is_selected: bool,
     pub is_focused: bool,
     pub total: usize,
+    pub computed_size: Option<u64>,
+    pub hash: Option<String>,
+    pub git_status: Option<String>,
+    pub registers: String,
     pub meta: HashMap<String, String>,
 }
 
@@ -128,16 +124,30 @@ impl NodeUiMetadata {
         node: &Node,
         index: usize,
         relative_index: usize,
+        hybrid_index: String,
         is_before_focus: bool,
         is_after_focus: bool,
         tree: String,
+        icon: String,
         prefix: String,
         suffix: String,
         is_selected: bool,
         is_focused: bool,
         total: usize,
+        computed_size: Option<u64>,
+        hash: Option<String>,
+        git_status: Option<String>,
+        registers: String,
+        datetime_format: &str,
         meta: HashMap<String, String>,
+        users: &UsersCache,
     ) -> Self {
+        let format_time = |t: SystemTime| {
+            DateTime::<Local>::from(t)
+                .format(datetime_format)
+                .to_string()
+        };
+
         Self {
             parent: node.parent.clone(),
             relative_path: node.relative_path.clone(),
@@ -148,29 +158,223 @@ impl NodeUiMetadata {
             is_dir: node.is_dir,
             is_file: node.is_file,
             is_readonly: node.is_readonly,
+            permissions: permissions_str(node.permissions),
+            octal_permissions: format!("{:04o}", node.permissions),
+            owner: users
+                .get_user_by_uid(node.uid)
+                .map(|u| u.name().to_string_lossy().to_string())
+                .unwrap_or_else(|| node.uid.to_string()),
+            group: users
+                .get_group_by_gid(node.gid)
+                .map(|g| g.name().to_string_lossy().to_string())
+                .unwrap_or_else(|| node.gid.to_string()),
             mime_essence: node.mime_essence.clone(),
             size: node.size,
+            modified: node.modified.map(format_time),
+            created: node.created.map(format_time),
+            accessed: node.accessed.map(format_time),
             canonical: node.canonical.to_owned().map(|s| s.into()),
             symlink: node.symlink.to_owned().map(|s| s.into()),
             index,
             relative_index,
+            hybrid_index,
             is_before_focus,
             is_after_focus,
             tree,
+            icon,
             prefix,
             suffix,
             is_selected,
             is_focused,
             total,
+            computed_size,
+            hash,
+            git_status,
+            registers,
             meta,
         }
     }
 }
 
-fn draw_table<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, hb: &Handlebars) {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TableTitleUiMetadata {
+    pub pwd: String,
+    pub total: usize,
+    pub visible: usize,
+    pub hidden: usize,
+    pub selected: usize,
+}
+
+impl TableTitleUiMetadata {
+    fn new(app: &app::App) -> Self {
+        let (total, visible, hidden) = app
+            .directory_buffer()
+            .map(|d| (d.unfiltered_total, d.total, d.hidden()))
+            .unwrap_or_default();
+
+        Self {
+            pwd: app.pwd().clone(),
+            total,
+            visible,
+            hidden,
+            selected: app.selection().len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TabUiMetadata {
+    pub index: usize,
+    pub number: usize,
+    pub pwd: String,
+    pub name: String,
+    pub selected: usize,
+    pub is_active: bool,
+}
+
+impl TabUiMetadata {
+    fn new(index: usize, pwd: &str, selected: usize, is_active: bool) -> Self {
+        let name = PathBuf::from(pwd)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| pwd.into());
+
+        Self {
+            index,
+            number: index + 1,
+            pwd: pwd.into(),
+            name,
+            selected,
+            is_active,
+        }
+    }
+}
+
+/// A bordered block with the title, or a bare one in the compact mode.
+fn block<'a, T: Into<Spans<'a>>>(app: &app::App, title: T) -> Block<'a> {
+    if app.config().general.compact.unwrap_or_default() {
+        Block::default()
+    } else {
+        Block::default().borders(Borders::ALL).title(title)
+    }
+}
+
+/// The lines, or the columns, taken by the borders of a block.
+fn borders_size(app: &app::App) -> u16 {
+    if app.config().general.compact.unwrap_or_default() {
+        0
+    } else {
+        2
+    }
+}
+
+fn draw_tabs<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, hb: &Handlebars) {
+    let config = app.config().to_owned();
+    let spans = app
+        .tabs()
+        .iter()
+        .enumerate()
+        .map(|(index, tab)| {
+            if index == app.tab_index() {
+                let meta = TabUiMetadata::new(index, app.pwd(), app.selection().len(), true);
+                let tab = hb
+                    .render(app::TEMPLATE_ACTIVE_TAB, &meta)
+                    .unwrap_or_else(|_| format!(" {} ", meta.name));
+                Span::styled(tab, config.general.active_tab.style.into())
+            } else {
+                let meta = TabUiMetadata::new(index, &tab.pwd, tab.selection.len(), false);
+                let tab = hb
+                    .render(app::TEMPLATE_TAB, &meta)
+                    .unwrap_or_else(|_| format!(" {} ", meta.name));
+                Span::styled(tab, config.general.tab.style.into())
+            }
+        })
+        .collect::<Vec<Span>>();
+
+    f.render_widget(Paragraph::new(Spans::from(spans)), rect);
+}
+
+/// The breadcrumb labels fitting the width, dropping the components after
+/// the root for a `…` until the rest fits. The root and the last component
+/// are always kept.
+pub fn fit_breadcrumb(names: &[String], separator: &str, width: usize) -> Vec<String> {
+    let len = |s: &str| s.chars().count();
+    let sep = len(separator);
+    let total = |labels: &[String]| {
+        labels.iter().map(|l| len(l)).sum::<usize>() + sep * labels.len().saturating_sub(1)
+    };
+
+    let mut labels = names.to_vec();
+    if names.len() < 3 || total(&labels) <= width {
+        return labels;
+    };
+
+    for skip in 1..names.len() - 1 {
+        labels = vec![names[0].clone(), "…".into()];
+        labels.extend(names[skip + 1..].iter().cloned());
+        if total(&labels) <= width {
+            break;
+        };
+    }
+    labels
+}
+
+fn draw_breadcrumb<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
+    let config = &app.config().general.breadcrumb;
+    let separator = config.format.clone().unwrap_or_default();
+    let names = app::breadcrumb(app.pwd())
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<String>>();
+
+    let mut spans = vec![];
+    for (i, label) in fit_breadcrumb(&names, &separator, rect.width.into())
+        .into_iter()
+        .enumerate()
+    {
+        if i > 0 {
+            spans.push(Span::raw(separator.clone()));
+        };
+        spans.push(Span::styled(label, config.style.into()));
+    }
+
+    f.render_widget(Paragraph::new(Spans::from(spans)), rect);
+}
+
+/// The depth of the node in the tree view, and whether it's the last one
+/// among its siblings.
+fn tree_position(nodes: &[app::Node], index: usize) -> (usize, bool) {
+    let depth = nodes.get(index).map(|n| n.depth).unwrap_or_default();
+    let is_last = nodes
+        .iter()
+        .skip(index + 1)
+        .map(|n| n.depth)
+        .find(|d| *d <= depth)
+        .map(|d| d < depth)
+        .unwrap_or(true);
+    (depth, is_last)
+}
+
+fn draw_table<B: Backend>(
+    f: &mut Frame<B>,
+    rect: Rect,
+    app: &app::App,
+    hb: &Handlebars,
+    users: &UsersCache,
+) {
     let config = app.config().to_owned();
     let header_height = config.general.table.header.height.unwrap_or(1);
-    let height: usize = (rect.height.max(header_height + 2) - (header_height + 2)).into();
+    let height: usize = rect
+        .height
+        .saturating_sub(header_height + borders_size(app))
+        .into();
+    let datetime_format = config
+        .general
+        .datetime_format
+        .clone()
+        .unwrap_or_else(|| app::DEFAULT_DATETIME_FORMAT.into());
 
     let rows = app
         .directory_buffer()
@@ -178,7 +382,7 @@ fn draw_table<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, hb: &Han
             dir.nodes
                 .iter()
                 .enumerate()
-                .skip(height * (dir.focus / height.max(1)))
+                .skip(dir.first_visible(height))
                 .take(height)
                 .map(|(index, node)| {
                     let is_focused = dir.focus == index;
@@ -187,7 +391,7 @@ fn draw_table<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, hb: &Han
                     let is_selected = app.selection().contains(node);
 
                     let is_first = index == 0;
-                    let is_last = index == dir.total.max(1) - 1;
+                    let (depth, is_last) = tree_position(&dir.nodes, index);
 
                     let tree = config
                         .general
@@ -203,23 +407,10 @@ fn draw_table<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, hb: &Han
                                 t.1.format
                             }
                         })
-                        .unwrap_or_default();
-
-                    let node_type = config
-                        .node_types
-                        .special
-                        .get(&node.relative_path)
-                        .or_else(|| config.node_types.extension.get(&node.extension))
-                        .or_else(|| config.node_types.mime_essence.get(&node.mime_essence))
-                        .unwrap_or_else(|| {
-                            if node.is_symlink {
-                                &config.node_types.symlink
-                            } else if node.is_dir {
-                                &config.node_types.directory
-                            } else {
-                                &config.node_types.file
-                            }
-                        });
+                        .unwrap_or_default()
+                        .map(|t| format!("{}{}", "  ".repeat(depth), t));
+
+                    let node_type = config.node_types.get(node);
 
                     let (relative_index, is_before_focus, is_after_focus) =
                         match dir.focus.cmp(&index) {
@@ -233,6 +424,12 @@ fn draw_table<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, hb: &Han
                         (ui.prefix, ui.suffix, ui.style.extend(node_type.style))
                     };
 
+                    let git_status = app.git_status(node).map(|s| {
+                        let ui = config.general.git_status_ui.get(s);
+                        style = style.extend(ui.style);
+                        ui.format.clone().unwrap_or_default()
+                    });
+
                     if is_selected {
                         let ui = config.general.selection_ui.clone();
                         prefix = ui.prefix.or(prefix);
@@ -248,18 +445,30 @@ fn draw_table<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, hb: &Han
                     };
 
                     let meta = NodeUiMetadata::new(
-                        &node,
+                        node,
                         index,
                         relative_index,
+                        hybrid_index(index, dir.focus),
                         is_before_focus,
                         is_after_focus,
                         tree.unwrap_or_default(),
+                        config.node_types.icon(node).cloned().unwrap_or_default(),
                         prefix.unwrap_or_default(),
                         suffix.unwrap_or_default(),
                         is_selected,
                         is_focused,
                         dir.total,
+                        app.directory_sizes()
+                            .get(&node.absolute_path)
+                            .map(|s| s.size),
+                        app.file_hashes()
+                            .get(&node.absolute_path)
+                            .map(|h| h.digest.clone()),
+                        git_status,
+                        app.registers_of(node),
+                        &datetime_format,
                         node_type.meta.clone(),
+                        users,
                     );
 
                     let cols = hb
@@ -286,31 +495,59 @@ fn draw_table<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, hb: &Han
         .map(|c| c.into())
         .collect();
 
+    let title = TableTitleUiMetadata::new(app);
+    let title = hb
+        .render(app::TEMPLATE_TABLE_TITLE, &title)
+        .unwrap_or_else(|_| format!(" {} ({}) ", title.pwd, title.visible));
+    let title = Span::styled(title, config.general.table.title.style.into());
+
     let table = Table::new(rows)
         .widths(&table_constraints)
         .style(config.general.table.style.into())
         .highlight_style(config.general.focus_ui.style.into())
         .column_spacing(config.general.table.col_spacing.unwrap_or_default())
-        .block(Block::default().borders(Borders::ALL).title(format!(
-            " {} ({}) ",
-            app.pwd(),
-            app.directory_buffer().map(|d| d.total).unwrap_or_default()
-        )));
+        .block(block(app, title));
+
+    // Mark the columns sorting the listing with their direction.
+    let directions = &config.general.sort_and_filter_ui.sort_direction_identifiers;
+    let col_sorters = config.general.table.col_sorters.unwrap_or_default();
+    let header_cols = config
+        .general
+        .table
+        .header
+        .cols
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let mut spans = vec![Span::raw(c.format.to_owned().unwrap_or_default())];
+            let sorter = col_sorters.get(i).cloned().flatten().and_then(|sorter| {
+                app.explorer_config()
+                    .sorters()
+                    .iter()
+                    .find(|s| s.sorter == sorter)
+            });
+
+            if let Some(s) = sorter {
+                let direction = if s.reverse {
+                    &directions.reverse
+                } else {
+                    &directions.forward
+                };
+                spans.push(Span::raw(" "));
+                spans.push(Span::styled(
+                    direction.format.to_owned().unwrap_or_default(),
+                    direction.style.into(),
+                ));
+            };
+            Cell::from(Spans::from(spans))
+        })
+        .collect::<Vec<Cell>>();
 
     let table = table.clone().header(
-        Row::new(
-            config
-                .general
-                .table
-                .header
-                .cols
-                .unwrap_or_default()
-                .iter()
-                .map(|c| Cell::from(c.format.to_owned().unwrap_or_default()))
-                .collect::<Vec<Cell>>(),
-        )
-        .height(header_height)
-        .style(config.general.table.header.style.into()),
+        Row::new(header_cols)
+            .height(header_height)
+            .style(config.general.table.header.style.into()),
     );
 
     f.render_widget(table, rect);
@@ -321,7 +558,7 @@ fn draw_selection<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &
         .selection()
         .iter()
         .rev()
-        .take((rect.height.max(2) - 2).into())
+        .take(rect.height.saturating_sub(borders_size(app)).into())
         .rev()
         .map(|n| n.absolute_path.clone())
         .map(ListItem::new)
@@ -330,22 +567,168 @@ fn draw_selection<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &
     let selection_count = selection.len();
 
     // Selected items
-    let selection_list = List::new(selection).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(format!(" Selection ({}) ", selection_count)),
-    );
+    let selection_list =
+        List::new(selection).block(block(app, format!(" Selection ({}) ", selection_count)));
 
     f.render_widget(selection_list, rect);
 }
 
-fn draw_help_menu<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
-    let help_menu_rows = app
-        .mode()
+fn draw_preview<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
+    let (items, title) = match app.preview() {
+        Some(dir) => {
+            let items = dir
+                .nodes
+                .iter()
+                .take(rect.height.saturating_sub(borders_size(app)).into())
+                .map(|n| {
+                    if n.is_dir {
+                        format!("{}/", n.relative_path)
+                    } else {
+                        n.relative_path.clone()
+                    }
+                })
+                .map(ListItem::new)
+                .collect::<Vec<ListItem>>();
+            (items, format!(" Preview ({}) ", dir.total))
+        }
+        None => (vec![], " Preview ".into()),
+    };
+
+    let list = List::new(items).block(block(app, title));
+    f.render_widget(list, rect);
+}
+
+fn draw_command_output<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
+    if let Some(output) = app.command_output() {
+        let title = format!(
+            " {}{}{} ",
+            output.command,
+            if output.success { "" } else { " (failed)" },
+            if output.truncated { " (truncated)" } else { "" },
+        );
+
+        let text = Paragraph::new(output.text.clone())
+            .block(block(app, title))
+            .wrap(Wrap { trim: false });
+
+        f.render_widget(Clear, rect);
+        f.render_widget(text, rect);
+    }
+}
+
+fn draw_picker<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
+    if let Some(picker) = app.picker() {
+        let config = app.config().to_owned();
+        let matches = picker.matches();
+        let items = matches
+            .iter()
+            .map(|i| ListItem::new(i.label.clone()))
+            .collect::<Vec<ListItem>>();
+
+        let mut state = ListState::default();
+        state.select(Some(picker.focus));
+
+        let list = List::new(items)
+            .block(block(
+                app,
+                format!(" {} ({}) ", picker.title, matches.len()),
+            ))
+            .highlight_style(config.general.focus_ui.style.into());
+
+        f.render_widget(Clear, rect);
+        f.render_stateful_widget(list, rect, &mut state);
+    }
+}
+
+pub fn human_size(size: u64, base: SizeUnitBase) -> String {
+    match base {
+        SizeUnitBase::Si => {
+            let size = size.file_size(options::DECIMAL).unwrap_or_default();
+            // humansize writes the SI kilo as `KB`.
+            match size.strip_suffix(" KB") {
+                Some(n) => format!("{} kB", n),
+                None => size,
+            }
+        }
+        SizeUnitBase::Binary => size.file_size(options::BINARY).unwrap_or_default(),
+    }
+}
+
+/// Like vim's hybrid line numbers: the index of the focused node, and the
+/// signed distance from the focus for the others.
+pub fn hybrid_index(index: usize, focus: usize) -> String {
+    match focus.cmp(&index) {
+        Ordering::Greater => format!("-{}", focus - index),
+        Ordering::Less => format!("+{}", index - focus),
+        Ordering::Equal => index.to_string(),
+    }
+}
+
+/// Style the text between a pair of `*` bold, and between a pair of `_`
+/// italic. A marker without a pair, or escaped with `\`, is kept as is.
+pub fn markup_spans(text: &str) -> Spans<'static> {
+    // The markers, with the escaped ones turned into plain characters.
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' | '_' => tokens.push((c, true)),
+            '\\' if matches!(chars.peek(), Some('*') | Some('_') | Some('\\')) => {
+                tokens.extend(chars.next().map(|c| (c, false)))
+            }
+            _ => tokens.push((c, false)),
+        }
+    }
+
+    let mut spans = vec![];
+    let mut current = String::new();
+    let mut active = Modifier::empty();
+    for (i, (c, is_marker)) in tokens.iter().copied().enumerate() {
+        let modifier = match c {
+            '*' if is_marker => Modifier::BOLD,
+            '_' if is_marker => Modifier::ITALIC,
+            _ => {
+                current.push(c);
+                continue;
+            }
+        };
+
+        if active.contains(modifier) || tokens[i + 1..].contains(&(c, true)) {
+            let style = TuiStyle::default().add_modifier(active);
+            spans.push(Span::styled(std::mem::take(&mut current), style));
+            active.toggle(modifier);
+        } else {
+            current.push(c);
+        };
+    }
+
+    spans.push(Span::styled(
+        current,
+        TuiStyle::default().add_modifier(active),
+    ));
+    Spans::from(
+        spans
+            .into_iter()
+            .filter(|s| !s.content.is_empty())
+            .collect::<Vec<Span>>(),
+    )
+}
+
+fn help_menu_rows(app: &app::App) -> Vec<Row<'_>> {
+    let markup = app.config().general.help_markup.unwrap_or_default();
+    let cell = |text: String| {
+        if markup {
+            Cell::from(markup_spans(&text))
+        } else {
+            Cell::from(text)
+        }
+    };
+
+    app.mode()
         .help_menu()
         .into_iter()
         .map(|l| match l {
-            HelpMenuLine::Paragraph(p) => Row::new([Cell::from(p)].to_vec()),
+            HelpMenuLine::Paragraph(p) => Row::new([cell(p)].to_vec()),
             HelpMenuLine::KeyMap(k, h) => {
                 let remaps = app
                     .mode()
@@ -356,57 +739,155 @@ fn draw_help_menu<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &
                     .map(|(f, _)| f.clone())
                     .collect::<Vec<String>>()
                     .join("|");
-                Row::new([Cell::from(k), Cell::from(remaps), Cell::from(h)].to_vec())
+                Row::new([Cell::from(k), Cell::from(remaps), cell(h)].to_vec())
             }
         })
-        .collect::<Vec<Row>>();
+        .collect()
+}
 
+fn help_menu<'a>(app: &app::App, rows: Vec<Row<'a>>) -> Table<'a> {
     let read_only_indicator = if app.config().general.read_only.unwrap_or_default() {
         "(r)"
     } else {
         ""
     };
 
-    let help_menu = Table::new(help_menu_rows)
-        .block(Block::default().borders(Borders::ALL).title(format!(
-            " Help [{}{}] ",
-            &app.mode().name,
-            read_only_indicator
-        )))
+    Table::new(rows)
+        .block(block(
+            app,
+            format!(" Help [{}{}] ", &app.mode().name, read_only_indicator),
+        ))
         .widths(&[
             TuiConstraint::Percentage(20),
             TuiConstraint::Percentage(20),
             TuiConstraint::Percentage(60),
-        ]);
-    f.render_widget(help_menu, rect);
+        ])
+}
+
+fn draw_help_menu<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
+    f.render_widget(help_menu(app, help_menu_rows(app)), rect);
+}
+
+/// The help menu over the given area, scrolled no further than needed to
+/// show its last line.
+fn draw_full_help<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
+    if let Some(scroll) = app.full_help() {
+        let rows = help_menu_rows(app);
+        let visible = usize::from(rect.height.saturating_sub(borders_size(app)));
+        let skip = scroll.min(rows.len().saturating_sub(visible));
+        let rows = rows.into_iter().skip(skip).collect();
+
+        f.render_widget(Clear, rect);
+        f.render_widget(help_menu(app, rows), rect);
+    }
 }
 
 fn draw_input_buffer<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
-    let input_buf = Paragraph::new(Spans::from(vec![
-        Span::styled(
-            app.config()
-                .general
-                .prompt
-                .format
-                .clone()
-                .unwrap_or_default(),
-            app.config().general.prompt.style.into(),
-        ),
-        Span::raw(app.input_buffer().unwrap_or_else(|| "".into())),
-        Span::styled(
-            app.config()
-                .general
-                .cursor
-                .format
-                .clone()
-                .unwrap_or_default(),
-            app.config().general.cursor.style.into(),
-        ),
-    ]))
-    .block(Block::default().borders(Borders::ALL).title(" Input "));
+    let input = app.input_buffer().unwrap_or_default();
+    let cursor = Span::styled(
+        app.config()
+            .general
+            .cursor
+            .format
+            .clone()
+            .unwrap_or_default(),
+        app.config().general.cursor.style.into(),
+    );
+
+    let prompt = Span::styled(
+        app.config()
+            .general
+            .prompt
+            .format
+            .clone()
+            .unwrap_or_default(),
+        app.config().general.prompt.style.into(),
+    );
+
+    let spans = if let Some((start, end)) = app.input_selection() {
+        let chars = input.chars().collect::<Vec<char>>();
+        let end = end.min(chars.len());
+        let start = start.min(end);
+        let before = chars[..start].iter().collect::<String>();
+        let selected = chars[start..end].iter().collect::<String>();
+        let after = chars[end..].iter().collect::<String>();
+
+        if selected.is_empty() {
+            vec![prompt, Span::raw(before), cursor, Span::raw(after)]
+        } else {
+            vec![
+                prompt,
+                Span::raw(before),
+                Span::styled(
+                    selected,
+                    TuiStyle::default().add_modifier(Modifier::REVERSED),
+                ),
+                Span::raw(after),
+            ]
+        }
+    } else {
+        vec![prompt, Span::raw(input), cursor]
+    };
+
+    let input_buf = Paragraph::new(Spans::from(spans)).block(block(app, " Input "));
     f.render_widget(input_buf, rect);
 }
 
+fn draw_confirmation<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
+    if let Some(confirmation) = app.confirmation() {
+        let prompt = Paragraph::new(Spans::from(vec![
+            Span::raw(confirmation.prompt.clone()),
+            Span::raw(" [y/n]"),
+        ]))
+        .block(block(app, " Confirm "));
+        f.render_widget(prompt, rect);
+    }
+}
+
+fn draw_type_ahead<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
+    if let Some(prefix) = app.type_ahead() {
+        let prompt = Paragraph::new(Spans::from(vec![
+            Span::raw(prefix.to_string()),
+            Span::raw(
+                app.config()
+                    .general
+                    .cursor
+                    .format
+                    .clone()
+                    .unwrap_or_default(),
+            ),
+        ]))
+        .block(block(app, " Type-ahead "));
+        f.render_widget(prompt, rect);
+    }
+}
+
+fn draw_transfer_progress<B: Backend>(
+    f: &mut Frame<B>,
+    rect: Rect,
+    app: &app::App,
+    _: &Handlebars,
+) {
+    if let Some(progress) = app.transfer_progress() {
+        let title = match progress.kind {
+            app::TransferKind::Copy => " Copying ",
+            app::TransferKind::Move => " Moving ",
+        };
+
+        let gauge = Gauge::default()
+            .block(block(app, title))
+            .gauge_style(app.config().general.focus_ui.style.into())
+            .ratio(progress.ratio())
+            .label(format!(
+                "{} ({} / {})",
+                progress.current,
+                human_size(progress.done, app.size_unit_base()),
+                human_size(progress.total, app.size_unit_base()),
+            ));
+        f.render_widget(gauge, rect);
+    }
+}
+
 fn draw_sort_n_filter_by<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
     let ui = app.config().general.sort_and_filter_ui.clone();
     let filter_by = app.explorer_config().filters();
@@ -429,9 +910,23 @@ fn draw_sort_n_filter_by<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::Ap
         ui.sort_direction_identifiers.reverse.style.into(),
     );
 
-    let mut spans = filter_by
-        .iter()
-        .map(|f| {
+    let quick_filter = app.explorer_config().quick_filter().map(|q| {
+        (
+            Span::styled(
+                ui.quick_filter_identifier
+                    .format
+                    .to_owned()
+                    .unwrap_or_default(),
+                ui.quick_filter_identifier.style.into(),
+            ),
+            Span::raw(q.clone()),
+        )
+    });
+
+    let mut spans = quick_filter
+        .clone()
+        .into_iter()
+        .chain(filter_by.iter().map(|f| {
             ui.filter_identifiers
                 .get(&f.filter)
                 .map(|u| {
@@ -441,7 +936,7 @@ fn draw_sort_n_filter_by<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::Ap
                     )
                 })
                 .unwrap_or_else(|| (Span::raw("f"), Span::raw("")))
-        })
+        }))
         .chain(sort_by.iter().map(|s| {
             let direction = if s.reverse {
                 reverse.clone()
@@ -463,97 +958,287 @@ fn draw_sort_n_filter_by<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::Ap
             ui.separator.format.to_owned().unwrap_or_default(),
             ui.separator.style.into(),
         )))
-        .map(|((a, b), c)| vec![a, b, c])
-        .flatten()
+        .flat_map(|((a, b), c)| vec![a, b, c])
         .collect::<Vec<Span>>();
     spans.pop();
 
-    let p = Paragraph::new(Spans::from(spans)).block(Block::default().borders(Borders::ALL).title(
-        format!(" Sort & filter ({}) ", filter_by.len() + sort_by.len()),
-    ));
+    let flags = [
+        (app.explorer_config().is_reversed(), &ui.reversed_identifier),
+        (
+            app.explorer_config().are_filters_suspended(),
+            &ui.filters_suspended_identifier,
+        ),
+    ];
+
+    for (_, identifier) in flags.iter().filter(|(on, _)| *on) {
+        if !spans.is_empty() {
+            spans.push(Span::styled(
+                ui.separator.format.to_owned().unwrap_or_default(),
+                ui.separator.style.into(),
+            ));
+        };
+        spans.push(Span::styled(
+            identifier.format.to_owned().unwrap_or_default(),
+            identifier.style.into(),
+        ));
+    }
+
+    let mut title = vec![Span::raw(format!(
+        " Sort & filter ({}) ",
+        quick_filter.iter().count() + filter_by.len() + sort_by.len()
+    ))];
+
+    if let Some(frame) = app.spinner() {
+        title.push(Span::styled(
+            frame,
+            app.config().general.spinner.style.into(),
+        ));
+        title.push(Span::raw(" "));
+    };
+
+    let p = Paragraph::new(Spans::from(spans)).block(block(app, Spans::from(title)));
 
     f.render_widget(p, rect);
 }
 
+fn log_item<'a>(log: &app::Log, config: &LogsConfig) -> ListItem<'a> {
+    let time = log.created_at.format("%r");
+    let level = match &log.level {
+        app::LogLevel::Info => &config.info,
+        app::LogLevel::Success => &config.success,
+        app::LogLevel::Warning => &config.warning,
+        app::LogLevel::Error => &config.error,
+    };
+    ListItem::new(format!(
+        "{} | {} | {}",
+        &time,
+        &level.format.to_owned().unwrap_or_default(),
+        &log.message
+    ))
+    .style(log.style.unwrap_or(level.style).into())
+}
+
 fn draw_logs<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
-    let config = app.config().general.logs.clone();
+    let config = &app.config().general.logs;
     let logs = app
         .logs()
         .iter()
         .rev()
         .take(1)
         .rev()
-        .map(|l| {
-            let time = l.created_at.format("%r");
-            match &l.level {
-                app::LogLevel::Info => ListItem::new(format!(
-                    "{} | {} | {}",
-                    &time,
-                    &config.info.format.to_owned().unwrap_or_default(),
-                    &l.message
-                ))
-                .style(config.info.style.into()),
-                app::LogLevel::Success => ListItem::new(format!(
-                    "{} | {} | {}",
-                    &time,
-                    &config.success.format.to_owned().unwrap_or_default(),
-                    &l.message
-                ))
-                .style(config.success.style.into()),
-                app::LogLevel::Error => ListItem::new(format!(
-                    "{} | {} | {}",
-                    &time,
-                    &config.error.format.to_owned().unwrap_or_default(),
-                    &l.message
-                ))
-                .style(config.error.style.into()),
-            }
-        })
+        .map(|l| log_item(l, config))
         .collect::<Vec<ListItem>>();
 
-    let logs_list = List::new(logs).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title(format!(" Logs ({}) ", app.logs().len())),
-    );
+    let logs_list = List::new(logs).block(block(app, format!(" Logs ({}) ", app.logs().len())));
 
     f.render_widget(logs_list, rect);
 }
 
-pub fn draw<B: Backend>(f: &mut Frame<B>, app: &app::App, hb: &Handlebars) {
-    let rect = f.size();
+/// The error logs over the given area, scrolled no further than needed to
+/// show the last one.
+fn draw_errors<B: Backend>(f: &mut Frame<B>, rect: Rect, app: &app::App, _: &Handlebars) {
+    if let Some(scroll) = app.errors_shown() {
+        let config = &app.config().general.logs;
+        let errors = app.errors();
+        let visible = usize::from(rect.height.saturating_sub(borders_size(app)));
+        let skip = scroll.min(errors.len().saturating_sub(visible));
+        let items = errors
+            .iter()
+            .skip(skip)
+            .map(|l| log_item(l, config))
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items).block(block(app, format!(" Errors ({}) ", errors.len())));
 
-    let chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([TuiConstraint::Percentage(70), TuiConstraint::Percentage(30)].as_ref())
-        .split(rect);
+        f.render_widget(Clear, rect);
+        f.render_widget(list, rect);
+    }
+}
+
+/// Split off the line for the tabs, if there's more than one tab.
+fn split_tabs(size: Rect, app: &app::App) -> (Option<Rect>, Rect) {
+    if app.tabs().len() > 1 {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([TuiConstraint::Length(1), TuiConstraint::Min(1)].as_ref())
+            .split(size);
+        (Some(chunks[0]), chunks[1])
+    } else {
+        (None, size)
+    }
+}
+
+/// Split off the line for the breadcrumb, if it's shown.
+fn split_breadcrumb(rect: Rect, app: &app::App) -> (Option<Rect>, Rect) {
+    if app.config().general.show_breadcrumb.unwrap_or_default() {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([TuiConstraint::Length(1), TuiConstraint::Min(1)].as_ref())
+            .split(rect);
+        (Some(chunks[0]), chunks[1])
+    } else {
+        (None, rect)
+    }
+}
+
+/// Where the panes of the current layout get drawn in the main area.
+struct MainAreas {
+    sort_and_filter: Option<Rect>,
+    table: Rect,
+    input_and_logs: Rect,
+    selection: Option<Rect>,
+    help_menu: Option<Rect>,
+    preview: Option<Rect>,
+}
+
+fn split_main(rect: Rect, app: &app::App) -> MainAreas {
+    let layout = app.layout();
+    let show_selection = layout.selection.unwrap_or_default();
+    let show_help_menu = layout.help_menu.unwrap_or_default();
+
+    let (left, right) = if show_selection || show_help_menu {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    TuiConstraint::Percentage(app.layout_ratio()),
+                    TuiConstraint::Percentage(100 - app.layout_ratio()),
+                ]
+                .as_ref(),
+            )
+            .split(rect);
+        (chunks[0], Some(chunks[1]))
+    } else {
+        (rect, None)
+    };
+
+    let (sort_and_filter, left) = if layout.sort_and_filter.unwrap_or_default() {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    TuiConstraint::Length(1 + borders_size(app)),
+                    TuiConstraint::Min(1),
+                ]
+                .as_ref(),
+            )
+            .split(left);
+        (Some(chunks[0]), chunks[1])
+    } else {
+        (None, left)
+    };
 
     let left_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints(
             [
-                TuiConstraint::Length(3),
-                TuiConstraint::Length(rect.height - 6),
-                TuiConstraint::Length(3),
+                TuiConstraint::Min(1),
+                TuiConstraint::Length(1 + borders_size(app)),
             ]
             .as_ref(),
         )
-        .split(chunks[0]);
+        .split(left);
+    let (mut table, input_and_logs) = (left_chunks[0], left_chunks[1]);
 
-    draw_sort_n_filter_by(f, left_chunks[0], app, hb);
-    draw_table(f, left_chunks[1], app, hb);
+    let (selection, help_menu) = match right {
+        Some(right) if show_selection && show_help_menu => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(
+                    [TuiConstraint::Percentage(50), TuiConstraint::Percentage(50)].as_ref(),
+                )
+                .split(right);
+            (Some(chunks[0]), Some(chunks[1]))
+        }
+        Some(right) if show_selection => (Some(right), None),
+        Some(right) => (None, Some(right)),
+        None => (None, None),
+    };
 
-    if app.input_buffer().is_some() {
-        draw_input_buffer(f, left_chunks[2], app, hb);
+    let preview = if app.preview_children() {
+        let table_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([TuiConstraint::Percentage(60), TuiConstraint::Percentage(40)].as_ref())
+            .split(table);
+        table = table_chunks[0];
+        Some(table_chunks[1])
     } else {
-        draw_logs(f, left_chunks[2], app, hb);
+        None
     };
 
-    let right_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([TuiConstraint::Percentage(50), TuiConstraint::Percentage(50)].as_ref())
-        .split(chunks[1]);
+    MainAreas {
+        sort_and_filter,
+        table,
+        input_and_logs,
+        selection,
+        help_menu,
+        preview,
+    }
+}
+
+/// Where the table rows get drawn on a screen of the given size.
+pub fn table_area(size: Rect, app: &app::App) -> app::TableArea {
+    let (_, rect) = split_tabs(size, app);
+    let (_, rect) = split_breadcrumb(rect, app);
+    let rect = split_main(rect, app).table;
+    let header_height = app.config().general.table.header.height.unwrap_or(1);
+    let border = borders_size(app) / 2;
+    app::TableArea {
+        x: rect.x + border,
+        y: rect.y + border + header_height,
+        width: rect.width.saturating_sub(border * 2),
+        height: rect.height.saturating_sub(header_height + border * 2),
+    }
+}
+
+/// Draw the app. The owner and group names are looked up through the given
+/// cache, kept from one frame to the next.
+pub fn draw<B: Backend>(f: &mut Frame<B>, app: &app::App, hb: &Handlebars, users: &UsersCache) {
+    let (tabs_rect, rect) = split_tabs(f.size(), app);
+    if let Some(tabs_rect) = tabs_rect {
+        draw_tabs(f, tabs_rect, app, hb);
+    };
+
+    let (breadcrumb_rect, rect) = split_breadcrumb(rect, app);
+    if let Some(breadcrumb_rect) = breadcrumb_rect {
+        draw_breadcrumb(f, breadcrumb_rect, app, hb);
+    };
+
+    let areas = split_main(rect, app);
 
-    draw_selection(f, right_chunks[0], app, hb);
-    draw_help_menu(f, right_chunks[1], app, hb);
+    if let Some(rect) = areas.sort_and_filter {
+        draw_sort_n_filter_by(f, rect, app, hb);
+    };
+    draw_table(f, areas.table, app, hb, users);
+    if let Some(preview) = areas.preview {
+        draw_preview(f, preview, app, hb);
+    };
+    draw_picker(f, areas.table, app, hb);
+
+    let rect = areas.input_and_logs;
+    if app.confirmation().is_some() {
+        draw_confirmation(f, rect, app, hb);
+    } else if app.input_buffer().is_some() {
+        draw_input_buffer(f, rect, app, hb);
+    } else if app.type_ahead().is_some() {
+        draw_type_ahead(f, rect, app, hb);
+    } else if app.transfer_progress().is_some() {
+        draw_transfer_progress(f, rect, app, hb);
+    } else {
+        draw_logs(f, rect, app, hb);
+    };
+
+    if let Some(rect) = areas.selection {
+        draw_selection(f, rect, app, hb);
+    };
+    if let Some(rect) = areas.help_menu {
+        draw_help_menu(f, rect, app, hb);
+    };
+    // The command output takes the place of the selection, or of the help
+    // menu when the selection isn't shown.
+    if let Some(rect) = areas.selection.or(areas.help_menu) {
+        draw_command_output(f, rect, app, hb);
+    };
+    draw_full_help(f, f.size(), app, hb);
+    draw_errors(f, f.size(), app, hb);
 }
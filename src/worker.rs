@@ -0,0 +1,58 @@
+use crate::app::{ExternalMsg, InternalMsg, MsgIn, Task};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+/// The number of background workers running right now.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+struct Running;
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Spawn a short-lived background worker. It's counted as running until it
+/// returns or panics.
+pub fn spawn<F>(f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    RUNNING.fetch_add(1, Ordering::SeqCst);
+    thread::spawn(move || {
+        let _running = Running;
+        f()
+    });
+}
+
+/// Send the external message to the app from a background thread.
+pub fn send(tx: &Sender<Task>, msg: ExternalMsg) {
+    tx.send(Task::new(MsgIn::External(msg), None)).unwrap();
+}
+
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst) > 0
+}
+
+/// Advance the spinner at the given interval while any worker is running,
+/// and once more after they stop so that the spinner gets cleared.
+pub fn keep_spinning(interval: Duration, tx: Sender<Task>) {
+    thread::spawn(move || {
+        let mut was_running = false;
+        loop {
+            thread::sleep(interval);
+            let running = is_running();
+            if running || was_running {
+                tx.send(Task::new(
+                    MsgIn::Internal(InternalMsg::AdvanceSpinner),
+                    None,
+                ))
+                .unwrap();
+            };
+            was_running = running;
+        }
+    });
+}
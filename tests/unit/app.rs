@@ -0,0 +1,2261 @@
+use std::env;
+use xplr::*;
+
+/// Create the app without the state kept between sessions, so that it doesn't
+/// depend on the user's data directory.
+fn create_app(
+    pwd: std::path::PathBuf,
+    config_file: Option<std::path::PathBuf>,
+) -> anyhow::Result<app::App> {
+    app::App::create_with_state_files(pwd, config_file, Default::default())
+}
+
+#[test]
+fn test_expand_path() {
+    env::set_var("XPLR_TEST_EXPAND_PATH", "foo");
+
+    assert_eq!(
+        app::expand_path("/tmp/$XPLR_TEST_EXPAND_PATH/${XPLR_TEST_EXPAND_PATH}").unwrap(),
+        "/tmp/foo/foo"
+    );
+    assert_eq!(
+        app::expand_path("~/bar").unwrap(),
+        app::expand_path("$HOME/bar").unwrap()
+    );
+    assert!(app::expand_path("/tmp/$XPLR_TEST_UNDEFINED").is_err());
+    assert_eq!(app::expand_path("/tmp/a$").unwrap(), "/tmp/a$");
+}
+
+fn app_with_focus_at(focus: usize) -> app::App {
+    let nodes = (0..10)
+        .map(|i| app::Node::new("/tmp".into(), i.to_string()))
+        .collect::<Vec<app::Node>>();
+    let dir = app::DirectoryBuffer::new("/tmp".into(), nodes, 10, focus);
+
+    create_app("/tmp".into(), None)
+        .unwrap()
+        .handle_task(app::Task::new(
+            app::MsgIn::Internal(app::InternalMsg::AddDirectory("/tmp".into(), dir)),
+            None,
+        ))
+        .unwrap()
+}
+
+fn focus_by_index_from_input(app: app::App, input: &str) -> usize {
+    app.handle_task(app::Task::new(
+        app::MsgIn::External(app::ExternalMsg::SetInputBuffer(input.into())),
+        None,
+    ))
+    .unwrap()
+    .handle_task(app::Task::new(
+        app::MsgIn::External(app::ExternalMsg::FocusByIndexFromInput),
+        None,
+    ))
+    .unwrap()
+    .directory_buffer()
+    .unwrap()
+    .focus
+}
+
+#[test]
+fn test_focus_by_index_from_input_absolute() {
+    assert_eq!(focus_by_index_from_input(app_with_focus_at(5), "2"), 2);
+    assert_eq!(focus_by_index_from_input(app_with_focus_at(5), "20"), 9);
+}
+
+#[test]
+fn test_focus_by_index_from_input_relative_down() {
+    assert_eq!(focus_by_index_from_input(app_with_focus_at(5), "+2"), 7);
+    assert_eq!(focus_by_index_from_input(app_with_focus_at(5), "+20"), 9);
+}
+
+#[test]
+fn test_focus_by_index_from_input_relative_up() {
+    assert_eq!(focus_by_index_from_input(app_with_focus_at(5), "-2"), 3);
+    assert_eq!(focus_by_index_from_input(app_with_focus_at(5), "-20"), 0);
+}
+
+#[test]
+fn test_confirm_refuses_to_modify_files_in_read_only_mode() {
+    let msgs = vec![
+        app::ExternalMsg::ToggleReadOnly,
+        app::ExternalMsg::Confirm {
+            prompt: "Delete?".into(),
+            on_yes: vec![app::ExternalMsg::Delete],
+            on_no: vec![],
+        },
+        app::ExternalMsg::ConfirmYes,
+    ];
+
+    let app = msgs
+        .into_iter()
+        .fold(create_app("/tmp".into(), None).unwrap(), |app, msg| {
+            app.handle_task(app::Task::new(app::MsgIn::External(msg), None))
+                .unwrap()
+        });
+
+    assert!(app.confirmation().is_none());
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+}
+
+#[test]
+fn test_external_msg_name() {
+    assert_eq!(app::ExternalMsg::FocusNext.name(), "FocusNext");
+    assert_eq!(
+        app::ExternalMsg::SwitchMode("default".into()).name(),
+        "SwitchMode"
+    );
+}
+
+/// Handle the tasks like the runner does, including the ones they enqueue.
+fn run_tasks(mut app: app::App, msgs: Vec<app::MsgIn>) -> app::App {
+    let mut queue: std::collections::VecDeque<app::Task> =
+        msgs.into_iter().map(|m| app::Task::new(m, None)).collect();
+
+    while let Some(task) = queue.pop_front() {
+        app = app.handle_task(task).unwrap();
+        while let Some(out) = app.pop_msg_out() {
+            if let app::MsgOut::Enque(task) = out {
+                queue.push_back(task);
+            }
+        }
+    }
+    app
+}
+
+#[test]
+fn test_play_macro() {
+    let down = app::MsgIn::Internal(app::InternalMsg::HandleKey(input::Key::Down));
+    let app = vec![
+        app::MsgIn::External(app::ExternalMsg::StartRecordingMacro('a')),
+        down.clone(),
+        down,
+        app::MsgIn::External(app::ExternalMsg::StopRecordingMacro),
+        app::MsgIn::External(app::ExternalMsg::FocusFirst),
+        app::MsgIn::External(app::ExternalMsg::PlayMacro('a')),
+        app::MsgIn::External(app::ExternalMsg::PlayMacro('a')),
+    ]
+    .into_iter()
+    .fold(app_with_focus_at(0), |app, msg| run_tasks(app, vec![msg]));
+
+    assert_eq!(app.directory_buffer().unwrap().focus, 4);
+}
+
+#[test]
+fn test_input_cursor_editing() {
+    let app = vec![
+        app::ExternalMsg::SetInputBuffer("foo bar".into()),
+        app::ExternalMsg::InputCursorWordLeft,
+        app::ExternalMsg::InputCursorLeft,
+        app::ExternalMsg::BufferInput("!".into()),
+        app::ExternalMsg::InputCursorHome,
+        app::ExternalMsg::RemoveInputBufferNextCharacter,
+        app::ExternalMsg::InputCursorWordRight,
+        app::ExternalMsg::RemoveInputBufferToEnd,
+    ]
+    .into_iter()
+    .fold(create_app("/tmp".into(), None).unwrap(), |app, msg| {
+        app.handle_task(app::Task::new(app::MsgIn::External(msg), None))
+            .unwrap()
+    });
+
+    assert_eq!(app.input_buffer(), Some("oo".into()));
+    assert_eq!(app.input_selection(), Some((2, 2)));
+}
+
+#[test]
+fn test_recent_directories() {
+    let recent = app::RecentDirectories::default()
+        .push("/a".into(), 3)
+        .push("/b".into(), 3)
+        .push("/a".into(), 3)
+        .push("/c".into(), 3)
+        .push("/d".into(), 3);
+
+    assert_eq!(recent.paths(), ["/d", "/c", "/a"]);
+    assert_eq!(recent.remove("/c").paths(), ["/d", "/a"]);
+}
+
+#[test]
+fn test_recent_directories_are_read_from_the_given_file() {
+    let dir = env::temp_dir().join(format!(
+        "xplr-test-recent-directories-file-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("recent_directories");
+    std::fs::write(&file, "/a\n/b\n").unwrap();
+
+    let state_files = app::StateFiles {
+        recent_directories: Some(file),
+        ..Default::default()
+    };
+    let app = app::App::create_with_state_files("/tmp".into(), None, state_files).unwrap();
+    assert_eq!(app.recent_directories().paths(), ["/tmp", "/a", "/b"]);
+
+    let app = create_app("/tmp".into(), None).unwrap();
+    assert_eq!(app.recent_directories().paths(), ["/tmp"]);
+}
+
+#[test]
+fn test_directory_scores() {
+    let now = 10 * 24 * 60 * 60;
+    let scores = app::DirectoryScores::default()
+        .visit("/home/user/proj/src".into(), 0)
+        .visit("/home/user/proj/src".into(), 0)
+        .visit("/home/user/proj/src".into(), 0)
+        .visit("/home/user/proj/docs".into(), now)
+        .visit("/home/user/Source".into(), now);
+
+    assert_eq!(
+        scores.matches("proj", now),
+        ["/home/user/proj/docs", "/home/user/proj/src"]
+    );
+    assert_eq!(scores.matches("SOURCE", now), ["/home/user/Source"]);
+    assert_eq!(scores.matches("proj src", now), ["/home/user/proj/src"]);
+    assert!(scores.matches("src proj", now).is_empty());
+}
+
+#[test]
+fn test_quick_filter_layers_on_top_of_filters() {
+    let app = vec![
+        app::ExternalMsg::QuickFilter("READ".into()),
+        app::ExternalMsg::AddNodeFilter(app::NodeFilterApplicable::new(
+            app::NodeFilter::RelativePathDoesEndWith,
+            ".md".into(),
+        )),
+    ]
+    .into_iter()
+    .fold(create_app("/tmp".into(), None).unwrap(), |app, msg| {
+        app.handle_task(app::Task::new(app::MsgIn::External(msg), None))
+            .unwrap()
+    });
+
+    let config = app.explorer_config();
+    assert_eq!(config.quick_filter(), Some(&"READ".to_string()));
+    assert!(config.filter(&app::Node::new("/tmp".into(), "readme.md".into())));
+    assert!(!config.filter(&app::Node::new("/tmp".into(), "readme.txt".into())));
+    assert!(!config.filter(&app::Node::new("/tmp".into(), "notes.md".into())));
+
+    let app = app
+        .handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::ClearQuickFilter),
+            None,
+        ))
+        .unwrap();
+    assert!(app
+        .explorer_config()
+        .filter(&app::Node::new("/tmp".into(), "notes.md".into())));
+}
+
+#[test]
+fn test_sort_by_regex_capture() {
+    let msg: app::ExternalMsg =
+        serde_yaml::from_str(r#"AddNodeSorter: {sorter: ByRegexCapture, regex: 'S\d+E(\d+)'}"#)
+            .unwrap();
+
+    let app = vec![app::ExternalMsg::ClearNodeSorters, msg]
+        .into_iter()
+        .fold(create_app("/tmp".into(), None).unwrap(), |app, msg| {
+            app.handle_task(app::Task::new(app::MsgIn::External(msg), None))
+                .unwrap()
+        });
+
+    let mut nodes = vec![
+        "Show.S01E10.mkv",
+        "notes.txt",
+        "Show.S01E9.mkv",
+        "Show.S01E005.mkv",
+    ]
+    .into_iter()
+    .map(|n| app::Node::new("/tmp".into(), n.into()))
+    .collect::<Vec<app::Node>>();
+
+    nodes.sort_by(|a, b| app.explorer_config().sort(a, b));
+
+    assert_eq!(
+        nodes
+            .iter()
+            .map(|n| n.relative_path.as_str())
+            .collect::<Vec<&str>>(),
+        [
+            "Show.S01E005.mkv",
+            "Show.S01E9.mkv",
+            "Show.S01E10.mkv",
+            "notes.txt"
+        ]
+    );
+
+    assert!(serde_yaml::from_str::<app::ExternalMsg>(
+        "AddNodeSorter: {sorter: ByRegexCapture, regex: '('}"
+    )
+    .is_err());
+}
+
+#[test]
+fn test_command_palette_fuzzy_matches_key_bindings() {
+    let app = vec![
+        app::ExternalMsg::OpenCommandPalette,
+        app::ExternalMsg::FilterPicker("DEFctrl-pPALETTE".into()),
+    ]
+    .into_iter()
+    .fold(create_app("/tmp".into(), None).unwrap(), |app, msg| {
+        app.handle_task(app::Task::new(app::MsgIn::External(msg), None))
+            .unwrap()
+    });
+
+    let picker = app.picker().unwrap();
+    assert_eq!(picker.return_mode, "default");
+    assert_eq!(
+        picker.focused_item().unwrap().messages,
+        vec![app::ExternalMsg::OpenCommandPalette]
+    );
+}
+
+#[test]
+fn test_yank_and_cut_fill_the_paste_register() {
+    let run = |msgs: Vec<app::ExternalMsg>| {
+        msgs.into_iter()
+            .fold(create_app("/tmp".into(), None).unwrap(), |app, msg| {
+                app.handle_task(app::Task::new(app::MsgIn::External(msg), None))
+                    .unwrap()
+            })
+    };
+
+    let app = run(vec![app::ExternalMsg::YankSelection]);
+    assert_eq!(app.paste_register(), None);
+
+    let app = run(vec![
+        app::ExternalMsg::SelectPath("/tmp/a".into()),
+        app::ExternalMsg::SelectPath("/tmp/b".into()),
+        app::ExternalMsg::CutSelection,
+    ]);
+    assert!(app.selection().is_empty());
+    assert_eq!(
+        app.paste_register(),
+        Some(&app::PasteRegister {
+            kind: app::TransferKind::Move,
+            paths: vec!["/tmp/a".into(), "/tmp/b".into()],
+        })
+    );
+}
+
+#[test]
+fn test_parse_mode() {
+    let parse = |spec: &str, mode: u32| fs_ops::parse_mode(spec, mode, false).unwrap();
+
+    assert_eq!(parse("755", 0o600), 0o755);
+    assert_eq!(parse("4755", 0o600), 0o4755);
+    assert_eq!(parse("u+x", 0o644), 0o744);
+    assert_eq!(parse("go-w", 0o666), 0o644);
+    assert_eq!(parse("a=r", 0o755), 0o444);
+    assert_eq!(parse("u=rw,go=r", 0o777), 0o644);
+    assert_eq!(parse("+X", 0o644), 0o644);
+    assert_eq!(parse("+X", 0o744), 0o755);
+    assert_eq!(parse("u+s,+t", 0o755), 0o5755);
+    assert_eq!(parse("u+x-w", 0o644), 0o544);
+    assert_eq!(fs_ops::parse_mode("+X", 0o644, true).unwrap(), 0o755);
+
+    assert!(fs_ops::parse_mode("", 0o644, false).is_err());
+    assert!(fs_ops::parse_mode("888", 0o644, false).is_err());
+    assert!(fs_ops::parse_mode("u+q", 0o644, false).is_err());
+    assert!(fs_ops::parse_mode("z+x", 0o644, false).is_err());
+}
+
+#[test]
+fn test_parse_owner() {
+    let parse = |spec: &str| fs_ops::parse_owner(spec, (1000, 1000));
+
+    assert_eq!(parse("0").unwrap(), (0, 1000));
+    assert_eq!(parse("0:0").unwrap(), (0, 0));
+    assert_eq!(parse(":0").unwrap(), (1000, 0));
+    assert_eq!(parse("root:").unwrap(), (0, 1000));
+    assert!(parse(":").is_err());
+    assert!(parse("no-such-user-xplr").is_err());
+}
+
+#[test]
+fn test_relative_symlink_target() {
+    let rel = |from: &str, to: &str| {
+        fs_ops::relative_path(std::path::Path::new(from), std::path::Path::new(to))
+            .to_string_lossy()
+            .to_string()
+    };
+
+    assert_eq!(rel("/a/b", "/a/b/c"), "c");
+    assert_eq!(rel("/a/b", "/a/c/d"), "../c/d");
+    assert_eq!(rel("/a/b", "/x"), "../../x");
+    assert_eq!(rel("/a/b", "/a/b"), ".");
+}
+
+#[test]
+fn test_compute_hash_rejects_unsupported_algorithms() {
+    assert_eq!(
+        app::HashAlgorithm::from_name("SHA256"),
+        Some(app::HashAlgorithm::Sha256)
+    );
+
+    let app = create_app("/tmp".into(), None)
+        .unwrap()
+        .handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::ComputeHash("crc32".into())),
+            None,
+        ))
+        .unwrap();
+
+    let log = app.logs().last().unwrap();
+    assert_eq!(log.level, app::LogLevel::Error);
+    assert!(log.message.ends_with("md5, sha1, sha256"));
+}
+
+#[test]
+fn test_line_diff() {
+    let a = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let b = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+    assert_eq!(
+        differ::line_diff(a, b).unwrap(),
+        "@@ -2 +2 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+    );
+    assert_eq!(differ::line_diff(a, a).unwrap(), "");
+}
+
+#[test]
+fn test_list_tar_archive() {
+    let dir = env::temp_dir().join(format!("xplr-test-list-tar-archive-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("test.tar");
+
+    let mut builder = tar::Builder::new(std::fs::File::create(&path).unwrap());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(5);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "docs/readme.txt", &b"hello"[..])
+        .unwrap();
+    builder.finish().unwrap();
+
+    let docs = path.join("docs").to_string_lossy().to_string();
+    let (archive, inner) = archive::split(&docs).unwrap();
+    assert_eq!(archive, path);
+
+    let root = archive::list(&archive, std::path::Path::new("")).unwrap();
+    assert_eq!(root.len(), 1);
+    assert!(root[0].is_dir);
+
+    let nodes = archive::list(&archive, &inner).unwrap();
+    assert_eq!(nodes[0].relative_path, "readme.txt");
+    assert_eq!(nodes[0].size, 5);
+
+    // The archive is read again once modified.
+    let mut builder = tar::Builder::new(std::fs::File::create(&path).unwrap());
+    for name in ["docs/readme.txt", "docs/license.txt"] {
+        builder
+            .append_data(&mut header, name, &b"hello"[..])
+            .unwrap();
+    }
+    builder.finish().unwrap();
+
+    let nodes = archive::list(&archive, &inner).unwrap();
+    assert_eq!(nodes.len(), 2);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_parse_mounts() {
+    let mounts = app::parse_mounts(
+        "proc /proc proc rw,nosuid 0 0\n\
+         /dev/sda1 / ext4 rw,relatime 0 0\n\
+         /dev/sdb1 /media/USB\\040DISK vfat rw 0 0\n",
+    );
+
+    assert_eq!(mounts.len(), 2);
+    assert_eq!(mounts[1].path, "/media/USB DISK");
+    assert_eq!(mounts[1].fs_type, "vfat");
+}
+
+#[test]
+fn test_focus_next_matching_wraps_only_when_asked() {
+    let nodes = vec!["a.rs", "b.txt", "c.rs", "d.txt"]
+        .into_iter()
+        .map(|n| app::Node::new("/tmp".into(), n.into()))
+        .collect::<Vec<app::Node>>();
+    let dir = app::DirectoryBuffer::new("/tmp".into(), nodes, 4, 2);
+
+    let focus_next = |app: app::App, wrap| {
+        let matching = app::FocusMatching {
+            filter: app::NodeFilter::RelativePathDoesEndWith,
+            input: ".rs".into(),
+            wrap,
+        };
+        app.handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::FocusNextMatching(matching)),
+            None,
+        ))
+        .unwrap()
+    };
+
+    let app = create_app("/tmp".into(), None)
+        .unwrap()
+        .handle_task(app::Task::new(
+            app::MsgIn::Internal(app::InternalMsg::AddDirectory("/tmp".into(), dir)),
+            None,
+        ))
+        .unwrap();
+
+    let app = focus_next(app, false);
+    assert_eq!(app.directory_buffer().unwrap().focus, 2);
+
+    let app = focus_next(app, true);
+    assert_eq!(app.directory_buffer().unwrap().focus, 0);
+}
+
+#[test]
+fn test_runaway_function_is_stopped() {
+    let dir = env::temp_dir().join(format!("xplr-test-runaway-function-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("config.yml");
+    std::fs::write(
+        &file,
+        "version: v0.5.5\nfunctions:\n  loop:\n    - Refresh\n    - CallFunction: loop\n",
+    )
+    .unwrap();
+
+    let app = create_app("/tmp".into(), Some(file))
+        .unwrap()
+        .handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::CallFunction("loop".into())),
+            None,
+        ))
+        .unwrap();
+
+    let log = app.logs().last().unwrap();
+    assert_eq!(log.level, app::LogLevel::Error);
+    assert!(log.message.starts_with("Function loop calls functions"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_logs_are_pruned_oldest_first() {
+    let dir = env::temp_dir().join(format!("xplr-test-logs-are-pruned-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("config.yml");
+    std::fs::write(&file, "version: v0.5.5\ngeneral:\n  max_logs: 2\n").unwrap();
+
+    let mut app = create_app("/tmp".into(), Some(file)).unwrap();
+    for msg in &["one", "two", "three"] {
+        app = app
+            .handle_task(app::Task::new(
+                app::MsgIn::External(app::ExternalMsg::LogInfo(msg.to_string())),
+                None,
+            ))
+            .unwrap();
+    }
+
+    let messages = app
+        .logs()
+        .iter()
+        .map(|l| l.message.clone())
+        .collect::<Vec<String>>();
+    assert_eq!(messages, vec!["two", "three"]);
+    assert_eq!(app.logs_since(app.logs_total() - 1)[0].message, "three");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_listing_marks_the_selection() {
+    let nodes = vec!["a", "b"]
+        .into_iter()
+        .map(|n| app::Node::new("/tmp".into(), n.into()))
+        .collect::<Vec<app::Node>>();
+    let dir = app::DirectoryBuffer::new("/tmp".into(), nodes, 2, 1);
+
+    let app = create_app("/tmp".into(), None)
+        .unwrap()
+        .handle_task(app::Task::new(
+            app::MsgIn::Internal(app::InternalMsg::AddDirectory("/tmp".into(), dir)),
+            None,
+        ))
+        .unwrap()
+        .handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::ToggleSelection),
+            None,
+        ))
+        .unwrap();
+
+    let listing = serde_json::to_value(app.listing()).unwrap();
+    assert_eq!(listing[0]["relative_path"], "a");
+    assert_eq!(listing[0]["is_selected"], false);
+    assert_eq!(listing[1]["absolute_path"], "/tmp/b");
+    assert_eq!(listing[1]["is_selected"], true);
+}
+
+#[test]
+fn test_control_fifo_skips_malformed_lines() {
+    let dir = env::temp_dir().join(format!("xplr-test-control-fifo-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let fifo = dir.join("control").to_string_lossy().to_string();
+    std::fs::remove_file(&fifo).unwrap_or_default();
+    assert!(fifo_reader::create(&fifo).unwrap());
+    assert!(!fifo_reader::create(&fifo).unwrap());
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    fifo_reader::keep_reading(fifo.clone(), tx);
+    std::fs::write(&fifo, "oops\n{\"LogInfo\": \"hello\"}\n").unwrap();
+
+    let timeout = std::time::Duration::from_secs(5);
+    let logs = (0..2)
+        .map(|_| {
+            let app = create_app("/tmp".into(), None).unwrap();
+            let app = app.handle_task(rx.recv_timeout(timeout).unwrap()).unwrap();
+            let log = app.logs().last().unwrap().clone();
+            (log.level, log.message)
+        })
+        .collect::<Vec<(app::LogLevel, String)>>();
+
+    assert_eq!(logs[0].0, app::LogLevel::Error);
+    assert_eq!(logs[1], (app::LogLevel::Info, "hello".into()));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_set_explorer_config_round_trips() {
+    let app = create_app("/tmp".into(), None).unwrap();
+    let view = serde_yaml::to_string(&app.explorer_config().view()).unwrap();
+
+    let set = |app: app::App, view: &str| {
+        app.handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::SetExplorerConfig(view.into())),
+            None,
+        ))
+        .unwrap()
+    };
+
+    let sorted = set(app.clone(), "{sorters: [{sorter: BySize, reverse: true}]}");
+    assert_eq!(sorted.explorer_config().sorters().len(), 1);
+
+    let invalid = set(sorted.clone(), "{sorters: nope}");
+    assert_eq!(invalid.explorer_config(), sorted.explorer_config());
+    assert_eq!(invalid.logs().last().unwrap().level, app::LogLevel::Error);
+
+    let restored = set(invalid, &view);
+    assert_eq!(restored.explorer_config(), app.explorer_config());
+}
+
+#[test]
+fn test_sort_by_column_flips_the_direction() {
+    let sort_by = |app: app::App, column| {
+        app.handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::SortByColumn(column)),
+            None,
+        ))
+        .unwrap()
+    };
+    let size_sorter = |app: &app::App| {
+        app.explorer_config()
+            .sorters()
+            .iter()
+            .find(|s| s.sorter == app::NodeSorter::BySize)
+            .map(|s| s.reverse)
+    };
+
+    let app = sort_by(create_app("/tmp".into(), None).unwrap(), 2);
+    assert_eq!(size_sorter(&app), Some(false));
+    assert!(!app
+        .explorer_config()
+        .sorters()
+        .iter()
+        .any(|s| s.sorter == app::NodeSorter::ByIRelativePath));
+
+    let app = sort_by(app, 2);
+    assert_eq!(size_sorter(&app), Some(true));
+
+    let app = sort_by(app, 0);
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+}
+
+#[test]
+fn test_directory_change_hook_does_not_recurse() {
+    // The hook changes to a link to the current directory, and the test to
+    // the current directory, which is left as it is.
+    let dir = env::temp_dir().join(format!(
+        "xplr-test-directory-change-hook-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("config.yml");
+    let root = env!("CARGO_MANIFEST_DIR");
+    let link = dir.join("root").to_string_lossy().to_string();
+    std::os::unix::fs::symlink(root, &link).unwrap();
+    std::fs::write(
+        &file,
+        format!(
+            "version: v0.5.5
+general:
+  on_directory_change:
+    messages:
+      - LogInfo: entered
+      - ChangeDirectory: {}
+",
+            link
+        ),
+    )
+    .unwrap();
+
+    let app = create_app("/tmp".into(), Some(file)).unwrap();
+    let app = run_tasks(
+        app,
+        vec![app::MsgIn::External(app::ExternalMsg::ChangeDirectory(
+            root.into(),
+        ))],
+    );
+
+    assert_eq!(app.pwd(), &link);
+    assert_eq!(
+        app.logs().iter().filter(|l| l.message == "entered").count(),
+        1
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_focus_change_hook_runs_once_per_burst() {
+    let dir = env::temp_dir().join(format!(
+        "xplr-test-focus-change-hook-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("config.yml");
+    std::fs::write(
+        &file,
+        "version: v0.5.5
+general:
+  on_focus_change:
+    messages:
+      - LogInfo: focused
+",
+    )
+    .unwrap();
+
+    let nodes = (0..10)
+        .map(|i| app::Node::new("/tmp".into(), i.to_string()))
+        .collect::<Vec<app::Node>>();
+    let buffer = app::DirectoryBuffer::new("/tmp".into(), nodes, 10, 0);
+
+    let mut app = create_app("/tmp".into(), Some(file)).unwrap();
+    let mut changes = vec![];
+    for msg in [
+        app::MsgIn::Internal(app::InternalMsg::AddDirectory("/tmp".into(), buffer)),
+        app::MsgIn::External(app::ExternalMsg::FocusNext),
+        app::MsgIn::External(app::ExternalMsg::FocusNext),
+    ] {
+        app = app.handle_task(app::Task::new(msg, None)).unwrap();
+        while let Some(out) = app.pop_msg_out() {
+            if let app::MsgOut::DebounceFocusChange(change, _) = out {
+                changes.push(app::MsgIn::Internal(app::InternalMsg::RunFocusChangeHook(
+                    change,
+                )));
+            }
+        }
+    }
+    assert_eq!(changes.len(), 3);
+
+    let app = run_tasks(app, changes);
+    assert_eq!(
+        app.logs().iter().filter(|l| l.message == "focused").count(),
+        1
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sibling_directory_skips_files_and_clamps() {
+    // The sibling directories are links to the current directory, so that
+    // changing to them leaves it as it is.
+    let dir = env::temp_dir().join(format!(
+        "xplr-test-sibling-directory-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    for d in &["a", "c"] {
+        std::os::unix::fs::symlink(env!("CARGO_MANIFEST_DIR"), dir.join(d)).unwrap();
+    }
+    std::fs::write(dir.join("b"), "").unwrap();
+
+    let parent = dir.to_string_lossy().to_string();
+    let nodes = ["a", "b", "c"]
+        .iter()
+        .map(|n| {
+            let mut node = app::Node::new(parent.clone(), n.to_string());
+            node.is_dir = *n != "b";
+            node
+        })
+        .collect::<Vec<app::Node>>();
+    let buffer = app::DirectoryBuffer::new(parent.clone(), nodes, 3, 0);
+
+    let app = create_app(dir.join("a"), None)
+        .unwrap()
+        .handle_task(app::Task::new(
+            app::MsgIn::Internal(app::InternalMsg::AddDirectory(parent.clone(), buffer)),
+            None,
+        ))
+        .unwrap();
+
+    let go = |app: app::App, msg| {
+        app.handle_task(app::Task::new(app::MsgIn::External(msg), None))
+            .unwrap()
+    };
+
+    let app = go(app, app::ExternalMsg::NextSiblingDirectory);
+    assert_eq!(app.pwd(), &format!("{}/c", parent));
+
+    let app = go(app, app::ExternalMsg::NextSiblingDirectory);
+    assert_eq!(app.pwd(), &format!("{}/c", parent));
+
+    let app = go(app, app::ExternalMsg::PreviousSiblingDirectory);
+    assert_eq!(app.pwd(), &format!("{}/a", parent));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_go_home_stays_when_missing() {
+    let dir = env::temp_dir().join(format!("xplr-test-go-home-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("config.yml");
+    std::fs::write(
+        &file,
+        "version: v0.5.5\ngeneral:\n  home_directory: /no/such/xplr/home\n",
+    )
+    .unwrap();
+
+    let app = create_app("/tmp".into(), Some(file))
+        .unwrap()
+        .handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::GoHome),
+            None,
+        ))
+        .unwrap();
+
+    assert_eq!(app.pwd(), "/tmp");
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_full_help_scrolls_then_closes_on_any_key() {
+    let press = |app: app::App, key| {
+        app.handle_task(app::Task::new(
+            app::MsgIn::Internal(app::InternalMsg::HandleKey(key)),
+            None,
+        ))
+        .unwrap()
+    };
+
+    let mut app = create_app("/tmp".into(), None).unwrap();
+    while app.pop_msg_out().is_some() {}
+
+    let app = app
+        .handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::ToggleFullHelp),
+            None,
+        ))
+        .unwrap();
+    assert_eq!(app.full_help(), Some(0));
+
+    let app = press(app, input::Key::Down);
+    assert_eq!(app.full_help(), Some(1));
+
+    let mut app = press(app, input::Key::Q);
+    assert_eq!(app.full_help(), None);
+    while let Some(out) = app.pop_msg_out() {
+        assert!(!matches!(out, app::MsgOut::Enque(_)));
+    }
+}
+
+#[test]
+fn test_explorer_config_resets_on_directory_change_unless_persisted() {
+    let dir = env::temp_dir().join(format!(
+        "xplr-test-persist-explorer-config-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("config.yml");
+    std::fs::write(
+        &file,
+        "version: v0.5.5\ngeneral:\n  persist_explorer_config_across_directories: false\n",
+    )
+    .unwrap();
+
+    let run = |app: app::App, msg| {
+        app.handle_task(app::Task::new(app::MsgIn::External(msg), None))
+            .unwrap()
+    };
+
+    let app = create_app("/tmp".into(), Some(file)).unwrap();
+    let initial = app.explorer_config().view();
+
+    // Changing to the current directory leaves it as it is for the others.
+    let root = env!("CARGO_MANIFEST_DIR");
+    let app = run(app, app::ExternalMsg::ReverseListing);
+    let app = run(app, app::ExternalMsg::ChangeDirectory(root.into()));
+    assert_eq!(app.explorer_config().view(), initial);
+
+    let app = run(app, app::ExternalMsg::TogglePersistExplorerConfig);
+    let app = run(app, app::ExternalMsg::ReverseListing);
+    let app = run(app, app::ExternalMsg::ChangeDirectory(root.into()));
+    assert!(app.explorer_config().view().reversed);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_load_view_restores_the_saved_view() {
+    let run = |app: app::App, msg| {
+        app.handle_task(app::Task::new(app::MsgIn::External(msg), None))
+            .unwrap()
+    };
+
+    let app = create_app("/tmp".into(), None).unwrap();
+    let app = run(app, app::ExternalMsg::ReverseListing);
+    let app = run(app, app::ExternalMsg::SetLayoutRatio(40));
+    let saved = run(app, app::ExternalMsg::SaveView("test view".into()));
+
+    let app = run(saved.clone(), app::ExternalMsg::ReverseListing);
+    let app = run(app, app::ExternalMsg::SetLayoutRatio(80));
+    let app = run(app, app::ExternalMsg::LoadView("test view".into()));
+    assert_eq!(app.explorer_config(), saved.explorer_config());
+    assert_eq!(app.layout_ratio(), 40);
+
+    let app = run(app, app::ExternalMsg::LoadView("no such view".into()));
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+}
+
+#[test]
+fn test_toggle_filters_enabled_keeps_the_filters() {
+    let toggle = |app: app::App| {
+        app.handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::ToggleFiltersEnabled),
+            None,
+        ))
+        .unwrap()
+    };
+
+    let hidden = app::Node::new("/tmp".into(), ".hidden".into());
+    let app = create_app("/tmp".into(), None).unwrap();
+    let filters = app.explorer_config().filters().clone();
+    assert!(!app.explorer_config().filter(&hidden));
+
+    let app = toggle(app);
+    assert!(app.explorer_config().are_filters_suspended());
+    assert!(app.explorer_config().filter(&hidden));
+
+    let app = toggle(app);
+    assert!(!app.explorer_config().filter(&hidden));
+    assert_eq!(app.explorer_config().filters(), &filters);
+}
+
+#[test]
+fn test_max_selection_refuses_the_rest() {
+    let dir = env::temp_dir().join(format!("xplr-test-max-selection-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("config.yml");
+    std::fs::write(&file, "version: v0.5.5\ngeneral:\n  max_selection: 3\n").unwrap();
+
+    let nodes = (0..10)
+        .map(|i| app::Node::new("/tmp".into(), i.to_string()))
+        .collect::<Vec<app::Node>>();
+    let buffer = app::DirectoryBuffer::new("/tmp".into(), nodes, 10, 0);
+
+    let app = create_app("/tmp".into(), Some(file))
+        .unwrap()
+        .handle_task(app::Task::new(
+            app::MsgIn::Internal(app::InternalMsg::AddDirectory("/tmp".into(), buffer)),
+            None,
+        ))
+        .unwrap()
+        .handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::SelectAll),
+            None,
+        ))
+        .unwrap();
+
+    assert_eq!(app.selection().len(), 3);
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Warning);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_select_by_filter() {
+    let by_filter = |app: app::App, select, input: &str| {
+        let filter =
+            app::NodeFilterApplicable::new(app::NodeFilter::RelativePathDoesEndWith, input.into());
+        let msg = if select {
+            app::ExternalMsg::SelectByFilter(filter)
+        } else {
+            app::ExternalMsg::UnSelectByFilter(filter)
+        };
+        app.handle_task(app::Task::new(app::MsgIn::External(msg), None))
+            .unwrap()
+    };
+
+    let app = by_filter(app_with_focus_at(0), true, "1");
+    assert_eq!(app.selection().len(), 1);
+
+    let app = by_filter(app, true, "2");
+    assert_eq!(app.selection().len(), 2);
+    assert_eq!(app.directory_buffer().unwrap().nodes.len(), 10);
+
+    let app = by_filter(app, false, "1");
+    assert_eq!(app.selection().len(), 1);
+    assert_eq!(app.selection()[0].relative_path, "2");
+}
+
+#[test]
+fn test_enter_or_open() {
+    // The directory is a link to the current one, so that entering it leaves
+    // the current directory as it is.
+    let dir = env::temp_dir().join(format!("xplr-test-enter-or-open-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::os::unix::fs::symlink(env!("CARGO_MANIFEST_DIR"), dir.join("d")).unwrap();
+    std::fs::write(dir.join("f"), "").unwrap();
+    std::os::unix::fs::symlink(dir.join("missing"), dir.join("l")).unwrap();
+
+    let parent = dir.to_string_lossy().to_string();
+    let at = |focus| {
+        let nodes = ["d", "f", "l"]
+            .iter()
+            .map(|n| app::Node::new(parent.clone(), n.to_string()))
+            .collect::<Vec<app::Node>>();
+        let buffer = app::DirectoryBuffer::new(parent.clone(), nodes, 3, focus);
+        let mut app = create_app(dir.clone(), None)
+            .unwrap()
+            .handle_task(app::Task::new(
+                app::MsgIn::Internal(app::InternalMsg::AddDirectory(parent.clone(), buffer)),
+                None,
+            ))
+            .unwrap();
+        while app.pop_msg_out().is_some() {}
+        app.handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::EnterOrOpen),
+            None,
+        ))
+        .unwrap()
+    };
+
+    assert_eq!(at(0).pwd(), &format!("{}/d", parent));
+
+    let mut opened = at(1);
+    assert_eq!(
+        opened.pop_msg_out(),
+        Some(app::MsgOut::Enque(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::OpenWithTypeCommand),
+            None
+        )))
+    );
+
+    let broken = at(2);
+    assert_eq!(broken.pwd(), &parent);
+    assert_eq!(broken.logs().last().unwrap().level, app::LogLevel::Error);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_transfer_conflict_strategies() {
+    let dir = env::temp_dir().join(format!(
+        "xplr-test-transfer-conflict-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::create_dir_all(dir.join("dst")).unwrap();
+    std::fs::write(dir.join("src").join("a.txt"), "").unwrap();
+    std::fs::write(dir.join("dst").join("a.txt"), "").unwrap();
+
+    let from = dir.join("src").join("a.txt").to_string_lossy().to_string();
+    let to = dir.join("dst").join("a.txt").to_string_lossy().to_string();
+    let copy = |conflict| {
+        let mut app = create_app(dir.join("dst"), None)
+            .unwrap()
+            .handle_task(app::Task::new(
+                app::MsgIn::External(app::ExternalMsg::SelectPath(from.clone())),
+                None,
+            ))
+            .unwrap()
+            .handle_task(app::Task::new(
+                app::MsgIn::External(app::ExternalMsg::CopySelectionHereOnConflict(conflict)),
+                None,
+            ))
+            .unwrap();
+        let transfer = std::iter::from_fn(|| app.pop_msg_out()).find_map(|msg| match msg {
+            app::MsgOut::Transfer(transfer) => Some(transfer),
+            _ => None,
+        });
+        (app, transfer)
+    };
+
+    let (_, transfer) = copy(config::PasteConflict::Rename);
+    let renamed = dir
+        .join("dst")
+        .join("a (1).txt")
+        .to_string_lossy()
+        .to_string();
+    assert_eq!(transfer.unwrap().items, vec![(from.clone(), renamed)]);
+
+    let (_, skipped) = copy(config::PasteConflict::Skip);
+    assert_eq!(skipped, None);
+
+    let (asked, transfer) = copy(config::PasteConflict::Ask);
+    assert_eq!(transfer, None);
+    assert_eq!(
+        asked.picker().unwrap().title,
+        format!("{} already exists", to)
+    );
+
+    let mut overwritten = asked
+        .handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::ResolveTransferConflict(
+                config::PasteConflict::Overwrite,
+            )),
+            None,
+        ))
+        .unwrap();
+    let transfer = std::iter::from_fn(|| overwritten.pop_msg_out())
+        .find_map(|msg| match msg {
+            app::MsgOut::Transfer(transfer) => Some(transfer),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(transfer.items, vec![(from, to.clone())]);
+    assert!(transfer.replace.contains(&to));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_relative_path_filter_anchored_to_base() {
+    let nodes = ["src/a.rs", "src/b.rs", "tests/src/c.rs"]
+        .iter()
+        .map(|p| app::Node::new("/tmp/xplr-base".into(), p.to_string()))
+        .collect::<Vec<app::Node>>();
+    let buffer = app::DirectoryBuffer::new("/tmp".into(), nodes, 3, 0);
+
+    let select = |filter: app::NodeFilterApplicable| {
+        create_app("/tmp".into(), None)
+            .unwrap()
+            .handle_task(app::Task::new(
+                app::MsgIn::Internal(app::InternalMsg::AddDirectory(
+                    "/tmp".into(),
+                    buffer.clone(),
+                )),
+                None,
+            ))
+            .unwrap()
+            .handle_task(app::Task::new(
+                app::MsgIn::External(app::ExternalMsg::SelectByFilter(filter)),
+                None,
+            ))
+            .unwrap()
+            .selection()
+            .len()
+    };
+
+    let filter =
+        app::NodeFilterApplicable::new(app::NodeFilter::RelativePathDoesStartWith, "src/".into());
+    assert_eq!(select(filter.clone()), 2);
+    assert_eq!(
+        select(filter.clone().with_base("/tmp/xplr-base/tests".into())),
+        1
+    );
+    assert_eq!(select(filter.with_base("/elsewhere".into())), 0);
+}
+
+#[test]
+fn test_type_ahead_focuses_by_prefix() {
+    let press = |app: app::App, key| {
+        app.handle_task(app::Task::new(
+            app::MsgIn::Internal(app::InternalMsg::HandleKey(key)),
+            None,
+        ))
+        .unwrap()
+    };
+
+    let nodes = ["Apple", "apricot", "banana", "berry"]
+        .iter()
+        .map(|n| app::Node::new("/tmp".into(), n.to_string()))
+        .collect::<Vec<app::Node>>();
+    let buffer = app::DirectoryBuffer::new("/tmp".into(), nodes, 4, 0);
+    let app = create_app("/tmp".into(), None)
+        .unwrap()
+        .handle_task(app::Task::new(
+            app::MsgIn::Internal(app::InternalMsg::AddDirectory("/tmp".into(), buffer)),
+            None,
+        ))
+        .unwrap()
+        .handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::EnterTypeAheadMode),
+            None,
+        ))
+        .unwrap();
+    let focus = |app: &app::App| app.directory_buffer().unwrap().focus;
+
+    let app = press(app, input::Key::B);
+    assert_eq!(focus(&app), 2);
+    let app = press(app, input::Key::E);
+    assert_eq!((app.type_ahead(), focus(&app)), (Some("be"), 3));
+
+    // Nothing matches, the focus stays.
+    let app = press(app, input::Key::Z);
+    assert_eq!(focus(&app), 3);
+
+    let app = (0..3).fold(app, |app, _| press(app, input::Key::Backspace));
+    let app = press(app, input::Key::ShiftA);
+    assert_eq!((app.type_ahead(), focus(&app)), (Some("A"), 0));
+    let app = press(app, input::Key::P);
+    assert_eq!(focus(&app), 0);
+    let app = press(app, input::Key::R);
+    assert_eq!(focus(&app), 1);
+
+    let app = press(app, input::Key::Esc);
+    assert_eq!(app.type_ahead(), None);
+    assert_eq!(focus(&app), 1);
+}
+
+#[test]
+fn test_jump_to_breadcrumb() {
+    // Jumping to the parent changes to the current directory, left as it is.
+    let dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let leaf = dir.join("src");
+
+    let jump = |index| {
+        create_app(leaf.clone(), None)
+            .unwrap()
+            .handle_task(app::Task::new(
+                app::MsgIn::External(app::ExternalMsg::JumpToBreadcrumb(index)),
+                None,
+            ))
+            .unwrap()
+    };
+
+    let crumbs = app::breadcrumb(&leaf.to_string_lossy());
+    assert_eq!(crumbs[0], ("/".to_string(), "/".to_string()));
+    assert_eq!(crumbs.last().unwrap().0, "src");
+
+    let parent = crumbs.len() - 2;
+    assert_eq!(jump(parent).pwd(), &dir.to_string_lossy().to_string());
+
+    let app = jump(crumbs.len());
+    assert_eq!(app.pwd(), &leaf.to_string_lossy().to_string());
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+}
+
+#[test]
+fn test_switch_and_cycle_layouts() {
+    let send = |app: app::App, msg| {
+        app.handle_task(app::Task::new(app::MsgIn::External(msg), None))
+            .unwrap()
+    };
+
+    let app = create_app("/tmp".into(), None).unwrap();
+    assert_eq!(app.layout_name(), "default");
+
+    let app = send(
+        app,
+        app::ExternalMsg::SwitchLayout("listing_and_preview".into()),
+    );
+    assert!(app.preview_children());
+    assert_eq!(app.layout().help_menu, Some(false));
+
+    let app = send(app, app::ExternalMsg::SwitchLayout("missing".into()));
+    assert_eq!(app.layout_name(), "listing_and_preview");
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+
+    let app = send(app, app::ExternalMsg::CycleLayout);
+    assert_eq!(app.layout_name(), "listing_only");
+    let app = send(app, app::ExternalMsg::CycleLayout);
+    assert_eq!(app.layout_name(), "default");
+}
+
+#[test]
+fn test_center_focus_scrolls_without_moving_the_focus() {
+    let area = app::TableArea {
+        x: 0,
+        y: 0,
+        width: 10,
+        height: 4,
+    };
+    let scroll = |focus, msg| {
+        let app = app_with_focus_at(focus)
+            .with_table_area(area)
+            .handle_task(app::Task::new(app::MsgIn::External(msg), None))
+            .unwrap();
+        let dir = app.directory_buffer().unwrap();
+        (dir.focus, dir.first_visible(4))
+    };
+
+    assert_eq!(scroll(5, app::ExternalMsg::CenterFocus), (5, 3));
+    assert_eq!(scroll(5, app::ExternalMsg::FocusToTop), (5, 5));
+    assert_eq!(scroll(5, app::ExternalMsg::FocusToBottom), (5, 2));
+
+    // Clamped at the ends of the listing.
+    assert_eq!(scroll(1, app::ExternalMsg::CenterFocus), (1, 0));
+    assert_eq!(scroll(9, app::ExternalMsg::FocusToTop), (9, 6));
+
+    // Paged again once the focus leaves the view.
+    let mut dir = app_with_focus_at(5).directory_buffer().unwrap().clone();
+    dir.offset = Some(3);
+    dir.focus = 8;
+    assert_eq!(dir.first_visible(4), 8);
+}
+
+#[test]
+fn test_key_sequence_waits_for_the_next_key() {
+    let dir = env::temp_dir().join(format!("xplr-test-key-sequence-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("config.yml");
+    std::fs::write(
+        &file,
+        "version: v0.5.5
+modes:
+  builtin:
+    default:
+      key_bindings:
+        on_key:
+          g g:
+            messages:
+              - LogInfo: top
+",
+    )
+    .unwrap();
+
+    let key = |k| app::MsgIn::Internal(app::InternalMsg::HandleKey(k));
+    let resolve = |n| app::MsgIn::Internal(app::InternalMsg::ResolveKeySequence(n));
+    let create = || {
+        let mut app = create_app("/tmp".into(), Some(file.clone())).unwrap();
+        while app.pop_msg_out().is_some() {}
+        app
+    };
+    let logged = |app: &app::App| app.logs().iter().any(|l| l.message == "top");
+
+    // The whole sequence runs its own binding.
+    let app = run_tasks(create(), vec![key(input::Key::G), key(input::Key::G)]);
+    assert!(logged(&app));
+    assert_eq!(app.mode().name, "default");
+
+    // A pause runs the binding of the prefix, but only the latest one.
+    let app = run_tasks(create(), vec![key(input::Key::G)]);
+    assert_eq!(app.mode().name, "default");
+    let app = run_tasks(app, vec![resolve(0)]);
+    assert_eq!(app.mode().name, "default");
+    let app = run_tasks(app, vec![resolve(1)]);
+    assert_eq!(app.mode().name, "go to");
+    assert!(!logged(&app));
+
+    // A key not continuing the sequence starts over after the prefix runs.
+    let app = run_tasks(create(), vec![key(input::Key::G), key(input::Key::F)]);
+    assert!(!logged(&app));
+    assert_eq!(app.mode().name, "default");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_name_not_in_filter() {
+    let dir = env::temp_dir().join(format!("xplr-test-name-not-in-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("backup")).unwrap();
+    std::fs::write(dir.join("backup").join("a"), "").unwrap();
+
+    let with_filter = |reference: &str| {
+        create_app("/tmp".into(), None)
+            .unwrap()
+            .handle_task(app::Task::new(
+                app::MsgIn::External(app::ExternalMsg::AddNodeFilter(
+                    app::NodeFilterApplicable::new(app::NodeFilter::NameNotIn, reference.into()),
+                )),
+                None,
+            ))
+            .unwrap()
+    };
+    let node = |name: &str| app::Node::new("/tmp".into(), name.into());
+
+    let app = with_filter(&dir.join("backup").to_string_lossy());
+    assert!(!app.explorer_config().filter(&node("a")));
+    assert!(app.explorer_config().filter(&node("b")));
+
+    // The names are listed once, when the filter is added.
+    std::fs::write(dir.join("backup").join("b"), "").unwrap();
+    assert!(app.explorer_config().filter(&node("b")));
+
+    let app = with_filter(&dir.join("missing").to_string_lossy());
+    assert!(!app.explorer_config().filter(&node("b")));
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_reload_config_keeps_the_config_on_error() {
+    let dir = std::env::temp_dir().join(format!("xplr-test-reload-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("config.yml");
+    std::fs::write(&file, "version: v0.5.5\ngeneral:\n  max_logs: 20\n").unwrap();
+
+    let app = create_app("/tmp".into(), Some(file.clone())).unwrap();
+    let reload = || app::MsgIn::External(app::ExternalMsg::ReloadConfig);
+
+    std::fs::write(&file, "version: v0.5.5\ngeneral: [\n").unwrap();
+    let app = run_tasks(app, vec![reload()]);
+    let log = app
+        .logs()
+        .iter()
+        .find(|l| l.message.starts_with("Cannot reload the config"))
+        .unwrap();
+    assert_eq!(log.level, app::LogLevel::Error);
+    assert_eq!(app.config().general.max_logs, Some(20));
+
+    std::fs::write(&file, "version: v0.5.5\ngeneral:\n  max_logs: 30\n").unwrap();
+    let app = run_tasks(app, vec![reload()]);
+    assert_eq!(app.config().general.max_logs, Some(30));
+    assert_eq!(app.mode().name, "default");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_show_errors_lists_only_the_errors() {
+    let external = |m| app::MsgIn::External(m);
+    let key = |k| app::MsgIn::Internal(app::InternalMsg::HandleKey(k));
+
+    let app = create_app("/tmp".into(), None).unwrap();
+    let app = run_tasks(
+        app,
+        vec![
+            external(app::ExternalMsg::LogError("one".into())),
+            external(app::ExternalMsg::LogInfo("two".into())),
+            external(app::ExternalMsg::LogError("three".into())),
+            external(app::ExternalMsg::ShowErrors),
+        ],
+    );
+
+    let errors = app
+        .errors()
+        .iter()
+        .map(|l| l.message.clone())
+        .filter(|m| m == "one" || m == "three")
+        .collect::<Vec<String>>();
+    assert_eq!(errors, vec!["one".to_string(), "three".to_string()]);
+    assert!(app.errors().iter().all(|l| l.level == app::LogLevel::Error));
+    assert_eq!(app.errors_shown(), Some(0));
+
+    let app = run_tasks(app, vec![key(input::Key::Down)]);
+    assert_eq!(app.errors_shown(), Some(1));
+
+    let app = run_tasks(app, vec![key(input::Key::Q)]);
+    assert_eq!(app.errors_shown(), None);
+}
+
+#[test]
+fn test_toggle_size_unit_base() {
+    let app = create_app("/tmp".into(), None).unwrap();
+    assert_eq!(app.size_unit_base(), config::SizeUnitBase::Binary);
+
+    let toggle = || app::MsgIn::External(app::ExternalMsg::ToggleSizeUnitBase);
+    let app = run_tasks(app, vec![toggle()]);
+    assert_eq!(app.size_unit_base(), config::SizeUnitBase::Si);
+
+    let app = run_tasks(app, vec![toggle()]);
+    assert_eq!(app.size_unit_base(), config::SizeUnitBase::Binary);
+}
+
+#[test]
+fn test_git_statuses() {
+    let dir = env::temp_dir().join(format!("xplr-test-git-status-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    let repo = git2::Repository::init(&dir).unwrap();
+
+    std::fs::write(dir.join("committed"), "one").unwrap();
+    std::fs::write(dir.join("sub").join("committed"), "one").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path("committed".as_ref()).unwrap();
+    index.add_path("sub/committed".as_ref()).unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = git2::Signature::now("xplr", "xplr@example.com").unwrap();
+    repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+        .unwrap();
+
+    std::fs::write(dir.join("sub").join("committed"), "two").unwrap();
+    std::fs::write(dir.join("added"), "").unwrap();
+    index.add_path("added".as_ref()).unwrap();
+    index.write().unwrap();
+    std::fs::write(dir.join("untracked"), "").unwrap();
+    std::fs::write(dir.join(".gitignore"), "ignored\n").unwrap();
+    std::fs::write(dir.join("ignored"), "").unwrap();
+
+    let root = dir.to_string_lossy().to_string();
+    let statuses = git_status::statuses(&root).unwrap();
+    let status = |name: &str| statuses.get(&format!("{}/{}", root, name)).copied();
+    assert_eq!(status("committed"), None);
+    assert_eq!(status("sub"), Some(app::GitStatus::Modified));
+    assert_eq!(status("sub/committed"), Some(app::GitStatus::Modified));
+    assert_eq!(status("added"), Some(app::GitStatus::Added));
+    assert_eq!(status("untracked"), Some(app::GitStatus::Untracked));
+    assert_eq!(status("ignored"), Some(app::GitStatus::Ignored));
+
+    // Only the directory is looked at.
+    let sub = format!("{}/sub", root);
+    let statuses = git_status::statuses(&sub).unwrap();
+    assert_eq!(
+        statuses.keys().collect::<Vec<_>>(),
+        vec![&format!("{}/committed", sub)]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(git_status::statuses("/").unwrap().is_empty());
+}
+
+#[test]
+fn test_git_stage_and_unstage() {
+    let dir = env::temp_dir().join(format!("xplr-test-git-stage-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    git2::Repository::init(&dir).unwrap();
+    std::fs::write(dir.join("file"), "").unwrap();
+    std::fs::write(dir.join("sub").join("file"), "").unwrap();
+
+    let root = dir.to_string_lossy().to_string();
+    let status = |name: &str| {
+        git_status::statuses(&root)
+            .unwrap()
+            .get(&format!("{}/{}", root, name))
+            .copied()
+    };
+
+    git_status::stage(&format!("{}/file", root)).unwrap();
+    git_status::stage(&format!("{}/sub", root)).unwrap();
+    assert_eq!(status("file"), Some(app::GitStatus::Added));
+    assert_eq!(status("sub/file"), Some(app::GitStatus::Added));
+
+    git_status::unstage(&format!("{}/file", root)).unwrap();
+    assert_eq!(status("file"), Some(app::GitStatus::Untracked));
+    assert_eq!(status("sub/file"), Some(app::GitStatus::Added));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let app = run_tasks(
+        app_with_focus_at(0),
+        vec![app::MsgIn::External(app::ExternalMsg::GitStage)],
+    );
+    let log = app
+        .logs()
+        .iter()
+        .find(|l| l.message.starts_with("Failed to stage"))
+        .unwrap();
+    assert_eq!(log.level, app::LogLevel::Error);
+    assert!(log.message.ends_with("isn't in a git repository"));
+}
+
+#[test]
+fn test_selection_registers() {
+    let external = |m| app::MsgIn::External(m);
+    let app = run_tasks(
+        app_with_focus_at(0),
+        vec![
+            external(app::ExternalMsg::AddToRegister('a')),
+            external(app::ExternalMsg::FocusNext),
+            external(app::ExternalMsg::Select),
+            external(app::ExternalMsg::AddToRegister('b')),
+            external(app::ExternalMsg::ClearSelection),
+        ],
+    );
+
+    assert!(app.selection().is_empty());
+    assert_eq!(app.registers()[&'a'].len(), 1);
+    assert_eq!(app.registers()[&'b'].len(), 1);
+    let focused = app.focused_node().unwrap().clone();
+    assert_eq!(app.registers_of(&focused), "b");
+
+    let app = run_tasks(
+        app,
+        vec![
+            external(app::ExternalMsg::SelectRegister('a')),
+            external(app::ExternalMsg::SelectRegister('b')),
+            external(app::ExternalMsg::ClearRegister('a')),
+        ],
+    );
+    assert_eq!(app.selection().len(), 2);
+    assert!(!app.registers().contains_key(&'a'));
+}
+
+#[test]
+fn test_selection_survives_reading_the_nodes_again() {
+    let app = run_tasks(
+        app_with_focus_at(0),
+        vec![app::MsgIn::External(app::ExternalMsg::ToggleSelection)],
+    );
+    assert_eq!(app.selection().len(), 1);
+
+    // The same nodes, read again after being accessed.
+    let nodes = (0..10)
+        .map(|i| {
+            let mut node = app::Node::new("/tmp".into(), i.to_string());
+            node.accessed = Some(std::time::SystemTime::now());
+            node
+        })
+        .collect::<Vec<app::Node>>();
+    let dir = app::DirectoryBuffer::new("/tmp".into(), nodes, 10, 0);
+
+    let app = run_tasks(
+        app,
+        vec![app::MsgIn::Internal(app::InternalMsg::AddDirectory(
+            "/tmp".into(),
+            dir,
+        ))],
+    );
+    assert!(app.listing()[0].is_selected);
+
+    let app = run_tasks(
+        app,
+        vec![app::MsgIn::External(app::ExternalMsg::ToggleSelection)],
+    );
+    assert!(app.selection().is_empty());
+}
+
+#[test]
+fn test_set_permissions_through_a_symlink() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = env::temp_dir().join(format!("xplr-test-chmod-link-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("target");
+    let link = dir.join("link");
+    std::fs::write(&target, "").unwrap();
+    std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o644)).unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let mode = |p: &std::path::Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o7777;
+    let op = fs_ops::FsOperation::set_permissions(&link.to_string_lossy(), "g+w").unwrap();
+    assert_eq!(mode(&target), 0o664);
+
+    op.undo().unwrap();
+    assert_eq!(mode(&target), 0o644);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_transfer_renames_apart_the_paths_of_a_batch() {
+    let dir = env::temp_dir().join(format!("xplr-test-transfer-batch-{}", std::process::id()));
+    for sub in ["one", "two", "dst"] {
+        std::fs::create_dir_all(dir.join(sub)).unwrap();
+        std::fs::write(dir.join(sub).join("a.txt"), "").unwrap();
+    }
+    let path = |sub: &str, name: &str| dir.join(sub).join(name).to_string_lossy().to_string();
+
+    let mut app = run_tasks(
+        create_app(dir.join("dst"), None).unwrap(),
+        vec![
+            app::MsgIn::External(app::ExternalMsg::SelectPath(path("one", "a.txt"))),
+            app::MsgIn::External(app::ExternalMsg::SelectPath(path("two", "a.txt"))),
+        ],
+    )
+    .handle_task(app::Task::new(
+        app::MsgIn::External(app::ExternalMsg::CopySelectionHereOnConflict(
+            config::PasteConflict::Rename,
+        )),
+        None,
+    ))
+    .unwrap();
+    let transfer = std::iter::from_fn(|| app.pop_msg_out())
+        .find_map(|msg| match msg {
+            app::MsgOut::Transfer(transfer) => Some(transfer),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(
+        transfer.items,
+        vec![
+            (path("one", "a.txt"), path("dst", "a (1).txt")),
+            (path("two", "a.txt"), path("dst", "a (2).txt")),
+        ]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_failed_overwrite_keeps_the_destination() {
+    let dir = env::temp_dir().join(format!(
+        "xplr-test-transfer-overwrite-{}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let from = dir.join("fifo");
+    let to = dir.join("kept");
+    std::process::Command::new("mkfifo")
+        .arg(&from)
+        .status()
+        .unwrap();
+    std::fs::write(&to, "kept").unwrap();
+
+    let to_str = to.to_string_lossy().to_string();
+    let transfer = app::Transfer {
+        kind: app::TransferKind::Copy,
+        items: vec![(from.to_string_lossy().to_string(), to_str.clone())],
+        replace: vec![to_str.clone()].into_iter().collect(),
+    };
+    let (tx, rx) = std::sync::mpsc::channel();
+    file_transfer::transfer(transfer, Default::default(), tx);
+    // The worker hangs up once done.
+    for _ in rx.iter() {}
+
+    assert_eq!(std::fs::read_to_string(&to).unwrap(), "kept");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+    let from = dir.join("new");
+    std::fs::write(&from, "new").unwrap();
+    let transfer = app::Transfer {
+        kind: app::TransferKind::Copy,
+        items: vec![(from.to_string_lossy().to_string(), to_str.clone())],
+        replace: vec![to_str].into_iter().collect(),
+    };
+    let (tx, rx) = std::sync::mpsc::channel();
+    file_transfer::transfer(transfer, Default::default(), tx);
+    for _ in rx.iter() {}
+
+    assert_eq!(std::fs::read_to_string(&to).unwrap(), "new");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_messages_writing_files_are_not_read_only() {
+    let writing = [
+        app::ExternalMsg::ExportListingJson("/tmp/listing.json".into()),
+        app::ExternalMsg::DumpExplorerConfig("/tmp/view.yml".into()),
+        app::ExternalMsg::DiffSelection,
+        app::ExternalMsg::DumpEffectiveConfig("/tmp/config.yml".into()),
+    ];
+    for msg in writing {
+        assert!(!msg.is_read_only(), "{:?}", msg);
+    }
+}
+
+#[test]
+fn test_tab_goes_to_the_next_tab_in_default_mode() {
+    // Switching to a tab changes to its directory, the current one here.
+    let app = run_tasks(
+        create_app(env!("CARGO_MANIFEST_DIR").into(), None).unwrap(),
+        vec![
+            app::MsgIn::External(app::ExternalMsg::NewTab),
+            app::MsgIn::External(app::ExternalMsg::SwitchMode("default".into())),
+        ],
+    );
+    assert_eq!(app.tabs().len(), 2);
+    let before = app.tab_index();
+
+    let app = run_tasks(
+        app,
+        vec![app::MsgIn::Internal(app::InternalMsg::HandleKey(
+            input::Key::Tab,
+        ))],
+    );
+    assert_eq!(app.tab_index(), (before + 1) % 2);
+}
+
+#[test]
+fn test_move_across_filesystems() {
+    // /dev/shm is a tmpfs, apart from the temp dir on most systems.
+    let shm = std::path::Path::new("/dev/shm");
+    if !shm.is_dir() {
+        return;
+    };
+    let from = shm.join(format!("xplr-test-move-across-{}", std::process::id()));
+    let to = env::temp_dir().join(format!("xplr-test-move-across-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&to);
+    std::fs::create_dir_all(from.join("sub")).unwrap();
+    std::fs::write(from.join("sub").join("file"), "content").unwrap();
+    std::os::unix::fs::symlink("sub/file", from.join("link")).unwrap();
+
+    fs_ops::move_across_filesystems(&from, &to).unwrap();
+    assert!(!from.exists());
+    assert_eq!(
+        std::fs::read_to_string(to.join("sub").join("file")).unwrap(),
+        "content"
+    );
+    assert_eq!(
+        std::fs::read_link(to.join("link")).unwrap(),
+        std::path::PathBuf::from("sub/file")
+    );
+
+    std::fs::remove_dir_all(&to).unwrap();
+}
+
+#[test]
+fn test_batch_is_undone_at_once() {
+    let dir = env::temp_dir().join(format!("xplr-test-batch-undo-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+    std::fs::write(path("a"), "").unwrap();
+    std::fs::write(path("b"), "").unwrap();
+
+    let ops = vec![
+        fs_ops::FsOperation::move_path(&path("a"), &path("c")).unwrap(),
+        fs_ops::FsOperation::move_path(&path("b"), &path("d")).unwrap(),
+    ];
+    let batch = fs_ops::FsOperation::batch(ops).unwrap();
+
+    batch.undo().unwrap();
+    assert!(dir.join("a").exists() && dir.join("b").exists());
+    assert!(!dir.join("c").exists() && !dir.join("d").exists());
+
+    batch.redo().unwrap();
+    assert!(dir.join("c").exists() && dir.join("d").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_results_of_a_cancelled_search_are_dropped() {
+    let search =
+        |pattern: &str| app::MsgIn::External(app::ExternalMsg::SearchRecursively(pattern.into()));
+    let results = |id, path: &str| {
+        app::MsgIn::Internal(app::InternalMsg::AddSearchResults(app::SearchResults {
+            id,
+            pattern: "a".into(),
+            paths: vec![path.into()],
+            done: false,
+        }))
+    };
+
+    let app = run_tasks(
+        app_with_focus_at(0),
+        vec![
+            search("a"),
+            app::MsgIn::External(app::ExternalMsg::CancelSearch),
+            search("a"),
+            results(1, "stale"),
+            results(2, "fresh"),
+        ],
+    );
+
+    let labels: Vec<_> = app
+        .picker()
+        .unwrap()
+        .items
+        .iter()
+        .map(|i| i.label.clone())
+        .collect();
+    assert_eq!(labels, vec!["fresh".to_string()]);
+}
+
+#[test]
+fn test_pasting_a_cut_path_where_it_is_leaves_it() {
+    let dir = env::temp_dir().join(format!("xplr-test-cut-in-place-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.txt"), "").unwrap();
+    let path = dir.join("a.txt").to_string_lossy().to_string();
+
+    let mut app = run_tasks(
+        create_app(dir.clone(), None).unwrap(),
+        vec![
+            app::MsgIn::External(app::ExternalMsg::SelectPath(path)),
+            app::MsgIn::External(app::ExternalMsg::CutSelection),
+        ],
+    )
+    .handle_task(app::Task::new(
+        app::MsgIn::External(app::ExternalMsg::PasteIntoCurrentOnConflict(
+            config::PasteConflict::Rename,
+        )),
+        None,
+    ))
+    .unwrap();
+
+    assert!(!std::iter::from_fn(|| app.pop_msg_out())
+        .any(|msg| matches!(msg, app::MsgOut::Transfer(_))));
+    assert!(app.paste_register().is_none());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_register_selects_the_nodes_as_listed_now() {
+    let app = run_tasks(
+        app_with_focus_at(0),
+        vec![app::MsgIn::External(app::ExternalMsg::AddToRegister('a'))],
+    );
+    assert!(app.registers()[&'a'].contains("/tmp/0"));
+
+    let accessed = std::time::SystemTime::now();
+    let nodes = (0..10)
+        .map(|i| {
+            let mut node = app::Node::new("/tmp".into(), i.to_string());
+            node.accessed = Some(accessed);
+            node
+        })
+        .collect::<Vec<app::Node>>();
+    let dir = app::DirectoryBuffer::new("/tmp".into(), nodes, 10, 0);
+
+    let app = run_tasks(
+        app,
+        vec![
+            app::MsgIn::Internal(app::InternalMsg::AddDirectory("/tmp".into(), dir)),
+            app::MsgIn::External(app::ExternalMsg::SelectRegister('a')),
+        ],
+    );
+    let selected = app.selection().iter().next().unwrap();
+    assert_eq!(selected.accessed, Some(accessed));
+}
+
+#[test]
+fn test_called_function_runs_before_the_queued_messages() {
+    let dir = env::temp_dir().join(format!("xplr-test-call-function-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("config.yml");
+    std::fs::write(
+        &file,
+        "version: v0.5.5\nfunctions:\n  last:\n    - FocusLast\n",
+    )
+    .unwrap();
+
+    let nodes = (0..10)
+        .map(|i| app::Node::new("/tmp".into(), i.to_string()))
+        .collect::<Vec<app::Node>>();
+    let buffer = app::DirectoryBuffer::new("/tmp".into(), nodes, 10, 0);
+    let app = run_tasks(
+        create_app("/tmp".into(), Some(file)).unwrap(),
+        vec![
+            app::MsgIn::Internal(app::InternalMsg::AddDirectory("/tmp".into(), buffer)),
+            app::MsgIn::External(app::ExternalMsg::CallFunction("last".into())),
+            app::MsgIn::External(app::ExternalMsg::FocusFirst),
+        ],
+    );
+    assert_eq!(app.directory_buffer().unwrap().focus, 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_debouncer_sends_the_last_task_of_a_burst() {
+    let (tx_msg_in, rx_msg_in) = std::sync::mpsc::channel();
+    let (tx_debouncer, rx_debouncer) = std::sync::mpsc::channel();
+    debouncer::keep_debouncing(tx_msg_in, rx_debouncer);
+
+    for change in 1..=3 {
+        let msg = app::MsgIn::Internal(app::InternalMsg::RunFocusChangeHook(change));
+        tx_debouncer
+            .send((
+                debouncer::Wait::FocusChange,
+                std::time::Duration::from_millis(50),
+                app::Task::new(msg, None),
+            ))
+            .unwrap();
+    }
+    drop(tx_debouncer);
+
+    let last = app::Task::new(
+        app::MsgIn::Internal(app::InternalMsg::RunFocusChangeHook(3)),
+        None,
+    );
+    assert_eq!(rx_msg_in.iter().collect::<Vec<app::Task>>(), vec![last]);
+}
+
+#[test]
+fn test_tree_view_lists_to_the_max_depth_and_stops_at_the_max_nodes() {
+    let dir = env::temp_dir().join(format!("xplr-test-tree-view-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("a").join("b").join("c")).unwrap();
+    std::fs::write(dir.join("a").join("x.txt"), "").unwrap();
+    std::fs::write(dir.join("y.txt"), "").unwrap();
+    let root = dir.to_string_lossy().to_string();
+
+    let tree = |max_nodes: usize| {
+        let file = dir.join(format!("config-{}.yml", max_nodes));
+        std::fs::write(
+            &file,
+            format!(
+                "version: v0.5.5\ngeneral:\n  tree_view:\n    max_depth: 2\n    max_nodes: {}\n",
+                max_nodes
+            ),
+        )
+        .unwrap();
+        let app = run_tasks(
+            create_app(dir.clone(), Some(file)).unwrap(),
+            vec![app::MsgIn::External(app::ExternalMsg::ToggleTreeView)],
+        );
+        let (nodes, _, cut_short) = explorer::list_tree(app.explorer_config(), &root).unwrap();
+        let mut paths: Vec<(usize, String)> = nodes
+            .into_iter()
+            .map(|n| (n.depth, n.relative_path))
+            .collect();
+        paths.sort();
+        (paths, cut_short)
+    };
+
+    let (paths, cut_short) = tree(100);
+    assert_eq!(
+        paths,
+        [
+            (0, "a".into()),
+            (0, "config-100.yml".into()),
+            (0, "y.txt".into()),
+            (1, "b".into()),
+            (1, "x.txt".into()),
+        ]
+    );
+    assert!(!cut_short);
+
+    let (paths, cut_short) = tree(2);
+    assert_eq!(paths.len(), 2);
+    assert!(cut_short);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_expand_lists_the_subtree_in_the_flat_listing() {
+    let dir = env::temp_dir().join(format!("xplr-test-expand-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("a").join("b")).unwrap();
+    std::fs::write(dir.join("y.txt"), "").unwrap();
+    let root = dir.to_string_lossy().to_string();
+
+    let nodes = ["a", "y.txt"]
+        .iter()
+        .map(|n| app::Node::new(root.clone(), n.to_string()))
+        .collect::<Vec<app::Node>>();
+    let buffer = app::DirectoryBuffer::new(root.clone(), nodes, 2, 0);
+    let app = run_tasks(
+        create_app(dir.clone(), None).unwrap(),
+        vec![
+            app::MsgIn::Internal(app::InternalMsg::AddDirectory(root.clone(), buffer)),
+            app::MsgIn::External(app::ExternalMsg::ToggleExpand),
+        ],
+    );
+    assert!(app.explorer_config().tree().is_none());
+    assert_eq!(
+        app.explorer_config().max_nodes(),
+        app::DEFAULT_TREE_MAX_NODES
+    );
+
+    let (nodes, _, _) = explorer::list_tree(app.explorer_config(), &root).unwrap();
+    let mut paths: Vec<(usize, String)> = nodes
+        .into_iter()
+        .map(|n| (n.depth, n.relative_path))
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        [(0, "a".into()), (0, "y.txt".into()), (1, "b".into())]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_nodes_of_an_expanded_subtree_keep_their_names() {
+    let dir = env::temp_dir().join(format!("xplr-test-subtree-names-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("a")).unwrap();
+    std::fs::write(dir.join("a").join("x.txt"), "").unwrap();
+    let root = dir.to_string_lossy().to_string();
+
+    let buffer = app::DirectoryBuffer::new(
+        root.clone(),
+        vec![app::Node::new(root.clone(), "a".into())],
+        1,
+        0,
+    );
+    let app = run_tasks(
+        create_app(dir.clone(), None).unwrap(),
+        vec![
+            app::MsgIn::Internal(app::InternalMsg::AddDirectory(root.clone(), buffer)),
+            app::MsgIn::External(app::ExternalMsg::ToggleExpand),
+        ],
+    );
+    let (nodes, total, _) = explorer::list_tree(app.explorer_config(), &root).unwrap();
+    let node = nodes[1].clone();
+    assert_eq!((node.depth, node.relative_path.as_str()), (1, "x.txt"));
+
+    let node_types: config::NodeTypesConfig =
+        serde_yaml::from_str("special: {x.txt: {style: {fg: Red}}}").unwrap();
+    assert_eq!(node_types.get(&node).style.fg, Some(tui::style::Color::Red));
+
+    let buffer = app::DirectoryBuffer::new(root.clone(), nodes, total, 1);
+    let mut app = [
+        app::MsgIn::Internal(app::InternalMsg::AddDirectory(root.clone(), buffer)),
+        app::MsgIn::External(app::ExternalMsg::CopyNameToClipboard),
+        app::MsgIn::External(app::ExternalMsg::ToggleSelection),
+        app::MsgIn::External(app::ExternalMsg::CopySelectedNamesToClipboard),
+        app::MsgIn::External(app::ExternalMsg::CopySelectionHere),
+    ]
+    .iter()
+    .fold(app, |app, msg| {
+        app.handle_task(app::Task::new(msg.clone(), None)).unwrap()
+    });
+
+    let out = std::iter::from_fn(|| app.pop_msg_out()).collect::<Vec<app::MsgOut>>();
+    let copied = out
+        .iter()
+        .filter_map(|msg| match msg {
+            app::MsgOut::CopyToClipboard(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<&str>>();
+    assert_eq!(copied, ["x.txt", "x.txt"]);
+
+    let transfer = out
+        .iter()
+        .find_map(|msg| match msg {
+            app::MsgOut::Transfer(transfer) => Some(transfer),
+            _ => None,
+        })
+        .unwrap();
+    assert_eq!(
+        transfer.items,
+        [(
+            node.absolute_path.clone(),
+            dir.join("x.txt").to_string_lossy().to_string()
+        )]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_preview_reads_no_more_than_the_cap() {
+    let dir = env::temp_dir().join(format!("xplr-test-preview-cap-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for i in 0..explorer::MAX_PREVIEW_NODES + 5 {
+        std::fs::write(dir.join(i.to_string()), "").unwrap();
+    }
+
+    let (nodes, unfiltered_total) =
+        explorer::list_preview(&app::ExplorerConfig::default(), &dir.to_string_lossy()).unwrap();
+    assert_eq!(nodes.len(), explorer::MAX_PREVIEW_NODES);
+    assert_eq!(unfiltered_total, explorer::MAX_PREVIEW_NODES);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_read_only_condition_follows_the_mode() {
+    let dir = env::temp_dir().join(format!("xplr-test-read-only-mode-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("config.yml");
+    std::fs::write(
+        &file,
+        "version: v0.5.5\nmodes:\n  builtin:\n    default:\n      read_only: true\n",
+    )
+    .unwrap();
+
+    let app = create_app("/tmp".into(), Some(file)).unwrap();
+    assert!(app.is_met(config::ActionCondition::ReadOnly));
+    assert!(!app.is_met(config::ActionCondition::NotReadOnly));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_styled_log_keeps_its_style() {
+    let msg: app::ExternalMsg =
+        serde_yaml::from_str("LogStyled: {text: satellite is in orbit, style: {fg: Cyan}}")
+            .unwrap();
+    let app = create_app("/tmp".into(), None)
+        .unwrap()
+        .handle_task(app::Task::new(app::MsgIn::External(msg), None))
+        .unwrap();
+
+    let log = app.logs().last().unwrap();
+    assert_eq!(log.level, app::LogLevel::Info);
+    assert_eq!(log.message, "satellite is in orbit");
+    assert_eq!(
+        log.style,
+        Some(config::Style {
+            fg: Some(tui::style::Color::Cyan),
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn test_create_directory_creates_the_missing_parents() {
+    let dir = env::temp_dir().join(format!("xplr-test-create-dir-{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).unwrap_or_default();
+    std::fs::create_dir_all(&dir).unwrap();
+    let nested = dir.join("a").join("b").to_string_lossy().to_string();
+
+    let create = |app: app::App| {
+        let msg = app::ExternalMsg::CreateDirectory(nested.clone());
+        run_tasks(app, vec![app::MsgIn::External(msg)])
+    };
+    let created = |app: &app::App| {
+        app.logs()
+            .iter()
+            .filter(|l| l.level == app::LogLevel::Success)
+            .filter(|l| l.message == format!("{} created", nested))
+            .count()
+    };
+
+    let app = create(create_app(dir.clone(), None).unwrap());
+    assert!(dir.join("a").join("b").is_dir());
+    assert_eq!(created(&app), 1);
+
+    // Like `mkdir -p`, an existing directory isn't an error.
+    let app = create(app);
+    assert_eq!(created(&app), 2);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_read_only_is_only_turned_off_by_a_key() {
+    let toggle = |app: app::App, key: Option<input::Key>| {
+        app.handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::ToggleReadOnly),
+            key,
+        ))
+        .unwrap()
+    };
+    let read_only = |app: &app::App| app.config().general.read_only.unwrap_or_default();
+
+    let app = toggle(create_app("/tmp".into(), None).unwrap(), None);
+    assert!(read_only(&app));
+
+    let app = toggle(app, None);
+    assert!(read_only(&app));
+    assert_eq!(app.logs().last().unwrap().level, app::LogLevel::Error);
+
+    let app = toggle(app, Some(input::Key::W));
+    assert!(!read_only(&app));
+}
+
+#[test]
+fn test_leaving_read_only_mode_asks_for_confirmation() {
+    let app = run_tasks(
+        create_app("/tmp".into(), None).unwrap(),
+        vec![
+            app::MsgIn::External(app::ExternalMsg::ToggleReadOnly),
+            app::MsgIn::External(app::ExternalMsg::SwitchMode("action".into())),
+            app::MsgIn::Internal(app::InternalMsg::HandleKey(input::Key::W)),
+        ],
+    );
+    assert!(app.config().general.read_only.unwrap_or_default());
+    assert!(app.confirmation().is_some());
+
+    let app = run_tasks(
+        app,
+        vec![app::MsgIn::Internal(app::InternalMsg::HandleKey(
+            input::Key::Y,
+        ))],
+    );
+    assert!(!app.config().general.read_only.unwrap_or_default());
+}
@@ -1,8 +1,100 @@
 use std::collections::HashMap;
+use std::env;
 use tui::style::Color;
 use tui::style::Modifier;
 use xplr::*;
 
+#[test]
+fn test_extend_style() {
+    let a = config::Style {
+        fg: Some(Color::Red),
+        bg: None,
+        add_modifier: Some(Modifier::BOLD),
+        sub_modifier: None,
+    };
+
+    let b = config::Style {
+        fg: None,
+        bg: Some(Color::Blue),
+        add_modifier: None,
+        sub_modifier: Some(Modifier::DIM),
+    };
+
+    let c = config::Style {
+        fg: Some(Color::Cyan),
+        bg: Some(Color::Magenta),
+        add_modifier: Some(Modifier::CROSSED_OUT),
+        sub_modifier: Some(Modifier::ITALIC),
+    };
+
+    assert_eq!(
+        a.extend(b),
+        config::Style {
+            fg: Some(Color::Red),
+            bg: Some(Color::Blue),
+            add_modifier: Some(Modifier::BOLD),
+            sub_modifier: Some(Modifier::DIM),
+        }
+    );
+
+    assert_eq!(
+        b.extend(a),
+        config::Style {
+            fg: Some(Color::Red),
+            bg: Some(Color::Blue),
+            add_modifier: Some(Modifier::BOLD),
+            sub_modifier: Some(Modifier::DIM),
+        }
+    );
+
+    assert_eq!(
+        a.extend(c),
+        config::Style {
+            fg: Some(Color::Cyan),
+            bg: Some(Color::Magenta),
+            add_modifier: Some(Modifier::CROSSED_OUT),
+            sub_modifier: Some(Modifier::ITALIC),
+        }
+    );
+
+    assert_eq!(
+        c.extend(a),
+        config::Style {
+            fg: Some(Color::Red),
+            bg: Some(Color::Magenta),
+            add_modifier: Some(Modifier::BOLD),
+            sub_modifier: Some(Modifier::ITALIC),
+        }
+    );
+}
+
+#[test]
+fn test_style_converts_to_and_from_the_tui_style() {
+    let style = config::Style {
+        fg: Some(Color::Red),
+        bg: None,
+        add_modifier: Some(Modifier::BOLD),
+        sub_modifier: None,
+    };
+
+    let tui_style: tui::style::Style = style.into();
+    if env::var("NO_COLOR").is_err() {
+        assert_eq!(tui_style.fg, Some(Color::Red));
+        assert_eq!(tui_style.add_modifier, Modifier::BOLD);
+        assert_eq!(tui_style.sub_modifier, Modifier::empty());
+    };
+
+    assert_eq!(
+        config::Style::from(tui::style::Style::default().fg(Color::Red)),
+        config::Style {
+            fg: Some(Color::Red),
+            bg: None,
+            add_modifier: Some(Modifier::empty()),
+            sub_modifier: Some(Modifier::empty()),
+        }
+    );
+}
+
 #[test]
 fn test_extend_hashmap() {
     let mut a = HashMap::new();
@@ -26,7 +118,7 @@ fn test_extend_ui_config() {
     let a = config::UiConfig {
         prefix: Some("a".to_string()),
         suffix: None,
-        style: ui::Style {
+        style: config::Style {
             fg: Some(Color::Red),
             bg: None,
             add_modifier: Some(Modifier::BOLD),
@@ -37,7 +129,7 @@ fn test_extend_ui_config() {
     let b = config::UiConfig {
         prefix: None,
         suffix: Some("b".to_string()),
-        style: ui::Style {
+        style: config::Style {
             fg: None,
             bg: Some(Color::Blue),
             add_modifier: None,
@@ -48,7 +140,7 @@ fn test_extend_ui_config() {
     let c = config::UiConfig {
         prefix: Some("cp".to_string()),
         suffix: Some("cs".to_string()),
-        style: ui::Style {
+        style: config::Style {
             fg: Some(Color::Cyan),
             bg: Some(Color::Magenta),
             add_modifier: Some(Modifier::CROSSED_OUT),
@@ -61,7 +153,7 @@ fn test_extend_ui_config() {
         config::UiConfig {
             prefix: Some("a".to_string()),
             suffix: Some("b".to_string()),
-            style: ui::Style {
+            style: config::Style {
                 fg: Some(Color::Red),
                 bg: Some(Color::Blue),
                 add_modifier: Some(Modifier::BOLD),
@@ -75,7 +167,7 @@ fn test_extend_ui_config() {
         config::UiConfig {
             prefix: Some("a".to_string()),
             suffix: Some("b".to_string()),
-            style: ui::Style {
+            style: config::Style {
                 fg: Some(Color::Red),
                 bg: Some(Color::Blue),
                 add_modifier: Some(Modifier::BOLD),
@@ -89,7 +181,7 @@ fn test_extend_ui_config() {
         config::UiConfig {
             prefix: Some("cp".to_string()),
             suffix: Some("cs".to_string()),
-            style: ui::Style {
+            style: config::Style {
                 fg: Some(Color::Cyan),
                 bg: Some(Color::Magenta),
                 add_modifier: Some(Modifier::CROSSED_OUT),
@@ -98,3 +190,357 @@ fn test_extend_ui_config() {
         }
     );
 }
+
+#[test]
+fn test_extend_action_replaces_messages() {
+    let a = config::Action {
+        help: Some("a".to_string()),
+        messages: vec![app::ExternalMsg::FocusNext],
+        append_messages: None,
+        ..Default::default()
+    };
+
+    let b = config::Action {
+        help: None,
+        messages: vec![app::ExternalMsg::FocusPrevious],
+        append_messages: None,
+        ..Default::default()
+    };
+
+    assert_eq!(
+        a.extend(b),
+        config::Action {
+            help: Some("a".to_string()),
+            messages: vec![app::ExternalMsg::FocusPrevious],
+            append_messages: None,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_extend_action_appends_messages() {
+    let a = config::Action {
+        help: Some("a".to_string()),
+        messages: vec![app::ExternalMsg::FocusNext],
+        append_messages: None,
+        ..Default::default()
+    };
+
+    let b = config::Action {
+        help: None,
+        messages: vec![app::ExternalMsg::FocusPrevious],
+        append_messages: Some(true),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        a.extend(b),
+        config::Action {
+            help: Some("a".to_string()),
+            messages: vec![app::ExternalMsg::FocusNext, app::ExternalMsg::FocusPrevious],
+            append_messages: None,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_extend_action_clears_help() {
+    let a = config::Action {
+        help: Some("a".to_string()),
+        messages: vec![app::ExternalMsg::FocusNext],
+        append_messages: None,
+        ..Default::default()
+    };
+
+    let b = config::Action {
+        help: Some("".to_string()),
+        messages: vec![app::ExternalMsg::FocusNext],
+        append_messages: None,
+        ..Default::default()
+    };
+
+    assert_eq!(a.extend(b).help, None);
+}
+
+#[test]
+fn test_deserialize_hidden_mode() {
+    let parse = |s: &str| serde_yaml::from_str::<config::HiddenMode>(s).unwrap();
+
+    assert_eq!(parse("true"), config::HiddenMode::Show);
+    assert_eq!(parse("false"), config::HiddenMode::Hide);
+    assert_eq!(parse("Show"), config::HiddenMode::Show);
+    assert_eq!(parse("Hide"), config::HiddenMode::Hide);
+    assert_eq!(parse("Only"), config::HiddenMode::Only);
+    assert!(serde_yaml::from_str::<config::HiddenMode>("Some").is_err());
+}
+
+#[test]
+fn test_node_type_icon_precedence() {
+    let config: config::NodeTypesConfig = serde_yaml::from_str(
+        r#"
+        file:
+          icon: f
+        extension:
+          rs:
+            icon: r
+          md:
+            meta:
+              icon: m
+          txt:
+            style:
+              fg: Red
+        "#,
+    )
+    .unwrap();
+
+    let icon = |name: &str| {
+        config
+            .icon(&app::Node::new("/tmp".into(), name.into()))
+            .cloned()
+    };
+
+    assert_eq!(icon("main.rs"), Some("r".into()));
+    assert_eq!(icon("README.md"), Some("m".into()));
+    assert_eq!(icon("notes.txt"), Some("f".into()));
+}
+
+#[test]
+fn test_explicit_config_file_must_exist() {
+    assert!(config::discover_config_files(Some("/no/such/xplr/config.yml".into())).is_err());
+
+    let files = config::discover_config_files(Some("Cargo.toml".into())).unwrap();
+    assert_eq!(files[0], std::path::PathBuf::from("Cargo.toml"));
+}
+
+#[test]
+fn test_effective_config_can_be_loaded_back() {
+    let config = config::Config::default();
+    let dumped = serde_yaml::to_string(&config).unwrap();
+    let loaded: config::Config = serde_yaml::from_str(&dumped).unwrap();
+    assert_eq!(serde_yaml::to_string(&loaded).unwrap(), dumped);
+}
+
+#[test]
+fn test_conditional_action_falls_through() {
+    let action: config::Action = serde_yaml::from_str(
+        r#"
+        help: open or enter
+        condition: FocusIsDirectory
+        messages:
+          - Enter
+        otherwise:
+          condition: FocusIsFile
+          messages:
+            - OpenWithTypeCommand
+        "#,
+    )
+    .unwrap();
+
+    let messages =
+        |met: config::ActionCondition| action.applicable(&|c| c == met).map(|a| a.messages.clone());
+
+    assert_eq!(
+        messages(config::ActionCondition::FocusIsDirectory),
+        Some(vec![app::ExternalMsg::Enter])
+    );
+    assert_eq!(
+        messages(config::ActionCondition::FocusIsFile),
+        Some(vec![app::ExternalMsg::OpenWithTypeCommand])
+    );
+    assert_eq!(messages(config::ActionCondition::ReadOnly), None);
+}
+
+#[test]
+fn test_read_only_sanitize_keeps_no_op() {
+    let action = |messages| config::Action {
+        help: None,
+        messages,
+        append_messages: None,
+        condition: None,
+        otherwise: None,
+    };
+
+    let no_op = action(vec![app::ExternalMsg::NoOp]);
+    assert_eq!(
+        no_op.clone().sanitized(true).map(|a| a.messages),
+        Some(no_op.messages)
+    );
+
+    assert!(action(vec![]).sanitized(true).is_none());
+    assert!(action(vec![
+        app::ExternalMsg::NoOp,
+        app::ExternalMsg::RenameFromInput
+    ])
+    .sanitized(true)
+    .is_none());
+}
+
+#[test]
+fn test_layouts_merge_by_name() {
+    let config: config::Config = serde_yaml::from_str(
+        r#"
+        version: v0.5.5
+        layouts:
+          default:
+            help_menu: false
+          mine:
+            selection: false
+        "#,
+    )
+    .unwrap();
+    let config = config.extended();
+
+    let default = config.layouts.get("default").unwrap();
+    assert_eq!(default.help_menu, Some(false));
+    assert_eq!(default.selection, Some(true));
+    assert_eq!(config.layouts.get("mine").unwrap().selection, Some(false));
+    assert!(config.layouts.contains_key("listing_only"));
+}
+
+#[test]
+fn test_key_sequences() {
+    let seq = config::KeyBindings::key_sequence;
+    assert_eq!(seq("ctrl-a"), ["ctrl-a"]);
+    assert_eq!(seq("~"), ["~"]);
+    assert_eq!(seq("gg"), ["g", "g"]);
+    assert_eq!(seq("dG"), ["d", "G"]);
+    assert_eq!(seq("ctrl-x ctrl-s"), ["ctrl-x", "ctrl-s"]);
+
+    // The default keys are all single keys.
+    let modes: HashMap<String, config::Mode> = serde_yaml::from_value(
+        serde_yaml::to_value(config::Config::default().modes.builtin).unwrap(),
+    )
+    .unwrap();
+    for mode in modes.values() {
+        for key in mode.key_bindings.on_key.keys() {
+            assert_eq!(seq(key).len(), 1, "{}", key);
+        }
+    }
+
+    let kb: config::KeyBindings = serde_yaml::from_str(
+        r#"
+        remaps:
+          zz: "g g"
+        on_key:
+          "g g":
+            messages:
+              - FocusFirst
+          "gx":
+            messages:
+              - FocusLast
+        "#,
+    )
+    .unwrap();
+    let keys = |k: &[&str]| k.iter().map(|k| k.to_string()).collect::<Vec<String>>();
+
+    assert!(kb.is_key_sequence_prefix(&keys(&["g"])));
+    assert!(kb.is_key_sequence_prefix(&keys(&["z"])));
+    assert!(!kb.is_key_sequence_prefix(&keys(&["g", "g"])));
+    assert_eq!(
+        kb.key_sequence_action(&keys(&["z", "z"])).unwrap().messages,
+        [app::ExternalMsg::FocusFirst]
+    );
+    assert_eq!(
+        kb.key_sequence_action(&keys(&["g", "x"])).unwrap().messages,
+        [app::ExternalMsg::FocusLast]
+    );
+    assert!(kb.key_sequence_action(&keys(&["x", "g"])).is_none());
+}
+
+#[test]
+fn test_config_file_locations_include_the_platform_config_dir() {
+    let locations = config::config_file_locations();
+    if let Some(dir) = dirs::config_dir() {
+        assert!(locations.contains(&dir.join("xplr").join("config.yml")));
+    };
+
+    let unique: std::collections::HashSet<_> = locations.iter().collect();
+    assert_eq!(unique.len(), locations.len());
+}
+
+#[test]
+fn test_modes_extend_the_key_bindings_of_others() {
+    let modes: config::ModesConfig = serde_yaml::from_str(
+        r#"
+        custom:
+          base:
+            key_bindings:
+              on_key:
+                esc:
+                  messages:
+                    - SwitchMode: default
+                left:
+                  messages:
+                    - InputCursorLeft
+          child:
+            extends: base
+            key_bindings:
+              on_key:
+                esc:
+                  messages:
+                    - Terminate
+          loop_a:
+            extends: loop_b
+          loop_b:
+            extends: loop_a
+        "#,
+    )
+    .unwrap();
+    let modes = config::Config::default().modes.extend(modes).resolved();
+
+    let child = &modes.get("child").unwrap().key_bindings.on_key;
+    assert_eq!(child["esc"].messages, [app::ExternalMsg::Terminate]);
+    assert_eq!(child["left"].messages, [app::ExternalMsg::InputCursorLeft]);
+    assert!(modes.get("loop_a").unwrap().key_bindings.on_key.is_empty());
+
+    for name in ["rename", "jump"] {
+        let kb = &modes.get(name).unwrap().key_bindings;
+        assert_eq!(
+            kb.on_key["alt-b"].messages,
+            [app::ExternalMsg::InputCursorWordLeft]
+        );
+        assert_eq!(
+            kb.default.as_ref().unwrap().messages,
+            [app::ExternalMsg::BufferInputFromKey]
+        );
+    }
+}
+
+#[test]
+fn test_naming_modes_use_the_focused_name_on_tab() {
+    let modes = config::Config::default().modes;
+    for name in ["create file", "create directory", "rename"] {
+        assert_eq!(
+            modes.get(name).unwrap().key_bindings.on_key["tab"].messages,
+            [app::ExternalMsg::SetInputBufferToFocusedName],
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_key_sequences_parsed_at_load_are_matched() {
+    let kb: config::KeyBindings = serde_yaml::from_str(
+        r#"
+        remaps:
+          zz: "g g"
+        on_key:
+          "g g":
+            messages:
+              - FocusFirst
+        "#,
+    )
+    .unwrap();
+    let kb = kb.with_key_sequences();
+    let keys = |k: &[&str]| k.iter().map(|k| k.to_string()).collect::<Vec<String>>();
+
+    assert!(kb.is_key_sequence_prefix(&keys(&["z"])));
+    assert_eq!(
+        kb.key_sequence_action(&keys(&["z", "z"])).unwrap().messages,
+        [app::ExternalMsg::FocusFirst]
+    );
+    assert!(kb.key_sequence_action(&keys(&["g"])).is_none());
+}
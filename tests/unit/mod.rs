@@ -1,2 +1,3 @@
+mod app;
 mod config;
 mod ui;
@@ -1,67 +1,77 @@
-use tui::style::Color;
 use tui::style::Modifier;
 use xplr::*;
 
 #[test]
-fn test_extend_style() {
-    let a = ui::Style {
-        fg: Some(Color::Red),
-        bg: None,
-        add_modifier: Some(Modifier::BOLD),
-        sub_modifier: None,
-    };
-
-    let b = ui::Style {
-        fg: None,
-        bg: Some(Color::Blue),
-        add_modifier: None,
-        sub_modifier: Some(Modifier::DIM),
-    };
-
-    let c = ui::Style {
-        fg: Some(Color::Cyan),
-        bg: Some(Color::Magenta),
-        add_modifier: Some(Modifier::CROSSED_OUT),
-        sub_modifier: Some(Modifier::ITALIC),
-    };
+fn test_markup_spans() {
+    let spans = ui::markup_spans("press *q* to _quit_, 2 * 3 \\*not bold\\*");
+    let parts = spans
+        .0
+        .iter()
+        .map(|s| (s.content.to_string(), s.style.add_modifier))
+        .collect::<Vec<(String, Modifier)>>();
 
     assert_eq!(
-        a.clone().extend(b.clone()),
-        ui::Style {
-            fg: Some(Color::Red),
-            bg: Some(Color::Blue),
-            add_modifier: Some(Modifier::BOLD),
-            sub_modifier: Some(Modifier::DIM),
-        }
+        parts,
+        [
+            ("press ".to_string(), Modifier::empty()),
+            ("q".to_string(), Modifier::BOLD),
+            (" to ".to_string(), Modifier::empty()),
+            ("quit".to_string(), Modifier::ITALIC),
+            (", 2 * 3 *not bold*".to_string(), Modifier::empty()),
+        ]
     );
+}
 
-    assert_eq!(
-        b.clone().extend(a.clone()),
-        ui::Style {
-            fg: Some(Color::Red),
-            bg: Some(Color::Blue),
-            add_modifier: Some(Modifier::BOLD),
-            sub_modifier: Some(Modifier::DIM),
-        }
-    );
+#[test]
+fn test_fit_breadcrumb_drops_the_middle() {
+    let names = ["/", "home", "user", "projects", "xplr"]
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<String>>();
 
+    assert_eq!(ui::fit_breadcrumb(&names, "/", 100), names);
     assert_eq!(
-        a.clone().extend(c.clone()),
-        ui::Style {
-            fg: Some(Color::Cyan),
-            bg: Some(Color::Magenta),
-            add_modifier: Some(Modifier::CROSSED_OUT),
-            sub_modifier: Some(Modifier::ITALIC),
-        }
+        ui::fit_breadcrumb(&names, "/", 22),
+        ["/", "…", "user", "projects", "xplr"]
     );
+    assert_eq!(ui::fit_breadcrumb(&names, "/", 1), ["/", "…", "xplr"]);
+}
+
+#[test]
+fn test_compact_table_area_drops_the_borders() {
+    let app = app::App::create_with_state_files("/tmp".into(), None, Default::default()).unwrap();
+    let size = tui::layout::Rect::new(0, 0, 100, 40);
+    let bordered = ui::table_area(size, &app);
+
+    let app = app
+        .handle_task(app::Task::new(
+            app::MsgIn::External(app::ExternalMsg::ToggleCompact),
+            None,
+        ))
+        .unwrap();
+    let compact = ui::table_area(size, &app);
+
+    assert_eq!(compact.x, 0);
+    assert_eq!(compact.width, bordered.width + 2);
+    assert!(compact.height > bordered.height + 2);
+}
 
+#[test]
+fn test_hybrid_index() {
+    assert_eq!(ui::hybrid_index(5, 5), "5");
+    assert_eq!(ui::hybrid_index(2, 5), "-3");
+    assert_eq!(ui::hybrid_index(7, 5), "+2");
+    assert_eq!(ui::hybrid_index(0, 0), "0");
+}
+
+#[test]
+fn test_human_size() {
+    assert_eq!(ui::human_size(1000, config::SizeUnitBase::Si), "1 kB");
+    assert_eq!(ui::human_size(1500, config::SizeUnitBase::Si), "1.50 kB");
+    assert_eq!(ui::human_size(2_000_000, config::SizeUnitBase::Si), "2 MB");
+    assert_eq!(ui::human_size(1024, config::SizeUnitBase::Binary), "1 KiB");
     assert_eq!(
-        c.clone().extend(a.clone()),
-        ui::Style {
-            fg: Some(Color::Red),
-            bg: Some(Color::Magenta),
-            add_modifier: Some(Modifier::BOLD),
-            sub_modifier: Some(Modifier::ITALIC),
-        }
+        ui::human_size(1024 * 1024, config::SizeUnitBase::Binary),
+        "1 MiB"
     );
 }